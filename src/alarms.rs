@@ -0,0 +1,245 @@
+//!   Copyright 2025 Isaac Schlaegel
+//!
+//!    Licensed under the Apache License, Version 2.0 (the "License");
+//!    you may not use this file except in compliance with the License.
+//!    You may obtain a copy of the License at
+//!
+//!        http://www.apache.org/licenses/LICENSE-2.0
+//!
+//!    Unless required by applicable law or agreed to in writing, software
+//!    distributed under the License is distributed on an "AS IS" BASIS,
+//!    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//!    See the License for the specific language governing permissions and
+//!    limitations under the License.
+
+use crate::{
+    enums::SelectedTopTab,
+    utils::{invalid_line, parse_address_range, parse_u16_value},
+};
+use std::path::Path;
+use strum::Display;
+use tokio::fs;
+
+#[derive(Clone, Copy, Display)]
+pub enum Comparison {
+    #[strum(to_string = ">")]
+    Above,
+    #[strum(to_string = ">=")]
+    AtLeast,
+    #[strum(to_string = "<")]
+    Below,
+    #[strum(to_string = "<=")]
+    AtMost,
+    #[strum(to_string = "==")]
+    Equal,
+    #[strum(to_string = "!=")]
+    NotEqual,
+}
+
+impl Comparison {
+    fn from_symbol(symbol: &str) -> Option<Self> {
+        match symbol {
+            ">" => Some(Comparison::Above),
+            ">=" => Some(Comparison::AtLeast),
+            "<" => Some(Comparison::Below),
+            "<=" => Some(Comparison::AtMost),
+            "=" | "==" => Some(Comparison::Equal),
+            "!=" => Some(Comparison::NotEqual),
+            _ => None,
+        }
+    }
+
+    fn holds(self, value: u16, threshold: u16) -> bool {
+        match self {
+            Comparison::Above => value > threshold,
+            Comparison::AtLeast => value >= threshold,
+            Comparison::Below => value < threshold,
+            Comparison::AtMost => value <= threshold,
+            Comparison::Equal => value == threshold,
+            Comparison::NotEqual => value != threshold,
+        }
+    }
+}
+
+pub struct Alarm {
+    pub table: SelectedTopTab,
+    pub address: u16,
+    pub comparison: Comparison,
+    pub threshold: u16,
+    pub active: bool,
+}
+
+impl Alarm {
+    pub fn condition(&self) -> String {
+        format!(
+            "{} {} {}",
+            self.table.memory_address(self.address),
+            self.comparison,
+            self.threshold
+        )
+    }
+}
+
+#[derive(Default)]
+pub struct AlarmList {
+    alarms: Vec<Alarm>,
+}
+
+impl AlarmList {
+    pub async fn from_file<P: AsRef<Path>>(filename: P) -> std::io::Result<Self> {
+        Self::parse(&fs::read_to_string(filename).await?)
+    }
+
+    // Line format: table,address[-address],comparison,threshold
+    // Addresses are 1-based, thresholds are raw values in decimal or 0x hex
+    pub fn parse(text: &str) -> std::io::Result<Self> {
+        let mut alarms = vec![];
+
+        for (index, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+            if fields.len() != 4 {
+                return Err(invalid_line(
+                    index,
+                    "Expected table,address,comparison,threshold",
+                ));
+            }
+
+            let table = SelectedTopTab::from_name(fields[0])
+                .ok_or_else(|| invalid_line(index, "Unknown table"))?;
+            let (start, end) = parse_address_range(fields[1])
+                .ok_or_else(|| invalid_line(index, "Invalid address range"))?;
+            let comparison = Comparison::from_symbol(fields[2])
+                .ok_or_else(|| invalid_line(index, "Invalid comparison"))?;
+            let threshold = parse_u16_value(fields[3])
+                .ok_or_else(|| invalid_line(index, "Invalid threshold"))?;
+
+            // A range is shorthand for one alarm per address
+            alarms.extend((start..=end).map(|address| Alarm {
+                table,
+                address,
+                comparison,
+                threshold,
+                active: false,
+            }));
+        }
+
+        Ok(Self { alarms })
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Alarm> {
+        self.alarms.iter()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.alarms.is_empty()
+    }
+
+    pub fn is_active(&self, table: SelectedTopTab, address: u16) -> bool {
+        self.alarms
+            .iter()
+            .any(|alarm| alarm.active && alarm.table == table && alarm.address == address)
+    }
+
+    // Returns the alarms that changed state, as (now active, condition)
+    pub fn evaluate(
+        &mut self,
+        table: SelectedTopTab,
+        address: u16,
+        value: u16,
+    ) -> Vec<(bool, String)> {
+        let mut changes = vec![];
+        for alarm in self
+            .alarms
+            .iter_mut()
+            .filter(|alarm| alarm.table == table && alarm.address == address)
+        {
+            let triggered = alarm.comparison.holds(value, alarm.threshold);
+            if triggered != alarm.active {
+                alarm.active = triggered;
+                changes.push((triggered, alarm.condition()));
+            }
+        }
+        changes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ranges_expand_to_one_alarm_per_address() {
+        let alarms = AlarmList::parse(
+            "# Tank levels\n\
+             holding_registers,1-3,>,100\n\
+             \n\
+             coils,5,=,1\n\
+             input_registers,10,==,0x10\n",
+        )
+        .unwrap();
+
+        let conditions: Vec<String> = alarms.iter().map(Alarm::condition).collect();
+        assert_eq!(
+            conditions,
+            [
+                "0x40001 > 100",
+                "0x40002 > 100",
+                "0x40003 > 100",
+                "0x00005 == 1",
+                "0x3000A == 16"
+            ]
+        );
+    }
+
+    #[test]
+    fn bad_lines_name_their_line_number() {
+        let error = AlarmList::parse("coils,1,=,1\n\ncoils,2,=\n")
+            .err()
+            .unwrap();
+        assert_eq!(
+            error.to_string(),
+            "Line 3: Expected table,address,comparison,threshold"
+        );
+
+        for line in [
+            "pumps,1,>,1",
+            "coils,0,>,1",
+            "coils,1,=>,1",
+            "coils,1,>,65536",
+        ] {
+            assert!(AlarmList::parse(line).is_err(), "{line}");
+        }
+    }
+
+    #[test]
+    fn only_transitions_are_reported() {
+        let mut alarms = AlarmList::parse("holding_registers,1,>=,50").unwrap();
+        let table = SelectedTopTab::HoldingRegisters;
+
+        assert!(alarms.evaluate(table, 0, 10).is_empty());
+        assert_eq!(
+            alarms.evaluate(table, 0, 50),
+            [(true, String::from("0x40001 >= 50"))]
+        );
+        assert!(alarms.evaluate(table, 0, 60).is_empty());
+        assert!(alarms.is_active(table, 0));
+        // Another address or table never touches it
+        assert!(alarms.evaluate(table, 1, 0).is_empty());
+        assert!(
+            alarms
+                .evaluate(SelectedTopTab::InputRegisters, 0, 0)
+                .is_empty()
+        );
+
+        assert_eq!(
+            alarms.evaluate(table, 0, 49),
+            [(false, String::from("0x40001 >= 50"))]
+        );
+        assert!(!alarms.is_active(table, 0));
+    }
+}
@@ -13,50 +13,113 @@
 //!    limitations under the License.
 
 use std::{
+    borrow::Cow,
+    collections::{BTreeSet, VecDeque},
     io::Write,
-    net::{IpAddr, Ipv4Addr, SocketAddr},
-    time::Duration,
+    net::{IpAddr, SocketAddr},
+    path::PathBuf,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
 };
 
+use arboard::Clipboard;
 use color_eyre::Result;
 use futures::StreamExt;
 use ratatui::{
     DefaultTerminal, Frame,
+    buffer::Buffer,
     crossterm::event::{Event, EventStream, KeyCode, KeyModifiers},
     layout::{Alignment, Constraint, Layout, Margin, Rect},
     style::{Color, Modifier, Style, Stylize},
     text::{Line, Span, Text},
     widgets::{
         Block, Borders, Cell, Clear, Paragraph, Row, Scrollbar, ScrollbarOrientation,
-        ScrollbarState, Table, TableState, Tabs, Wrap,
+        ScrollbarState, Sparkline, Table, TableState, Tabs, Widget, Wrap,
     },
 };
 use strum::IntoEnumIterator;
 use tokio::{
+    net::TcpStream,
     sync::mpsc::{self, Receiver, Sender},
     task::JoinHandle,
 };
-use tokio_modbus::client::{Reader, Writer, tcp};
+use tokio_modbus::{
+    ExceptionCode, Request, Response, Slave,
+    client::{Client, Reader, Writer, tcp},
+    slave::SlaveContext,
+};
 use tokio_util::sync::CancellationToken;
 
 use crate::{
-    app_colors::{AppColors, PALETTES},
-    app_table::AppTable,
+    alarms::AlarmList,
+    app_colors::{AppColors, ColorConfig, PALETTES},
+    app_table::{AppTable, TableCell},
+    capture::{Capture, ImportRow, Snapshot, import_file},
     enums::*,
+    keymap::{self, Command, HELP_PAGES, KeyContext},
+    labels::LabelMap,
+    latency::Latency,
     macro_parser::MagModCommandList,
+    metrics::KnownValues,
+    mqtt::MqttPublisher,
+    poll::{POLL_RESOLUTION, PollSchedule, coalesce},
+    profile::{DataType, DeviceProfile},
     queue::QueueItem,
-    utils::{ModbusReadCommand, ModbusWriteCommand, centered_rect, trim_borders},
+    recent::RecentConnections,
+    scaling::{ScalingMap, ScalingRule},
+    settings::Settings,
+    utils::{
+        BROADCAST_UNIT_ID, DEVICE_ID_FUNCTION, DEVICE_ID_MEI_TYPE, MAX_PDU_BYTES,
+        MAX_READ_WRITE_WORDS, ModbusReadCommand, ModbusReadWriteCommand, ModbusWriteCommand,
+        ascii_to_registers, bytes_to_hex, centered_rect, cursor_spans, device_object_name,
+        highlight_matches, input_line, parse_device_identification, parse_goto_address,
+        parse_raw_pdu, parse_read_range, parse_u16_value, parse_wide_value, read_back, read_tables,
+        registers_to_ascii, resolve_host, send_broadcast, trim_borders,
+    },
 };
 
 const CONNECTION_POPUP_TEXT: &str = "Please Enter an IP Address and Port";
 
-const FOOTER_TEXT: [&str; 6] = [
+const OFFLINE_POPUP_TEXT: &str = "Enter the Macro Target Address/Port"; // Same width as above
+
+const HISTORY_LENGTH: usize = 120;
+
+const ALARM_LOG_LENGTH: usize = 100;
+
+const EDIT_POPUP_MAX_LEN: usize = 10; // Fits a scaled value
+const EDIT_POPUP_BINARY_MAX_LEN: usize = 21; // 0b and 16 bits with '_' between nibbles
+const EDIT_POPUP_WIDE_MAX_LEN: usize = 11; // Fits i32::MIN
+
+const GOTO_POPUP_MAX_LEN: usize = 6; // A table's reference digit and a 5 digit address
+
+const FILL_POPUP_MAX_LEN: usize = 6;
+
+const RANGE_POPUP_MAX_LEN: usize = 11; // "65536,65535"
+
+const IMPORT_POPUP_MAX_LEN: usize = 32;
+
+const IMPORT_MAX_LISTED_LINES: usize = 10; // Bad line numbers named in the error popup
+
+const ASCII_POPUP_REGISTERS: usize = 8; // Registers shown when the ASCII popup opens
+const ASCII_POPUP_MAX_REGISTERS: usize = 32;
+
+const HELP_MENU_SCROLL_STEP: u16 = 10; // Rows moved by PageUp and PageDown in the help menu
+
+const VISUAL_BELL_DURATION: Duration = Duration::from_millis(150);
+
+// Smallest terminal the layout fits, below it only a message is drawn
+const MIN_TERMINAL_WIDTH: u16 = 60;
+const MIN_TERMINAL_HEIGHT: u16 = 21; // Header, tables, a few bottom rows and the footer
+
+const FOOTER_TEXT: [&str; 9] = [
     "(Esc) Quit | (Q) Previous Tab | (E) Next Tab | (Tab) Change Focus | (?) Help", // Main Controls
     "(W A S D) Navigate | (Space) Toggle/Edit | (Enter) Apply | (G) Go To", // Top Tab Controls
     "(← →) Select Button | (Enter) Connect/Disconnect",                     // Connection Menu
-    "(↑ ↓) Navigate | (G) Go To Address | (R) Revert Item | (M) Save Macro", // Queue Menu
-    "(Enter) - Close Popup",                                                // Error Popup
-    "Enter address (1-65535) | (Enter) Go To Address | (Esc) Cancel",       // Goto Popup
+    "(↑ ↓) Navigate | (Space) Mark | (A) Apply Marked | (B) 0x17 | (G) Go To | (R) Revert | (M) Save Macro", // Queue Menu
+    "(Enter) - Close Popup", // Error Popup
+    "Enter address (1-65536) | (Enter) Go To Address | (Esc) Cancel", // Goto Popup
+    "(↑ ↓) Navigate | (G) Go To Address | (R) Read Now | (X) Unpin", // Watch Menu
+    "Samples the selected cell while its table's tick refresh (Shift+T) is on", // History Menu
+    "Alarms from --alarms are checked on every read | (C) Clear Log", // Alarms Menu
 ];
 
 pub struct App {
@@ -72,9 +135,13 @@ pub struct App {
 
     // Networking
     connection_status: ConnectionStatus,
-    current_ip_address: Option<Ipv4Addr>,
+    current_ip_address: Option<IpAddr>,
     current_port: Option<u16>,
     selected_connection_button: SelectedConnectionButton,
+    connection_target: Option<SocketAddr>, // Drops are counted per target
+    connection_drops: usize,               // Lost connections, reset by disconnecting
+    connected_since: Option<Instant>,      // When the device answered
+    last_connection: Option<SocketAddr>,   // Kept through drops so it can be redialed
 
     // UI Focus
     app_mode: AppMode,
@@ -84,37 +151,140 @@ pub struct App {
 
     // Tables + Colors
     colors: AppColors,
+    color_config: ColorConfig,
+    theme: Theme,
+    word_order: WordOrder, // Used to split and join 32-bit values across register pairs
+    display_mode: DisplayMode, // Register values, unscaled ones only
     tables: Vec<AppTable>,
+    page_size: Option<(usize, usize)>, // Rows and columns, instead of fitting the terminal
+    scaling: ScalingMap,
+    labels: LabelMap,
+    profile: DeviceProfile,      // Data types and read-only addresses
+    poll_schedule: PollSchedule, // Per-table tick refresh rates and addresses read on their own
 
     // Queue Tab
     queue_table_data: Vec<QueueItem>,
+    queue_marked_items: BTreeSet<(usize, u16)>, // Table index, Address
     queue_table_state: TableState,
     queue_item_index: usize,
     queue_scroll_state: ScrollbarState,
+    queue_kept_items: BTreeSet<(usize, u16)>, // Written cells that stay queued afterwards
+
+    // Watch Tab
+    watch_list: Vec<(SelectedTopTab, u16)>, // Table, Address
+    watch_table_state: TableState,
+
+    // History Tab
+    history_cell: Option<(SelectedTopTab, u16)>, // Table, Address
+    history_samples: VecDeque<u64>,
+    latency: Latency,
+
+    // Alarms Tab
+    alarms: AlarmList,
+    alarm_log: VecDeque<String>, // Newest first, with MQTT failures alongside alarm changes
 
     // Connection Popup
     connecting_popup_field: ConnectingField,
+    connection_popup_offline: bool, // Enter sets the offline target instead of connecting
     address_input_cursor: usize,
     address_input: String,
     port_input_cursor: usize,
     port_input: String,
+    recent_connections: RecentConnections,
+    recent_index: Option<usize>, // History entry shown in the connection popup
 
     // Edit Popup
     edit_popup_cursor: usize,
     edit_popup_input: String,
+    edit_popup_wide: bool, // The register and the next as one 32-bit integer, toggled with Tab
+    edit_popup_base: InputBase, // Decimal whenever the register is scaled
+
+    // Bit Edit Popup
+    bit_popup_cursor: usize, // Bit index, 15 is the leftmost
+    bit_popup_value: u16,
+    bit_popup_masked: bool, // Uses mask write (0x16) instead of a full register write
+
+    // ASCII Popup
+    ascii_popup_registers: usize, // Length of the run starting at the selected register
+    ascii_popup_input: String,
 
     // Goto Popup
     goto_popup_cursor: usize,
     goto_popup_input: String,
+    goto_popup_base: InputBase,
+
+    // Goto Page Popup
+    page_popup_cursor: usize,
+    page_popup_input: String,
+
+    // Read Range Popup
+    range_popup_cursor: usize,
+    range_popup_input: String,
+
+    // Search Popup
+    search_popup_cursor: usize,
+    search_popup_input: String,
+    last_search: Option<u16>,
 
     // Macro Popup
     macro_popup_cursor: usize,
     macro_popup_input: String,
 
+    // Capture Popup
+    capture_popup_cursor: usize,
+    capture_popup_input: String,
+
+    // Fill Popup
+    fill_popup_cursor: usize,
+    fill_popup_input: String,
+
+    // Import Popup
+    import_popup_cursor: usize,
+    import_popup_input: String,
+
+    // Snapshot Popup
+    snapshot_popup_cursor: usize,
+    snapshot_popup_input: String,
+
+    // Raw PDU Popup
+    raw_popup_input: String,
+    raw_popup_response: Option<String>, // Reply to the last PDU sent from the popup
+
+    // Settings Popup
+    settings_popup_row: Setting,
+    settings_path: Option<PathBuf>, // Rewritten whenever a setting changes, None keeps them in memory
+    saved_settings: Settings,       // What the settings file holds, without this run's flags
+
+    // Clipboard, kept alive so copied text outlives the call on X11
+    clipboard: Option<Clipboard>,
+
+    // Record Mode
+    capture: Capture,
+    snapshot: Option<Snapshot>, // Live values that differ from it are highlighted
+
+    // MQTT Bridge
+    mqtt: Option<MqttPublisher>, // Every value read is published when set
+
+    // Metrics Endpoint
+    known_values: Option<KnownValues>, // Every value read is recorded for the exporter when set
+
     // Misc Statuses
-    page_refresh: bool, // Reads the page every time you change pages
-    tick_refresh: bool, // Reads the page every tick
+    verify_writes: bool, // Reads written values back after applying
+    wrap_around: bool,   // Cursor movement jumps between the first and last address
+    nudge_step: u16,     // Added or subtracted by + and -
+    recording: bool,     // Keeps every value read from the device in the capture
+    alarm_blink: bool,   // Flips every tick so active alarms flash
+    bell: Bell,
+    flash_until: Option<Instant>,   // Visual bell
+    prefetch_on_connect: bool,      // Reads the current page of every table after connecting
+    read_on_tab_switch: bool,       // Reads the current page whenever another table is selected
+    idle_timeout: Option<Duration>, // Disconnects after this long without a request
+    unit_id: u8,                    // Slave behind the target, 0 broadcasts writes to all of them
+    confirm_quit: bool,             // Asks before quitting with queued changes
     help_menu_page: u8,
+    help_menu_scroll: u16, // Rows scrolled past, clamped to the page when rendered
+    help_search_input: String, // Filters the help menu when not empty
+    help_searching: bool,
     exit: bool,
 }
 
@@ -138,6 +308,10 @@ impl App {
             current_ip_address: None,
             current_port: None,
             selected_connection_button: SelectedConnectionButton::NewConnection,
+            connection_target: None,
+            connection_drops: 0,
+            connected_since: None,
+            last_connection: None,
 
             // UI Focus
             app_mode: AppMode::Main,
@@ -147,46 +321,289 @@ impl App {
 
             // Tables + Colors
             colors: AppColors::new(&PALETTES[0]),
+            color_config: ColorConfig::default(),
+            theme: Theme::default(),
+            word_order: WordOrder::default(),
+            display_mode: DisplayMode::default(),
             tables: vec![
                 AppTable::new(sender.clone(), SelectedTopTab::Coils),
                 AppTable::new(sender.clone(), SelectedTopTab::DiscreteInputs),
                 AppTable::new(sender.clone(), SelectedTopTab::InputRegisters),
                 AppTable::new(sender.clone(), SelectedTopTab::HoldingRegisters),
             ],
+            page_size: None,
+            scaling: ScalingMap::default(),
+            labels: LabelMap::default(),
+            profile: DeviceProfile::default(),
+            poll_schedule: PollSchedule::default(),
 
             // Queue Tab
             queue_table_data: vec![],
+            queue_marked_items: BTreeSet::new(),
             queue_table_state: TableState::new(),
             queue_item_index: 0,
             queue_scroll_state: ScrollbarState::new(1),
+            queue_kept_items: BTreeSet::new(),
+
+            // Watch Tab
+            watch_list: vec![],
+            watch_table_state: TableState::new(),
+
+            // History Tab
+            history_cell: None,
+            history_samples: VecDeque::with_capacity(HISTORY_LENGTH),
+            latency: Latency::default(),
+
+            // Alarms Tab
+            alarms: AlarmList::default(),
+            alarm_log: VecDeque::with_capacity(ALARM_LOG_LENGTH),
 
             // Connection Popup
             connecting_popup_field: ConnectingField::Address,
+            connection_popup_offline: false,
             address_input: String::from(" "),
             port_input: String::from(" "),
+            recent_connections: RecentConnections::default(),
+            recent_index: None,
             address_input_cursor: 0,
             port_input_cursor: 0,
 
             // Edit Popup
             edit_popup_cursor: 0,
             edit_popup_input: String::new(),
+            edit_popup_wide: false,
+            edit_popup_base: InputBase::default(),
+
+            // Bit Edit Popup
+            bit_popup_cursor: 15,
+            bit_popup_value: 0,
+            bit_popup_masked: true,
+            ascii_popup_registers: ASCII_POPUP_REGISTERS,
+            ascii_popup_input: String::new(),
 
             // Goto Popup
             goto_popup_cursor: 0,
             goto_popup_input: String::new(),
+            goto_popup_base: InputBase::default(),
+
+            // Goto Page Popup
+            page_popup_cursor: 0,
+            page_popup_input: String::new(),
+
+            // Read Range Popup
+            range_popup_cursor: 0,
+            range_popup_input: String::new(),
+
+            // Search Popup
+            search_popup_cursor: 0,
+            search_popup_input: String::new(),
+            last_search: None,
 
             // Macro Popup
             macro_popup_cursor: 0,
             macro_popup_input: String::new(),
 
+            // Capture Popup
+            capture_popup_cursor: 0,
+            capture_popup_input: String::new(),
+
+            // Fill Popup
+            fill_popup_cursor: 0,
+            fill_popup_input: String::new(),
+
+            // Import Popup
+            import_popup_cursor: 0,
+            import_popup_input: String::new(),
+
+            // Snapshot Popup
+            snapshot_popup_cursor: 0,
+            snapshot_popup_input: String::new(),
+
+            // Raw PDU Popup
+            raw_popup_input: String::new(),
+            raw_popup_response: None,
+
+            // Settings Popup
+            settings_popup_row: Setting::default(),
+            settings_path: None,
+            saved_settings: Settings::default(),
+
+            // Clipboard
+            clipboard: None,
+
+            // Record Mode
+            capture: Capture::default(),
+            snapshot: None,
+
+            // MQTT Bridge
+            mqtt: None,
+
+            // Metrics Endpoint
+            known_values: None,
+
             // Misc Statuses
-            page_refresh: false,
-            tick_refresh: false,
+            verify_writes: false,
+            wrap_around: true,
+            nudge_step: 1,
+            recording: false,
+            alarm_blink: false,
+            bell: Bell::default(),
+            flash_until: None,
+            prefetch_on_connect: false,
+            read_on_tab_switch: false,
+            idle_timeout: None,
+            unit_id: 255, // Same as tcp::connect
+            confirm_quit: true,
             help_menu_page: 0,
+            help_menu_scroll: 0,
+            help_search_input: String::new(),
+            help_searching: false,
             exit: false,
         }
     }
 
+    pub fn with_scaling(mut self, scaling: ScalingMap) -> Self {
+        self.scaling = scaling;
+        self
+    }
+
+    pub fn with_labels(mut self, labels: LabelMap) -> Self {
+        self.labels = labels;
+        self
+    }
+
+    pub fn with_profile(mut self, profile: DeviceProfile) -> Self {
+        for table in &mut self.tables {
+            if let Some(size) = profile.address_space(table.table_type) {
+                table.set_address_space(size);
+            }
+        }
+        self.profile = profile;
+        self
+    }
+
+    // Applied before --page-size, which pins the register columns in every display mode.
+    // `saved_settings` is what the file holds, so flags given for one run are never saved
+    pub fn with_settings(
+        mut self,
+        settings: Settings,
+        saved_settings: Settings,
+        settings_path: Option<PathBuf>,
+    ) -> Self {
+        self.display_mode = settings.display_mode;
+        for table in [
+            SelectedTopTab::InputRegisters,
+            SelectedTopTab::HoldingRegisters,
+        ] {
+            self.tables[table as usize].set_columns(self.display_mode.register_columns());
+        }
+        self.verify_writes = settings.verify_writes;
+        self.settings_path = settings_path;
+        self.saved_settings = saved_settings;
+        self.with_prefetch(settings.prefetch_on_connect)
+            .with_read_on_tab_switch(settings.read_on_tab_switch)
+            .with_confirm_quit(settings.confirm_quit)
+            .with_wrap_around(settings.wrap_around)
+            .with_theme(settings.theme)
+            .with_word_order(settings.word_order)
+            .with_bell(settings.bell)
+    }
+
+    // Spawns the publisher, which keeps retrying the broker in the background
+    pub fn with_mqtt(mut self, broker: Option<(String, u16)>, prefix: String) -> Self {
+        self.mqtt = broker
+            .map(|(host, port)| MqttPublisher::spawn(host, port, prefix, self.sender.clone()));
+        self
+    }
+
+    pub fn with_metrics(mut self, known_values: Option<KnownValues>) -> Self {
+        self.known_values = known_values;
+        self
+    }
+
+    pub fn with_poll_schedule(mut self, poll_schedule: PollSchedule) -> Self {
+        self.poll_schedule = poll_schedule;
+        self
+    }
+
+    pub fn with_prefetch(mut self, prefetch_on_connect: bool) -> Self {
+        self.prefetch_on_connect = prefetch_on_connect;
+        self
+    }
+
+    pub fn with_read_on_tab_switch(mut self, read_on_tab_switch: bool) -> Self {
+        self.read_on_tab_switch = read_on_tab_switch;
+        self
+    }
+
+    pub fn with_idle_timeout(mut self, idle_timeout: Option<Duration>) -> Self {
+        self.idle_timeout = idle_timeout;
+        self
+    }
+
+    pub fn with_unit_id(mut self, unit_id: u8) -> Self {
+        self.unit_id = unit_id;
+        self
+    }
+
+    pub fn with_confirm_quit(mut self, confirm_quit: bool) -> Self {
+        self.confirm_quit = confirm_quit;
+        self
+    }
+
+    pub fn with_wrap_around(mut self, wrap_around: bool) -> Self {
+        self.wrap_around = wrap_around;
+        for table in self.tables.iter_mut() {
+            table.wrap_around = wrap_around;
+        }
+        self
+    }
+
+    pub fn with_page_size(mut self, page_size: Option<(usize, usize)>) -> Self {
+        self.page_size = page_size;
+        if let Some((rows, cols)) = page_size {
+            for table in self.tables.iter_mut() {
+                table.set_page_size(rows, cols);
+            }
+        }
+        self
+    }
+
+    pub fn with_nudge_step(mut self, nudge_step: u16) -> Self {
+        self.nudge_step = nudge_step;
+        self
+    }
+
+    pub fn with_recent_connections(mut self, recent_connections: RecentConnections) -> Self {
+        self.recent_connections = recent_connections;
+        self
+    }
+
+    pub fn with_alarms(mut self, alarms: AlarmList) -> Self {
+        self.alarms = alarms;
+        self
+    }
+
+    pub fn with_theme(mut self, theme: Theme) -> Self {
+        self.theme = theme;
+        self
+    }
+
+    pub fn with_colors(mut self, color_config: ColorConfig) -> Self {
+        self.color_config = color_config;
+        self
+    }
+
+    pub fn with_word_order(mut self, word_order: WordOrder) -> Self {
+        self.word_order = word_order;
+        self
+    }
+
+    pub fn with_bell(mut self, bell: Bell) -> Self {
+        self.bell = bell;
+        self
+    }
+
     pub async fn run(
         &mut self,
         terminal: &mut DefaultTerminal,
@@ -203,6 +620,7 @@ impl App {
         self.main_task = tokio::spawn(async move {
             let mut reader = EventStream::new();
             let mut tick_interval = tokio::time::interval(Duration::from_secs(1));
+            let mut poll_interval = tokio::time::interval(POLL_RESOLUTION);
             let mut render_interval = tokio::time::interval(Duration::from_secs_f64(1.0 / 60.0));
 
             loop {
@@ -224,6 +642,9 @@ impl App {
                     _ = tick_interval.tick() => {
                         let _ = event_sender.send(Action::Tick).await;
                     },
+                    _ = poll_interval.tick() => {
+                        let _ = event_sender.send(Action::Poll).await;
+                    },
                     _ = render_interval.tick() => {
                         let _ = event_sender.send(Action::Render).await;
                     }
@@ -241,10 +662,15 @@ impl App {
                 Some(action) => match action {
                     Action::CEvent(event) => self.on_crossterm_event(event).await?,
                     Action::Tick => {
-                        if self.tick_refresh {
-                            self.modbus_read_current_page().await;
+                        self.alarm_blink = match self.alarm_blink {
+                            true => false,
+                            false => true,
+                        };
+                        if let SelectedBottomTab::Watch = self.selected_bottom_tab {
+                            self.modbus_read_watch_list().await;
                         }
                     }
+                    Action::Poll => self.modbus_read_scheduled().await,
                     Action::Render => {
                         terminal.draw(|frame| self.render(frame))?;
                     }
@@ -256,28 +682,60 @@ impl App {
                             self.apply_modbus_updates(commands);
                         }
                     }
+                    Action::RawResponse(response) => {
+                        self.raw_popup_response = Some(response);
+                    }
+                    Action::DeviceIdentification(lines) => {
+                        let lines = match lines.is_empty() {
+                            true => vec![String::from("The device returned no objects")],
+                            false => lines,
+                        };
+                        self.app_mode = AppMode::Popup(PopupType::DeviceIdentification(lines));
+                    }
+                    Action::IllegalAddresses(ranges) => {
+                        for (table, start, count) in ranges {
+                            self.tables[table as usize].set_illegal(start, count);
+                        }
+                    }
                     Action::Connect(addr) => self.start_modbus_task(addr).await?,
+                    Action::Connected(addr) => self.on_connected(addr).await,
                     Action::ConnectionError(message) => {
+                        if self.connected_since.take().is_some() {
+                            self.connection_drops += 1;
+                        }
                         self.connection_status = ConnectionStatus::NotConnected;
                         self.current_ip_address = None;
                         self.current_port = None;
+                        self.history_samples.clear();
 
                         self.app_mode = AppMode::Popup(PopupType::Error(message));
                     }
                     Action::Disconnect => {
                         self.stop_modbus_task().await;
+                        self.connection_target = None;
+                        self.connection_drops = 0;
+                    }
+                    Action::IdleDisconnect => {
+                        // Not a drop, the link was closed on purpose
+                        self.stop_modbus_task().await;
+                        self.connection_status = ConnectionStatus::IdleDisconnected;
                     }
                     Action::Error(message) => {
                         self.app_mode = AppMode::Popup(PopupType::Error(message));
                     }
+                    Action::Log(message) => self.log(message),
                     Action::PageRefresh => {
-                        if self.page_refresh {
+                        if self.tables[self.selected_top_tab as usize].page_refresh {
                             self.modbus_read_current_page().await;
                         }
                     }
-                    Action::SuccessfulWrite => {
-                        self.table_apply_queued_cells();
+                    Action::SuccessfulWrite(commands) => {
+                        self.table_apply_queued_cells(commands);
                     }
+                    Action::Latency(round_trip) => match round_trip {
+                        Some(round_trip) => self.latency.finish(round_trip),
+                        None => self.latency.start(Instant::now()),
+                    },
                 },
                 None => {
                     break;
@@ -309,202 +767,495 @@ impl App {
         let (tx_to_task, mut rx_from_ui) = mpsc::channel::<ModbusCommandQueue>(100);
         self.modbus_sender = tx_to_task.clone();
 
-        self.connection_status = ConnectionStatus::Connected;
-        self.current_ip_address = match addr.ip() {
-            IpAddr::V4(v4) => Some(v4),
-            _ => self.current_ip_address,
-        };
+        self.connection_status = ConnectionStatus::Connecting;
+        self.current_ip_address = Some(addr.ip());
         self.current_port = Some(addr.port());
+        self.latency.clear();
+        if self.connection_target != Some(addr) {
+            self.connection_target = Some(addr);
+            self.connection_drops = 0;
+        }
 
         let ui_tx = self.sender.clone();
+        let idle_timeout = self.idle_timeout;
+        let unit_id = self.unit_id;
+        // Weak, so the task still ends once the app drops its sender
+        let requeue = tx_to_task.downgrade();
 
         self.modbus_task = Some(tokio::spawn(async move {
+            if unit_id == BROADCAST_UNIT_ID {
+                Self::broadcast_task(addr, rx_from_ui, ui_tx, idle_timeout).await;
+                return;
+            }
             let mut ctx = match tcp::connect(addr).await {
-                Ok(c) => c,
+                Ok(c) => {
+                    let _ = ui_tx.send(Action::Connected(addr)).await;
+                    c
+                }
                 Err(e) => {
                     let _ = ui_tx.send(Action::ConnectionError(e.to_string())).await;
                     return;
                 }
             };
-            while let Some(queue) = rx_from_ui.recv().await {
+            ctx.set_slave(Slave(unit_id));
+            // Tick refresh only sends requests for tables that have it on, so only those keep
+            // the connection alive
+            loop {
+                let queue = match idle_timeout {
+                    Some(idle_timeout) => {
+                        match tokio::time::timeout(idle_timeout, rx_from_ui.recv()).await {
+                            Ok(queue) => queue,
+                            Err(_) => {
+                                let _ = ctx.disconnect().await;
+                                let _ = ui_tx.send(Action::IdleDisconnect).await;
+                                break;
+                            }
+                        }
+                    }
+                    None => rx_from_ui.recv().await,
+                };
+                let Some(queue) = queue else {
+                    break;
+                };
+                let queue = match queue {
+                    // Goes to the back while anything else is waiting, so it never holds up a
+                    // read or write the user asked for
+                    ModbusCommandQueue::Prefetch(commands) if !rx_from_ui.is_empty() => {
+                        if let Some(requeue) = requeue.upgrade() {
+                            let _ = requeue.try_send(ModbusCommandQueue::Prefetch(commands));
+                        }
+                        continue;
+                    }
+                    ModbusCommandQueue::Prefetch(commands) => ModbusCommandQueue::Read(commands),
+                    queue => queue,
+                };
+                let verify = matches!(queue, ModbusCommandQueue::VerifiedWrite(_));
+                let sent = Instant::now();
+                let _ = ui_tx.send(Action::Latency(None)).await;
                 match queue {
                     ModbusCommandQueue::Read(commands) => {
-                        let mut table_commands = Vec::new();
-                        for (table, start, count) in commands {
-                            match table {
-                                SelectedTopTab::Coils => match ctx.read_coils(start, count).await {
-                                    Ok(tcp_result) => match tcp_result {
-                                        Ok(modbus_result) => {
-                                            for (i, coil) in modbus_result.into_iter().enumerate() {
-                                                table_commands.push((
-                                                    table,
-                                                    start + i as u16,
-                                                    CellType::Coil(coil),
-                                                ));
-                                            }
-                                        }
-                                        Err(modbus_err) => {
-                                            let _ = ui_tx
-                                                .send(Action::Error(format!(
-                                                    "Modbus Error: {}",
-                                                    modbus_err
-                                                )))
-                                                .await;
-                                        }
-                                    },
-                                    Err(_) => {
-                                        let _ = ui_tx
-                                            .send(Action::ConnectionError(String::from(
-                                                "Connection Was Lost",
-                                            )))
-                                            .await;
-                                    }
-                                },
-                                SelectedTopTab::DiscreteInputs => {
-                                    match ctx.read_discrete_inputs(start, count).await {
-                                        Ok(tcp_result) => match tcp_result {
-                                            Ok(modbus_result) => {
-                                                for (i, coil) in
-                                                    modbus_result.into_iter().enumerate()
-                                                {
-                                                    table_commands.push((
-                                                        table,
-                                                        start + i as u16,
-                                                        CellType::Coil(coil),
-                                                    ));
-                                                }
-                                            }
-                                            Err(modbus_err) => {
-                                                let _ = ui_tx
-                                                    .send(Action::Error(format!(
-                                                        "Modbus Error: {}",
-                                                        modbus_err
-                                                    )))
-                                                    .await;
-                                            }
-                                        },
-                                        Err(_) => {
-                                            let _ = ui_tx
-                                                .send(Action::ConnectionError(String::from(
-                                                    "Connection Was Lost",
-                                                )))
-                                                .await;
-                                        }
-                                    }
+                        match read_tables(&mut ctx, commands).await {
+                            Ok((values, exceptions, rejected)) => {
+                                let _ = ui_tx
+                                    .send(Action::FromModbus(ModbusCommandQueue::Write(values)))
+                                    .await;
+                                if !rejected.is_empty() {
+                                    let _ = ui_tx.send(Action::IllegalAddresses(rejected)).await;
                                 }
-                                SelectedTopTab::InputRegisters => {
-                                    match ctx.read_input_registers(start, count).await {
-                                        Ok(tcp_result) => match tcp_result {
-                                            Ok(modbus_result) => {
-                                                for (i, word) in
-                                                    modbus_result.into_iter().enumerate()
-                                                {
-                                                    table_commands.push((
-                                                        table,
-                                                        start + i as u16,
-                                                        CellType::Word(word),
-                                                    ));
-                                                }
-                                            }
-                                            Err(modbus_err) => {
-                                                let _ = ui_tx
-                                                    .send(Action::Error(format!(
-                                                        "Modbus Error: {}",
-                                                        modbus_err
-                                                    )))
-                                                    .await;
-                                            }
-                                        },
-                                        Err(_) => {
-                                            let _ = ui_tx
-                                                .send(Action::ConnectionError(String::from(
-                                                    "Connection Was Lost",
-                                                )))
-                                                .await;
-                                        }
+                                // Exceptions are reported, but the connection stays up
+                                if !exceptions.is_empty() {
+                                    let _ = ui_tx
+                                        .send(Action::Error(format!(
+                                            "Modbus Error: {}",
+                                            exceptions.join(" | ")
+                                        )))
+                                        .await;
+                                }
+                            }
+                            Err(_) => {
+                                let _ = ui_tx
+                                    .send(Action::ConnectionError(String::from(
+                                        "Connection Was Lost",
+                                    )))
+                                    .await;
+                            }
+                        }
+                    }
+                    ModbusCommandQueue::MaskWrite(commands) => {
+                        let mut written = vec![];
+                        for &(addr, and_mask, or_mask, value) in &commands {
+                            let result =
+                                match ctx.masked_write_register(addr, and_mask, or_mask).await {
+                                    // Device doesn't implement 0x16, write the whole register instead
+                                    Ok(Err(ExceptionCode::IllegalFunction)) => {
+                                        ctx.write_single_register(addr, value).await
                                     }
+                                    result => result,
+                                };
+                            match result {
+                                Ok(Ok(())) => written.push((
+                                    SelectedTopTab::HoldingRegisters,
+                                    addr,
+                                    CellType::Word(value),
+                                )),
+                                Ok(Err(modbus_err)) => {
+                                    let _ = ui_tx
+                                        .send(Action::Error(format!(
+                                            "Modbus Error ({}): {}",
+                                            SelectedTopTab::HoldingRegisters.memory_address(addr),
+                                            modbus_err
+                                        )))
+                                        .await;
+                                    break;
                                 }
-                                SelectedTopTab::HoldingRegisters => {
-                                    match ctx.read_holding_registers(start, count).await {
-                                        Ok(tcp_result) => match tcp_result {
-                                            Ok(modbus_result) => {
-                                                for (i, word) in
-                                                    modbus_result.into_iter().enumerate()
-                                                {
-                                                    table_commands.push((
-                                                        table,
-                                                        start + i as u16,
-                                                        CellType::Word(word),
-                                                    ));
-                                                }
-                                            }
-                                            Err(modbus_err) => {
-                                                let _ = ui_tx
-                                                    .send(Action::ConnectionError(format!(
-                                                        "Modbus Error: {}",
-                                                        modbus_err
-                                                    )))
-                                                    .await;
+                                Err(_) => {
+                                    let _ = ui_tx
+                                        .send(Action::ConnectionError(String::from(
+                                            "Connection Was Lost",
+                                        )))
+                                        .await;
+                                    break;
+                                }
+                            }
+                        }
+                        if !written.is_empty() {
+                            let _ = ui_tx.send(Action::SuccessfulWrite(written.clone())).await;
+                            // Untouched bits may have been changed by another master
+                            if let Ok(Ok(results)) = read_back(&mut ctx, &written).await {
+                                let _ = ui_tx
+                                    .send(Action::FromModbus(ModbusCommandQueue::Write(
+                                        results
+                                            .into_iter()
+                                            .map(|((table, addr, _), read)| (table, addr, read))
+                                            .collect(),
+                                    )))
+                                    .await;
+                            }
+                        }
+                    }
+                    ModbusCommandQueue::ReadWrite(commands) => {
+                        for (addr, values) in commands {
+                            // Reads back the same range the block was written to
+                            match ctx
+                                .read_write_multiple_registers(
+                                    addr,
+                                    values.len() as u16,
+                                    addr,
+                                    &values,
+                                )
+                                .await
+                            {
+                                Ok(Ok(read_values)) => {
+                                    let written = values
+                                        .into_iter()
+                                        .enumerate()
+                                        .map(|(i, value)| {
+                                            (
+                                                SelectedTopTab::HoldingRegisters,
+                                                addr + i as u16,
+                                                CellType::Word(value),
+                                            )
+                                        })
+                                        .collect();
+                                    let _ = ui_tx.send(Action::SuccessfulWrite(written)).await;
+                                    let _ = ui_tx
+                                        .send(Action::FromModbus(ModbusCommandQueue::Write(
+                                            read_values
+                                                .into_iter()
+                                                .enumerate()
+                                                .map(|(i, value)| {
+                                                    (
+                                                        SelectedTopTab::HoldingRegisters,
+                                                        addr + i as u16,
+                                                        CellType::Word(value),
+                                                    )
+                                                })
+                                                .collect(),
+                                        )))
+                                        .await;
+                                }
+                                Ok(Err(modbus_err)) => {
+                                    let _ = ui_tx
+                                        .send(Action::Error(format!(
+                                            "Modbus Error ({}): {}",
+                                            SelectedTopTab::HoldingRegisters.memory_address(addr),
+                                            modbus_err
+                                        )))
+                                        .await;
+                                    break;
+                                }
+                                Err(_) => {
+                                    let _ = ui_tx
+                                        .send(Action::ConnectionError(String::from(
+                                            "Connection Was Lost",
+                                        )))
+                                        .await;
+                                    break;
+                                }
+                            }
+                        }
+                    }
+                    ModbusCommandQueue::Raw(function, data) => {
+                        let response =
+                            match ctx.call(Request::Custom(function, Cow::Owned(data))).await {
+                                Ok(Ok(Response::Custom(function, data))) => {
+                                    Some(format!("{function:02X} {}", bytes_to_hex(&data)))
+                                }
+                                // Standard function codes come back already decoded
+                                Ok(Ok(response)) => Some(format!("{response:?}")),
+                                Ok(Err(modbus_err)) => Some(format!("Modbus Error: {modbus_err}")),
+                                Err(_) => None,
+                            };
+                        let _ = match response {
+                            Some(response) => ui_tx.send(Action::RawResponse(response)).await,
+                            None => {
+                                ui_tx
+                                    .send(Action::ConnectionError(String::from(
+                                        "Connection Was Lost",
+                                    )))
+                                    .await
+                            }
+                        };
+                    }
+                    ModbusCommandQueue::DeviceIdentification => {
+                        // Basic objects, asking again from where the device left off while
+                        // it says more follow
+                        let mut lines = vec![];
+                        let mut object_id = 0x00;
+                        let response = loop {
+                            let data = vec![DEVICE_ID_MEI_TYPE, 0x01, object_id];
+                            match ctx
+                                .call(Request::Custom(DEVICE_ID_FUNCTION, Cow::Owned(data)))
+                                .await
+                            {
+                                Ok(Ok(Response::Custom(_, data))) => {
+                                    match parse_device_identification(&data) {
+                                        Some((objects, next_object)) => {
+                                            lines.extend(objects.into_iter().map(|(id, value)| {
+                                                format!("{}: {value}", device_object_name(id))
+                                            }));
+                                            match next_object {
+                                                Some(next) if next > object_id => object_id = next,
+                                                _ => break Some(lines),
                                             }
-                                        },
-                                        Err(_) => {
-                                            let _ = ui_tx
-                                                .send(Action::ConnectionError(String::from(
-                                                    "Connection Was Lost",
-                                                )))
-                                                .await;
+                                        }
+                                        None => {
+                                            break Some(vec![String::from(
+                                                "The device sent a malformed reply",
+                                            )]);
                                         }
                                     }
                                 }
+                                Ok(Ok(_)) => {
+                                    break Some(vec![String::from(
+                                        "The device sent a malformed reply",
+                                    )]);
+                                }
+                                // Not supported, but the connection is still fine
+                                Ok(Err(modbus_err)) => {
+                                    break Some(vec![format!(
+                                        "Not supported by this device ({modbus_err})"
+                                    )]);
+                                }
+                                Err(_) => break None,
                             }
-                        }
-                        let _ = ui_tx
-                            .send(Action::FromModbus(ModbusCommandQueue::Write(
-                                table_commands,
-                            )))
-                            .await;
+                        };
+                        let _ = match response {
+                            Some(lines) => ui_tx.send(Action::DeviceIdentification(lines)).await,
+                            None => {
+                                ui_tx
+                                    .send(Action::ConnectionError(String::from(
+                                        "Connection Was Lost",
+                                    )))
+                                    .await
+                            }
+                        };
                     }
-                    ModbusCommandQueue::Write(commands) => {
+                    ModbusCommandQueue::Write(commands)
+                    | ModbusCommandQueue::VerifiedWrite(commands) => {
                         let mut was_successful = true;
-                        for command in commands {
-                            let (table, addr, content) = command;
-                            match (table, content) {
+                        for &(table, addr, content) in &commands {
+                            let result = match (table, content) {
                                 (SelectedTopTab::Coils, CellType::Coil(b)) => {
-                                    if ctx.write_single_coil(addr, b).await.is_err() {
-                                        let _ = ui_tx
-                                            .send(Action::ConnectionError(String::from(
-                                                "Connection Was Lost",
-                                            )))
-                                            .await;
-                                        was_successful = false;
-                                        break;
-                                    }
+                                    ctx.write_single_coil(addr, b).await
                                 }
                                 (SelectedTopTab::HoldingRegisters, CellType::Word(w)) => {
-                                    if ctx.write_single_register(addr, w).await.is_err() {
+                                    ctx.write_single_register(addr, w).await
+                                }
+                                _ => continue,
+                            };
+                            if result.is_err() {
+                                let _ = ui_tx
+                                    .send(Action::ConnectionError(String::from(
+                                        "Connection Was Lost",
+                                    )))
+                                    .await;
+                                was_successful = false;
+                                break;
+                            }
+                        }
+                        if was_successful {
+                            let _ = ui_tx.send(Action::SuccessfulWrite(commands.clone())).await;
+                        }
+                        if was_successful && verify {
+                            match read_back(&mut ctx, &commands).await {
+                                Ok(Ok(results)) => {
+                                    let mismatches: Vec<_> = results
+                                        .iter()
+                                        .filter(|((_, _, written), read)| written != read)
+                                        .collect();
+                                    if let Some(((table, addr, written), read)) = mismatches.first()
+                                    {
                                         let _ = ui_tx
-                                            .send(Action::ConnectionError(String::from(
-                                                "Connection Was Lost",
+                                            .send(Action::Error(format!(
+                                                "Verify Failed: {} value(s) differ, {} wrote {} read {}",
+                                                mismatches.len(),
+                                                table.memory_address(*addr),
+                                                written.to_u16(),
+                                                read.to_u16(),
                                             )))
                                             .await;
-                                        was_successful = false;
-                                        break;
                                     }
+                                    let _ = ui_tx
+                                        .send(Action::FromModbus(ModbusCommandQueue::Write(
+                                            results
+                                                .into_iter()
+                                                .map(|((table, addr, _), read)| (table, addr, read))
+                                                .collect(),
+                                        )))
+                                        .await;
+                                }
+                                Ok(Err(modbus_err)) => {
+                                    let _ = ui_tx
+                                        .send(Action::Error(format!(
+                                            "Modbus Error: {}",
+                                            modbus_err
+                                        )))
+                                        .await;
+                                }
+                                Err(_) => {
+                                    let _ = ui_tx
+                                        .send(Action::ConnectionError(String::from(
+                                            "Connection Was Lost",
+                                        )))
+                                        .await;
                                 }
-                                _ => {}
                             }
                         }
-                        if was_successful {
-                            let _ = ui_tx.send(Action::SuccessfulWrite).await;
-                        }
                     }
                 }
+                let _ = ui_tx.send(Action::Latency(Some(sent.elapsed()))).await;
             }
         }));
 
+        if self.prefetch_on_connect {
+            // Queued behind the connection, so it runs as soon as the task connects. The table
+            // on screen is read first, the others once nothing else is waiting
+            let current_page =
+                vec![self.tables[self.selected_top_tab as usize].current_page_read()];
+            let other_pages = self
+                .tables
+                .iter()
+                .filter(|table| table.table_type != self.selected_top_tab)
+                .map(|table| table.current_page_read())
+                .collect();
+            let _ = self
+                .modbus_sender
+                .send(ModbusCommandQueue::Read(current_page))
+                .await;
+            let _ = self
+                .modbus_sender
+                .send(ModbusCommandQueue::Prefetch(other_pages))
+                .await;
+        }
+
         Ok(())
     }
 
+    // Stands in for the Modbus task when the unit ID is 0. Nothing replies to a broadcast, so
+    // writes are sent without waiting and marked unverified, and reads are dropped
+    async fn broadcast_task(
+        addr: SocketAddr,
+        mut rx_from_ui: Receiver<ModbusCommandQueue>,
+        ui_tx: Sender<Action>,
+        idle_timeout: Option<Duration>,
+    ) {
+        let mut stream = match TcpStream::connect(addr).await {
+            Ok(stream) => {
+                let _ = ui_tx.send(Action::Connected(addr)).await;
+                let message = String::from("Unit 0 is a broadcast, writes can't be read back");
+                let _ = ui_tx.send(Action::Log(message)).await;
+                stream
+            }
+            Err(e) => {
+                let _ = ui_tx.send(Action::ConnectionError(e.to_string())).await;
+                return;
+            }
+        };
+        let mut transaction = 0;
+        loop {
+            let queue = match idle_timeout {
+                Some(idle_timeout) => {
+                    match tokio::time::timeout(idle_timeout, rx_from_ui.recv()).await {
+                        Ok(queue) => queue,
+                        Err(_) => {
+                            let _ = ui_tx.send(Action::IdleDisconnect).await;
+                            break;
+                        }
+                    }
+                }
+                None => rx_from_ui.recv().await,
+            };
+            let Some(queue) = queue else {
+                break;
+            };
+            let verify = matches!(queue, ModbusCommandQueue::VerifiedWrite(_));
+            let commands = match queue {
+                ModbusCommandQueue::Write(commands)
+                | ModbusCommandQueue::VerifiedWrite(commands) => commands,
+                // No reply to check for 0x16 support, so the whole register is written
+                ModbusCommandQueue::MaskWrite(commands) => commands
+                    .into_iter()
+                    .map(|(addr, _, _, value)| {
+                        (
+                            SelectedTopTab::HoldingRegisters,
+                            addr,
+                            CellType::Word(value),
+                        )
+                    })
+                    .collect(),
+                ModbusCommandQueue::Read(_) | ModbusCommandQueue::Prefetch(_) => continue,
+                _ => {
+                    let message = String::from("Broadcasts get no reply, only writes can be sent");
+                    let _ = ui_tx.send(Action::Error(message)).await;
+                    continue;
+                }
+            };
+
+            let mut sent = vec![];
+            for command in commands {
+                match send_broadcast(&mut stream, &mut transaction, &command).await {
+                    Ok(true) => sent.push(command),
+                    Ok(false) => {}
+                    Err(_) => {
+                        let _ = ui_tx
+                            .send(Action::ConnectionError(String::from("Connection Was Lost")))
+                            .await;
+                        return;
+                    }
+                }
+            }
+            if !sent.is_empty() {
+                let message = format!("{} write(s) sent (broadcast, unverified)", sent.len());
+                let _ = ui_tx.send(Action::Log(message)).await;
+                let _ = ui_tx.send(Action::SuccessfulWrite(sent)).await;
+            }
+            if verify {
+                let message = String::from("Broadcasts get no reply, so writes can't be verified");
+                let _ = ui_tx.send(Action::Error(message)).await;
+            }
+        }
+    }
+
+    // Sent by the task once tcp::connect succeeds. A task that was stopped while connecting
+    // may still get its message out, so only the current attempt counts
+    async fn on_connected(&mut self, addr: SocketAddr) {
+        if !matches!(self.connection_status, ConnectionStatus::Connecting)
+            || self.connection_target != Some(addr)
+        {
+            return;
+        }
+        self.connection_status = ConnectionStatus::Connected;
+        self.connected_since = Some(Instant::now());
+        self.last_connection = Some(addr);
+        self.recent_connections.push(addr);
+        if let Err(err) = self.recent_connections.save().await {
+            self.app_mode = AppMode::Popup(PopupType::Error(format!(
+                "Couldn't Save Recent Connections: {err}"
+            )));
+        }
+    }
+
     async fn stop_modbus_task(&mut self) {
         if let Some(handle) = self.modbus_task.take() {
             handle.abort();
@@ -516,209 +1267,533 @@ impl App {
         self.connection_status = ConnectionStatus::NotConnected;
         self.current_ip_address = None;
         self.current_port = None;
+        self.connected_since = None;
+        self.history_samples.clear();
     }
 
     async fn on_crossterm_event(&mut self, event: Event) -> Result<()> {
         if let Event::Key(key) = event {
             if key.kind.is_press() {
+                // Raw mode turns Ctrl+C into a key press instead of a signal, so it is handled
+                // here for every mode. A second press skips the queued changes prompt
+                if key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL) {
+                    match self.app_mode {
+                        AppMode::Popup(PopupType::ConfirmQuit) => self.exit = true,
+                        _ => self.request_exit(),
+                    }
+                    return Ok(());
+                }
+
                 let shift_pressed = key.modifiers.contains(KeyModifiers::SHIFT);
                 match &self.app_mode {
                     AppMode::Main => {
-                        match self.current_focus {
-                            CurrentFocus::Top => {
-                                match key.code {
-                                    KeyCode::Esc => self.exit = true,
-                                    KeyCode::Tab => self.current_focus = CurrentFocus::Bottom,
-                                    KeyCode::Char('q') => self.previous_top_tab(),
-                                    KeyCode::Char('e') => self.next_top_tab(),
-                                    KeyCode::Up | KeyCode::Char('w') if shift_pressed => {
-                                        self.table_page_up().await
-                                    }
-                                    KeyCode::Up | KeyCode::Char('w') => self.table_move_up().await,
-                                    KeyCode::Down | KeyCode::Char('s') if shift_pressed => {
-                                        self.table_page_down().await
-                                    }
-                                    KeyCode::Down | KeyCode::Char('s') => {
-                                        self.table_move_down().await
-                                    }
-                                    KeyCode::Left | KeyCode::Char('a') => self.table_move_left(),
-                                    KeyCode::Right | KeyCode::Char('d') => self.table_move_right(),
-                                    KeyCode::Char('r') => {
-                                        // Read the values that are currently on the screen
-                                        if let ConnectionStatus::Connected = self.connection_status
-                                        {
-                                            self.modbus_read_current_page().await;
-                                        } else {
-                                            let _ = self
-                                                .sender
-                                                .send(Action::Error(String::from(
-                                                    "Connect to a server first.",
-                                                )))
-                                                .await;
-                                        }
-                                    }
-                                    KeyCode::Char('R') => {
-                                        self.page_refresh = match self.page_refresh {
-                                            true => false,
-                                            false => true,
-                                        }
-                                    }
-                                    KeyCode::Char('T') => {
-                                        self.tick_refresh = match self.tick_refresh {
-                                            true => false,
-                                            false => true,
-                                        }
-                                    }
-                                    KeyCode::Char('u') => {
-                                        if let ConnectionStatus::Connected = self.connection_status
-                                        {
-                                            self.table_revert_current_cell();
-                                        }
-                                    }
-                                    KeyCode::Char('g') => {
-                                        self.app_mode = AppMode::Popup(PopupType::Goto);
+                        let context = match self.current_focus {
+                            CurrentFocus::Top => KeyContext::Table,
+                            CurrentFocus::Bottom => {
+                                KeyContext::from_bottom_tab(self.selected_bottom_tab)
+                            }
+                        };
+                        match keymap::command(context, key.code, shift_pressed) {
+                            // General
+                            Some(Command::Quit) => self.request_exit(),
+                            Some(Command::SwitchFocus) => {
+                                self.current_focus = match self.current_focus {
+                                    CurrentFocus::Top => CurrentFocus::Bottom,
+                                    CurrentFocus::Bottom => CurrentFocus::Top,
+                                }
+                            }
+                            Some(Command::ToggleHelp) => self.app_mode = AppMode::Help,
+                            Some(Command::OpenSettings) => {
+                                self.app_mode = AppMode::Popup(PopupType::Settings)
+                            }
+                            Some(Command::PreviousTab) => match self.current_focus {
+                                CurrentFocus::Top => self.previous_top_tab().await,
+                                CurrentFocus::Bottom => self.previous_bottom_tab(),
+                            },
+                            Some(Command::NextTab) => match self.current_focus {
+                                CurrentFocus::Top => self.next_top_tab().await,
+                                CurrentFocus::Bottom => self.next_bottom_tab(),
+                            },
+                            Some(Command::JumpToTable) => {
+                                // The key is the table's Modbus reference digit
+                                let table = match key.code {
+                                    KeyCode::Char(c) => c
+                                        .to_digit(10)
+                                        .and_then(SelectedTopTab::from_reference_digit),
+                                    _ => None,
+                                };
+                                if let Some(table) = table {
+                                    self.select_top_tab(table).await;
+                                }
+                            }
+
+                            // Table
+                            Some(Command::PageUp) => self.table_page_up().await,
+                            Some(Command::PageDown) => self.table_page_down().await,
+                            Some(Command::MoveUp) => self.table_move_up().await,
+                            Some(Command::MoveDown) => self.table_move_down().await,
+                            Some(Command::MoveLeft) => self.table_move_left(),
+                            Some(Command::MoveRight) => self.table_move_right(),
+                            Some(Command::FirstAddress) => self.table_go_to_first().await,
+                            Some(Command::LastAddress) => self.table_go_to_last().await,
+                            Some(Command::ReadPage) => {
+                                // Read the values that are currently on the screen
+                                if let ConnectionStatus::Connected = self.connection_status {
+                                    self.modbus_read_current_page().await;
+                                } else {
+                                    let _ = self
+                                        .sender
+                                        .send(Action::Error(String::from(
+                                            "Connect to a server first.",
+                                        )))
+                                        .await;
+                                }
+                            }
+                            Some(Command::ReadRange) => {
+                                if let ConnectionStatus::Connected = self.connection_status {
+                                    self.app_mode = AppMode::Popup(PopupType::ReadRange);
+                                } else {
+                                    let _ = self
+                                        .sender
+                                        .send(Action::Error(String::from(
+                                            "Connect to a server first.",
+                                        )))
+                                        .await;
+                                }
+                            }
+                            Some(Command::TogglePageRefresh) => {
+                                let table = &mut self.tables[self.selected_top_tab as usize];
+                                table.page_refresh = match table.page_refresh {
+                                    true => false,
+                                    false => true,
+                                }
+                            }
+                            Some(Command::ToggleTickRefresh) => {
+                                let table = &mut self.tables[self.selected_top_tab as usize];
+                                table.tick_refresh = match table.tick_refresh {
+                                    true => false,
+                                    false => true,
+                                }
+                            }
+                            Some(Command::ToggleVerify) => {
+                                self.change_setting(Setting::VerifyWrites).await
+                            }
+                            Some(Command::ToggleRecording) => self.toggle_recording(),
+                            Some(Command::CompareSnapshot) => match self.snapshot {
+                                Some(_) => self.snapshot = None,
+                                None => self.app_mode = AppMode::Popup(PopupType::LoadSnapshot),
+                            },
+                            Some(Command::SendRawPdu) => {
+                                if let ConnectionStatus::Connected = self.connection_status {
+                                    self.app_mode = AppMode::Popup(PopupType::RawPdu);
+                                } else {
+                                    let _ = self
+                                        .sender
+                                        .send(Action::Error(String::from(
+                                            "Connect to a server first.",
+                                        )))
+                                        .await;
+                                }
+                            }
+                            Some(Command::ToggleWrapAround) => {
+                                self.change_setting(Setting::WrapAround).await
+                            }
+                            Some(Command::CycleTheme) => self.change_setting(Setting::Theme).await,
+                            Some(Command::CycleWordOrder) => {
+                                self.change_setting(Setting::WordOrder).await
+                            }
+                            Some(Command::CycleDisplayMode) => {
+                                self.change_setting(Setting::DisplayMode).await
+                            }
+                            Some(Command::RevertCell) => {
+                                if self.can_queue() {
+                                    self.table_revert_current_cell();
+                                }
+                            }
+                            Some(Command::RevertPage) => self.table_revert_current_page()?,
+                            Some(Command::Increment) => self.table_nudge_current_cell(true).await?,
+                            Some(Command::Decrement) => {
+                                self.table_nudge_current_cell(false).await?
+                            }
+                            Some(Command::SelectRange) => {
+                                let table = &mut self.tables[self.selected_top_tab as usize];
+                                match (self.selected_top_tab, table.selection_anchor) {
+                                    (SelectedTopTab::Coils, None) => {
+                                        table.selection_anchor = Some(table.table_address)
                                     }
-                                    KeyCode::Enter => {
-                                        if let ConnectionStatus::Connected = self.connection_status
-                                        {
-                                            self.modbus_apply_queued().await;
-                                        } else {
-                                            let _ = self
-                                                .sender
-                                                .send(Action::Error(String::from(
-                                                    "Connect to a server first.",
-                                                )))
-                                                .await;
-                                        }
+                                    (SelectedTopTab::Coils, Some(_)) => {
+                                        table.selection_anchor = None
                                     }
-                                    KeyCode::Char(' ') => {
-                                        if let ConnectionStatus::Connected = self.connection_status
-                                        {
-                                            match self.selected_top_tab {
-                                                SelectedTopTab::Coils => {
-                                                    self.table_toggle_current_cell()
-                                                }
-                                                SelectedTopTab::HoldingRegisters => {
-                                                    self.app_mode = AppMode::Popup(PopupType::Edit)
-                                                }
-                                                _ => {}
-                                            }
-                                        } else {
-                                            let _ = self
-                                                .sender
-                                                .send(Action::Error(String::from(
-                                                    "Connect to a server first.",
-                                                )))
-                                                .await;
-                                        }
+                                    _ => self.beep()?,
+                                }
+                            }
+                            Some(Command::FillPage) => {
+                                if self.can_queue() {
+                                    let (start, end) =
+                                        self.tables[self.selected_top_tab as usize].page_range();
+                                    match self.read_only_reason(start, end) {
+                                        Some(reason) => self.refuse_write(reason).await?,
+                                        None => self.app_mode = AppMode::Popup(PopupType::Fill),
                                     }
-                                    KeyCode::Char('?') => self.app_mode = AppMode::Help,
-                                    _ => {}
+                                } else {
+                                    let _ = self
+                                        .sender
+                                        .send(Action::Error(String::from(
+                                            "Connect to a server first.",
+                                        )))
+                                        .await;
                                 }
                             }
-                            CurrentFocus::Bottom => {
-                                match key.code {
-                                    KeyCode::Esc => self.exit = true,
-                                    KeyCode::Tab => self.current_focus = CurrentFocus::Top,
-                                    KeyCode::Char('q') => self.previous_bottom_tab(),
-                                    KeyCode::Char('e') => self.next_bottom_tab(),
-                                    KeyCode::Char('?') => self.app_mode = AppMode::Help,
-                                    _ => {}
-                                }
-                                match self.selected_bottom_tab {
-                                    SelectedBottomTab::Connection => match key.code {
-                                        KeyCode::Left | KeyCode::Char('a') => {
-                                            if let SelectedConnectionButton::Disconnect =
-                                                self.selected_connection_button
-                                            {
-                                                self.selected_connection_button =
-                                                    SelectedConnectionButton::NewConnection;
-                                            }
-                                        }
-                                        KeyCode::Right | KeyCode::Char('d') => {
-                                            if let SelectedConnectionButton::NewConnection =
-                                                self.selected_connection_button
-                                            {
-                                                self.selected_connection_button =
-                                                    SelectedConnectionButton::Disconnect;
-                                            }
-                                        }
-                                        KeyCode::Enter => match self.selected_connection_button {
-                                            SelectedConnectionButton::NewConnection => {
-                                                self.app_mode =
-                                                    AppMode::Popup(PopupType::Connection);
-                                            }
-                                            SelectedConnectionButton::Disconnect => {
-                                                self.sender.send(Action::Disconnect).await?
-                                            }
-                                        },
-                                        _ => {}
-                                    },
-                                    SelectedBottomTab::Queue => match key.code {
-                                        KeyCode::Up => {
-                                            self.queue_select_previous_item();
-                                        }
-                                        KeyCode::Down => {
-                                            self.queue_select_next_item();
-                                        }
-                                        KeyCode::Char('g') => {
-                                            if !self.queue_table_data.is_empty() {
-                                                self.queue_go_to_cell(
-                                                    self.queue_table_data[self.queue_item_index]
-                                                        .address,
-                                                    self.queue_table_data[self.queue_item_index]
-                                                        .table_index,
-                                                );
+                            Some(Command::GoToAddress) => {
+                                self.app_mode = AppMode::Popup(PopupType::Goto);
+                            }
+                            Some(Command::GoToPage) => {
+                                self.app_mode = AppMode::Popup(PopupType::GotoPage);
+                            }
+                            Some(Command::CopyValue) => {
+                                let value = self.current_cell_value();
+                                if let Err(err) = self.copy_to_clipboard(value) {
+                                    let _ = self
+                                        .sender
+                                        .send(Action::Error(format!("Clipboard Error: {err}")))
+                                        .await;
+                                }
+                            }
+                            Some(Command::CopyAddress) => {
+                                let address = self.selected_top_tab.memory_address(
+                                    self.tables[self.selected_top_tab as usize].table_address,
+                                );
+                                if let Err(err) = self.copy_to_clipboard(address) {
+                                    let _ = self
+                                        .sender
+                                        .send(Action::Error(format!("Clipboard Error: {err}")))
+                                        .await;
+                                }
+                            }
+                            Some(Command::PinCell) => self.watch_toggle_current_cell(),
+                            Some(Command::ToggleListView) => {
+                                self.tables[self.selected_top_tab as usize].toggle_list_view();
+                            }
+                            Some(Command::Search) => {
+                                self.app_mode = AppMode::Popup(PopupType::Search);
+                            }
+                            Some(Command::FindNext) => match self.last_search {
+                                Some(value) => self.table_search(value).await,
+                                None => self.beep()?,
+                            },
+                            Some(command @ (Command::NextQueued | Command::PreviousQueued)) => {
+                                let table = &self.tables[self.selected_top_tab as usize];
+                                match table.find_queued(command == Command::NextQueued) {
+                                    Some(address) => self.table_go_to_cell(address),
+                                    None => self.beep()?,
+                                }
+                            }
+                            Some(command @ (Command::ApplyQueue | Command::ApplyQueueKeep)) => {
+                                if let ConnectionStatus::Connected = self.connection_status {
+                                    self.modbus_apply_queued(command == Command::ApplyQueueKeep)
+                                        .await;
+                                } else {
+                                    let _ = self
+                                        .sender
+                                        .send(Action::Error(String::from(
+                                            "Connect to a server first.",
+                                        )))
+                                        .await;
+                                }
+                            }
+                            Some(Command::EditCell) => {
+                                if self.can_queue() {
+                                    let table = &self.tables[self.selected_top_tab as usize];
+                                    let (start, end) = table
+                                        .selection()
+                                        .unwrap_or((table.table_address, table.table_address));
+                                    // Inputs can't be written, so Space pins an assertion instead
+                                    match (self.selected_top_tab, self.read_only_reason(start, end))
+                                    {
+                                        (
+                                            SelectedTopTab::DiscreteInputs
+                                            | SelectedTopTab::InputRegisters,
+                                            _,
+                                        ) => self.table_toggle_current_assertion(),
+                                        (_, Some(reason)) => self.refuse_write(reason).await?,
+                                        (SelectedTopTab::Coils, None) => {
+                                            let table =
+                                                &self.tables[SelectedTopTab::Coils as usize];
+                                            match table.selection_anchor.is_some() {
+                                                true => self.table_toggle_selection(),
+                                                false => self.table_toggle_current_cell(),
                                             }
                                         }
-                                        KeyCode::Char('r') => {
-                                            if !self.queue_table_data.is_empty() {
-                                                self.queue_revert_item()
+                                        (_, None) => {
+                                            // A typed register's width comes from its type
+                                            if self.edit_type().is_some() {
+                                                self.edit_popup_wide = false;
                                             }
+                                            self.app_mode = AppMode::Popup(PopupType::Edit)
                                         }
-                                        KeyCode::Char('m') => {
-                                            if let ConnectionStatus::Connected =
-                                                self.connection_status
-                                            {
-                                                if !self.queue_table_data.is_empty() {
-                                                    self.app_mode = AppMode::Popup(
-                                                        PopupType::SaveMacro(SaveMacroMode::Main),
-                                                    );
-                                                } else {
-                                                    let _ = self
-                                                        .sender
-                                                        .send(Action::Error(String::from(
-                                                            "Queue some commands first",
-                                                        )))
-                                                        .await;
-                                                }
-                                            } else {
-                                                let _ = self
-                                                    .sender
-                                                    .send(Action::Error(String::from(
-                                                        "Connect to a server first",
-                                                    )))
-                                                    .await;
-                                            }
+                                    }
+                                } else {
+                                    let _ = self
+                                        .sender
+                                        .send(Action::Error(String::from(
+                                            "Connect to a server first.",
+                                        )))
+                                        .await;
+                                }
+                            }
+                            Some(Command::EditAscii) => {
+                                if self.can_queue() {
+                                    if let SelectedTopTab::InputRegisters
+                                    | SelectedTopTab::HoldingRegisters = self.selected_top_tab
+                                    {
+                                        self.ascii_popup_input = String::new();
+                                        self.app_mode = AppMode::Popup(PopupType::Ascii);
+                                    } else {
+                                        self.beep()?;
+                                    }
+                                } else {
+                                    let _ = self
+                                        .sender
+                                        .send(Action::Error(String::from(
+                                            "Connect to a server first.",
+                                        )))
+                                        .await;
+                                }
+                            }
+                            Some(Command::EditBits) => {
+                                if self.can_queue() {
+                                    let address =
+                                        self.tables[self.selected_top_tab as usize].table_address;
+                                    match (
+                                        self.selected_top_tab,
+                                        self.read_only_reason(address, address),
+                                    ) {
+                                        (SelectedTopTab::Coils, _) => self.beep()?,
+                                        (_, Some(reason)) => self.refuse_write(reason).await?,
+                                        (_, None) => {
+                                            self.bit_popup_value = self.tables
+                                                [self.selected_top_tab as usize]
+                                                .current_cell()
+                                                .display_content()
+                                                .to_u16();
+                                            self.bit_popup_cursor = 15;
+                                            self.app_mode = AppMode::Popup(PopupType::BitEdit);
                                         }
-                                        _ => {}
-                                    },
+                                    }
+                                } else {
+                                    let _ = self
+                                        .sender
+                                        .send(Action::Error(String::from(
+                                            "Connect to a server first.",
+                                        )))
+                                        .await;
+                                }
+                            }
+
+                            // Connection
+                            Some(Command::SelectNewConnection) => {
+                                self.selected_connection_button =
+                                    SelectedConnectionButton::NewConnection;
+                            }
+                            Some(Command::SelectDisconnect) => {
+                                self.selected_connection_button =
+                                    SelectedConnectionButton::Disconnect;
+                            }
+                            Some(Command::Reconnect) => match self.last_connection {
+                                Some(addr) => self.sender.send(Action::Connect(addr)).await?,
+                                None => self.beep()?,
+                            },
+                            Some(Command::ReadDeviceIdentification) => {
+                                if let ConnectionStatus::Connected = self.connection_status {
+                                    let _ = self
+                                        .modbus_sender
+                                        .send(ModbusCommandQueue::DeviceIdentification)
+                                        .await;
+                                } else {
+                                    let _ = self
+                                        .sender
+                                        .send(Action::Error(String::from(
+                                            "Connect to a server first.",
+                                        )))
+                                        .await;
+                                }
+                            }
+                            Some(Command::WorkOffline) => {
+                                self.connection_popup_offline = true;
+                                self.open_connection_popup();
+                            }
+                            Some(Command::PressConnectionButton) => {
+                                match self.selected_connection_button {
+                                    SelectedConnectionButton::NewConnection => {
+                                        self.connection_popup_offline = false;
+                                        self.open_connection_popup();
+                                    }
+                                    SelectedConnectionButton::Disconnect => {
+                                        self.sender.send(Action::Disconnect).await?
+                                    }
+                                }
+                            }
+
+                            // Queue
+                            Some(Command::PreviousQueueItem) => self.queue_select_previous_item(),
+                            Some(Command::NextQueueItem) => self.queue_select_next_item(),
+                            Some(Command::MarkQueueItem) if !self.queue_table_data.is_empty() => {
+                                self.queue_toggle_mark();
+                            }
+                            Some(Command::ToggleReadWrite) if !self.queue_table_data.is_empty() => {
+                                self.queue_toggle_read_write()?;
+                            }
+                            Some(Command::ApplyMarked) => {
+                                if let ConnectionStatus::Connected = self.connection_status {
+                                    if !self.queue_marked_items.is_empty() {
+                                        self.modbus_apply_marked().await;
+                                    } else {
+                                        let _ = self
+                                            .sender
+                                            .send(Action::Error(String::from(
+                                                "Mark some queued commands first",
+                                            )))
+                                            .await;
+                                    }
+                                } else {
+                                    let _ = self
+                                        .sender
+                                        .send(Action::Error(String::from(
+                                            "Connect to a server first",
+                                        )))
+                                        .await;
+                                }
+                            }
+                            Some(Command::GoToQueueItem) => {
+                                if !self.queue_table_data.is_empty() {
+                                    self.queue_go_to_cell(
+                                        self.queue_table_data[self.queue_item_index].address,
+                                        self.queue_table_data[self.queue_item_index].table_index,
+                                    );
+                                }
+                            }
+                            Some(Command::RevertQueueItem) => {
+                                if !self.queue_table_data.is_empty() {
+                                    self.queue_revert_item()
+                                }
+                            }
+                            Some(Command::ImportCsv) => {
+                                if self.can_queue() {
+                                    self.app_mode = AppMode::Popup(PopupType::ImportCsv);
+                                } else {
+                                    let _ = self
+                                        .sender
+                                        .send(Action::Error(String::from(
+                                            "Connect to a server first",
+                                        )))
+                                        .await;
+                                }
+                            }
+                            Some(Command::SaveMacro) => {
+                                if self.can_queue() {
+                                    if !self.queue_table_data.is_empty() {
+                                        self.app_mode = AppMode::Popup(PopupType::SaveMacro(
+                                            SaveMacroMode::Main,
+                                        ));
+                                    } else {
+                                        let _ = self
+                                            .sender
+                                            .send(Action::Error(String::from(
+                                                "Queue some commands first",
+                                            )))
+                                            .await;
+                                    }
+                                } else {
+                                    let _ = self
+                                        .sender
+                                        .send(Action::Error(String::from(
+                                            "Connect to a server first",
+                                        )))
+                                        .await;
                                 }
                             }
+
+                            // Watch
+                            Some(Command::PreviousWatchItem) => self.watch_select_previous_item(),
+                            Some(Command::NextWatchItem) => self.watch_select_next_item(),
+                            Some(Command::GoToWatchItem) => {
+                                if let Some(&(table, address)) = self.selected_watch_item() {
+                                    self.queue_go_to_cell(address, table as usize);
+                                }
+                            }
+                            Some(Command::ReadWatchList) => {
+                                if let ConnectionStatus::Connected = self.connection_status {
+                                    self.modbus_read_watch_list().await;
+                                } else {
+                                    let _ = self
+                                        .sender
+                                        .send(Action::Error(String::from(
+                                            "Connect to a server first",
+                                        )))
+                                        .await;
+                                }
+                            }
+                            Some(Command::Unpin) => self.watch_remove_selected_item(),
+
+                            // Alarms
+                            Some(Command::ClearAlarmLog) => self.alarm_log.clear(),
+                            _ => {}
                         }
                     }
+                    AppMode::Help if self.help_searching => match key.code {
+                        KeyCode::Esc => {
+                            self.help_search_input = String::new();
+                            self.help_searching = false;
+                        }
+                        KeyCode::Enter => self.help_searching = false,
+                        KeyCode::Backspace => {
+                            if self.help_search_input.is_empty() {
+                                self.beep()?;
+                            } else {
+                                self.help_search_input.pop();
+                            }
+                        }
+                        KeyCode::Char(c) => self.help_search_input.push(c),
+                        _ => {}
+                    },
                     AppMode::Help => match key.code {
-                        KeyCode::Esc => self.exit = true,
-                        KeyCode::Char('?') => self.app_mode = AppMode::Main,
-                        KeyCode::Tab => self.help_menu_page = match self.help_menu_page {
-                            0 => 1,
-                            _ => 0,
-                        },
+                        KeyCode::Esc => self.request_exit(),
+                        KeyCode::Char('?') => {
+                            self.help_search_input = String::new();
+                            self.app_mode = AppMode::Main;
+                        }
+                        KeyCode::Char('/') => {
+                            self.help_search_input = String::new();
+                            self.help_searching = true;
+                        }
+                        KeyCode::Tab => {
+                            self.help_menu_scroll = 0;
+                            self.help_menu_page = match self.help_menu_page {
+                                0 => 1,
+                                _ => 0,
+                            }
+                        }
+                        KeyCode::Up => {
+                            self.help_menu_scroll = self.help_menu_scroll.saturating_sub(1)
+                        }
+                        KeyCode::Down => {
+                            self.help_menu_scroll = self.help_menu_scroll.saturating_add(1)
+                        }
+                        KeyCode::PageUp => {
+                            self.help_menu_scroll =
+                                self.help_menu_scroll.saturating_sub(HELP_MENU_SCROLL_STEP)
+                        }
+                        KeyCode::PageDown => {
+                            self.help_menu_scroll =
+                                self.help_menu_scroll.saturating_add(HELP_MENU_SCROLL_STEP)
+                        }
+                        KeyCode::Home => self.help_menu_scroll = 0,
+                        KeyCode::End => self.help_menu_scroll = u16::MAX,
                         _ => {}
                     },
                     AppMode::Popup(popup) => match popup {
                         PopupType::Connection => match key.code {
-                            KeyCode::Esc => self.exit = true,
+                            KeyCode::Esc => self.request_exit(),
                             KeyCode::Backspace => match self.connecting_popup_field {
                                 ConnectingField::Address => {
                                     if self.address_input_cursor > 0 {
@@ -740,30 +1815,32 @@ impl App {
                                 }
                             },
                             KeyCode::Enter => {
+                                // Both inputs keep a trailing space for the cursor, so this means empty
                                 if self.address_input.len() < 2 || self.port_input.len() < 2 {
                                     self.beep()?;
-                                }
-
-                                let address = (self.address_input.as_str().trim().to_owned()
-                                    + ":"
-                                    + self.port_input.as_str().trim())
-                                .parse::<SocketAddr>();
-
-                                match address {
-                                    Ok(addr) => {
-                                        self.app_mode = AppMode::Main;
-
-                                        self.address_input = String::from(" ");
-                                        self.address_input_cursor = 0;
-
-                                        self.port_input = String::from(" ");
-                                        self.port_input_cursor = 0;
-
-                                        self.connecting_popup_field = ConnectingField::Address;
-
-                                        self.sender.send(Action::Connect(addr)).await?;
+                                } else {
+                                    let host = self.address_input.trim().to_owned();
+                                    let port = self.port_input.trim().parse::<u16>();
+                                    let address = format!("{host}:{}", self.port_input.trim())
+                                        .parse::<SocketAddr>();
+
+                                    match (address, port) {
+                                        (Ok(addr), _) => {
+                                            self.close_connection_popup();
+                                            match self.connection_popup_offline {
+                                                true => self.start_offline(addr).await,
+                                                false => {
+                                                    self.sender.send(Action::Connect(addr)).await?
+                                                }
+                                            }
+                                        }
+                                        // Anything else is a hostname, which macros can't store
+                                        (Err(_), Ok(port)) if !self.connection_popup_offline => {
+                                            self.close_connection_popup();
+                                            self.resolve_and_connect(host, port);
+                                        }
+                                        (Err(_), _) => self.beep()?,
                                     }
-                                    Err(_) => self.beep()?,
                                 }
                             }
                             KeyCode::Left => match self.connecting_popup_field {
@@ -778,19 +1855,30 @@ impl App {
                             },
                             KeyCode::Right => match self.connecting_popup_field {
                                 ConnectingField::Address => {
-                                    if self.address_input_cursor < self.address_input.len() - 1 {
+                                    if self.address_input_cursor + 1 < self.address_input.len() {
                                         self.address_input_cursor =
                                             self.address_input_cursor.saturating_add(1);
                                     }
                                 }
                                 ConnectingField::Port => {
-                                    if self.port_input_cursor < self.port_input.len() - 1 {
+                                    if self.port_input_cursor + 1 < self.port_input.len() {
                                         self.port_input_cursor =
                                             self.port_input_cursor.saturating_add(1);
                                     }
                                 }
                             },
-                            KeyCode::Up | KeyCode::Down | KeyCode::Tab => {
+                            KeyCode::Up => match self.recent_index {
+                                Some(index) if index > 0 => self.select_recent(index - 1),
+                                _ => self.beep()?,
+                            },
+                            KeyCode::Down => {
+                                let index = self.recent_index.map_or(0, |index| index + 1);
+                                match self.recent_connections.get(index) {
+                                    Some(_) => self.select_recent(index),
+                                    None => self.beep()?,
+                                }
+                            }
+                            KeyCode::Tab => {
                                 self.connecting_popup_field = match self.connecting_popup_field {
                                     ConnectingField::Address => ConnectingField::Port,
                                     ConnectingField::Port => ConnectingField::Address,
@@ -798,15 +1886,15 @@ impl App {
                             }
                             KeyCode::Delete => match self.connecting_popup_field {
                                 ConnectingField::Address => {
-                                    if self.address_input_cursor < self.address_input.len() - 1 {
+                                    if self.address_input_cursor + 1 < self.address_input.len() {
                                         self.address_input.remove(self.address_input_cursor);
                                     } else {
                                         self.beep()?;
                                     }
                                 }
                                 ConnectingField::Port => {
-                                    if self.address_input_cursor < self.address_input.len() - 1 {
-                                        self.address_input.remove(self.address_input_cursor);
+                                    if self.port_input_cursor + 1 < self.port_input.len() {
+                                        self.port_input.remove(self.port_input_cursor);
                                     } else {
                                         self.beep()?;
                                     }
@@ -835,6 +1923,36 @@ impl App {
                             _ => {}
                         },
                         PopupType::Edit => match key.code {
+                            KeyCode::Char('u') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                                self.edit_popup_cursor = 0;
+                                self.edit_popup_input = String::new();
+                            }
+                            KeyCode::Char('v') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                                match self.clipboard_edit_value() {
+                                    Some(value) => {
+                                        self.edit_popup_cursor = value.len();
+                                        self.edit_popup_input = value;
+                                    }
+                                    None => self.beep()?,
+                                }
+                            }
+                            KeyCode::Char('b') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                                // Converts what has been typed so far, scaled values stay decimal
+                                let base = self.edit_popup_base.next();
+                                let input = match self.edit_popup_value() {
+                                    Some(value) => Some(self.edit_popup_text(base, value)),
+                                    None if self.edit_popup_input.is_empty() => Some(String::new()),
+                                    None => None,
+                                };
+                                match (self.edit_base_is_fixed(), input) {
+                                    (false, Some(input)) => {
+                                        self.edit_popup_base = base;
+                                        self.edit_popup_cursor = input.len();
+                                        self.edit_popup_input = input;
+                                    }
+                                    _ => self.beep()?,
+                                }
+                            }
                             KeyCode::Esc => {
                                 self.edit_popup_cursor = 0;
                                 self.edit_popup_input = String::new();
@@ -842,30 +1960,151 @@ impl App {
                             }
                             KeyCode::Backspace => {
                                 if self.edit_popup_cursor > 0 {
-                                    self.edit_popup_input.pop();
+                                    self.edit_popup_input.remove(self.edit_popup_cursor - 1);
                                     self.edit_popup_cursor =
                                         self.edit_popup_cursor.saturating_sub(1);
                                 } else {
                                     self.beep()?;
                                 }
                             }
+                            KeyCode::Delete => {
+                                if self.edit_popup_cursor < self.edit_popup_input.len() {
+                                    self.edit_popup_input.remove(self.edit_popup_cursor);
+                                } else {
+                                    self.beep()?;
+                                }
+                            }
+                            KeyCode::Left => {
+                                self.edit_popup_cursor = self.edit_popup_cursor.saturating_sub(1)
+                            }
+                            KeyCode::Right => {
+                                self.edit_popup_cursor =
+                                    (self.edit_popup_cursor + 1).min(self.edit_popup_input.len())
+                            }
+                            KeyCode::Home => self.edit_popup_cursor = 0,
+                            KeyCode::End => self.edit_popup_cursor = self.edit_popup_input.len(),
+                            KeyCode::Tab => {
+                                match self.current_scaling_rule().is_some()
+                                    || self.edit_type().is_some()
+                                {
+                                    true => self.beep()?,
+                                    false => {
+                                        self.edit_popup_wide = match self.edit_popup_wide {
+                                            true => false,
+                                            false => true,
+                                        };
+                                        self.edit_popup_cursor = 0;
+                                        self.edit_popup_input = String::new();
+                                    }
+                                }
+                            }
+                            KeyCode::Enter if self.edit_type().is_some() => {
+                                self.queue_typed_edit()?
+                            }
+                            KeyCode::Enter if self.edit_popup_wide => {
+                                match self.edit_popup_value() {
+                                    Some(value) => self.queue_wide_edit(
+                                        self.tables[self.selected_top_tab as usize].table_address,
+                                        value,
+                                    )?,
+                                    None => self.beep()?,
+                                }
+                            }
+                            KeyCode::Enter if self.edit_base() != InputBase::Decimal => {
+                                match self
+                                    .edit_base()
+                                    .parse(&self.edit_popup_input)
+                                    .and_then(|value| u16::try_from(value).ok())
+                                {
+                                    Some(value) => {
+                                        self.table_queue_current_cell(value);
+                                        self.edit_popup_cursor = 0;
+                                        self.edit_popup_input = String::new();
+                                        self.app_mode = AppMode::Main;
+                                    }
+                                    None => self.beep()?,
+                                }
+                            }
                             KeyCode::Enter => {
-                                if let Ok(new_value) = self.edit_popup_input.parse::<usize>() {
-                                    if new_value > 65535 {
-                                        self.beep()?;
-                                    } else {
-                                        self.table_queue_current_cell(new_value as u16);
+                                // Binary is always the raw register, even when it is scaled
+                                let is_binary = self.edit_popup_input.starts_with("0b");
+                                let new_value = match self.current_scaling_rule() {
+                                    Some(rule) if !is_binary => self
+                                        .edit_popup_input
+                                        .parse::<f64>()
+                                        .ok()
+                                        .and_then(|value| rule.to_raw(value)),
+                                    _ => self.display_mode.parse(&self.edit_popup_input),
+                                };
+                                match new_value {
+                                    Some(new_value) => {
+                                        self.table_queue_current_cell(new_value);
                                         self.edit_popup_cursor = 0;
                                         self.edit_popup_input = String::new();
                                         self.app_mode = AppMode::Main;
                                     }
+                                    // Register pairs are only written from the 32-bit mode (Tab)
+                                    None => self.beep()?,
+                                }
+                            }
+                            KeyCode::Char(c) if self.edit_base() != InputBase::Decimal => {
+                                if self.edit_base().is_digit(c)
+                                    && self.edit_popup_input.len() < self.edit_popup_max_len()
+                                {
+                                    self.edit_popup_input
+                                        .insert(self.edit_popup_cursor, c.to_ascii_uppercase());
+                                    self.edit_popup_cursor =
+                                        self.edit_popup_cursor.saturating_add(1);
                                 } else {
                                     self.beep()?;
                                 }
                             }
                             KeyCode::Char(c) => {
-                                if c.is_ascii_digit() && self.edit_popup_cursor < 5 {
-                                    self.edit_popup_input.push(c);
+                                let numeric_type = self
+                                    .edit_type()
+                                    .map(|(data_type, _)| data_type)
+                                    .filter(|data_type| data_type.is_numeric());
+                                let is_typed_char = match (numeric_type, c) {
+                                    (Some(DataType::I16 | DataType::I32 | DataType::F32), '-') => {
+                                        self.edit_popup_cursor == 0
+                                            && !self.edit_popup_input.starts_with('-')
+                                    }
+                                    (Some(DataType::F32), '.') => {
+                                        !self.edit_popup_input.contains('.')
+                                    }
+                                    _ => false,
+                                };
+                                let is_scaled_char =
+                                    self.current_scaling_rule().is_some() && matches!(c, '.' | '-');
+                                let is_sign = (self.display_mode == DisplayMode::Signed
+                                    || self.edit_popup_wide)
+                                    && c == '-'
+                                    && self.edit_popup_cursor == 0
+                                    && !self.edit_popup_input.starts_with('-');
+                                let is_binary_char = match c {
+                                    _ if self.edit_popup_wide => false,
+                                    'b' => {
+                                        self.edit_popup_input == "0" && self.edit_popup_cursor == 1
+                                    }
+                                    '_' => {
+                                        self.edit_popup_input.starts_with("0b")
+                                            && self.edit_popup_cursor >= 2
+                                    }
+                                    _ => false,
+                                };
+                                let is_allowed = match numeric_type {
+                                    Some(_) => c.is_ascii_digit() || is_typed_char,
+                                    None => {
+                                        c.is_ascii_digit()
+                                            || is_scaled_char
+                                            || is_sign
+                                            || is_binary_char
+                                    }
+                                };
+                                if is_allowed
+                                    && self.edit_popup_input.len() < self.edit_popup_max_len()
+                                {
+                                    self.edit_popup_input.insert(self.edit_popup_cursor, c);
                                     self.edit_popup_cursor =
                                         self.edit_popup_cursor.saturating_add(1);
                                 } else {
@@ -874,56 +2113,598 @@ impl App {
                             }
                             _ => {}
                         },
-                        PopupType::Error(_) => {
-                            if key.code == KeyCode::Enter {
-                                self.app_mode = AppMode::Main;
+                        PopupType::BitEdit => match key.code {
+                            KeyCode::Esc => self.app_mode = AppMode::Main,
+                            KeyCode::Left | KeyCode::Char('a') => {
+                                self.bit_popup_cursor = (self.bit_popup_cursor + 1).min(15)
                             }
-                        }
-                        PopupType::Goto => match key.code {
-                            KeyCode::Esc => {
-                                self.goto_popup_cursor = 0;
-                                self.goto_popup_input = String::new();
+                            KeyCode::Right | KeyCode::Char('d') => {
+                                self.bit_popup_cursor = self.bit_popup_cursor.saturating_sub(1)
+                            }
+                            KeyCode::Char(' ') => {
+                                self.bit_popup_value ^= 1 << self.bit_popup_cursor
+                            }
+                            KeyCode::Char('m') => {
+                                self.bit_popup_masked = match self.bit_popup_masked {
+                                    true => false,
+                                    false => true,
+                                }
+                            }
+                            KeyCode::Enter => {
+                                self.table_queue_current_bits(self.bit_popup_value);
                                 self.app_mode = AppMode::Main;
                             }
-                            KeyCode::Backspace => {
-                                if self.goto_popup_cursor > 0 {
-                                    self.goto_popup_input.pop();
-                                    self.goto_popup_cursor =
-                                        self.goto_popup_cursor.saturating_sub(1);
+                            _ => {}
+                        },
+                        PopupType::Ascii => match key.code {
+                            KeyCode::Esc => self.app_mode = AppMode::Main,
+                            KeyCode::Up => {
+                                if self.ascii_popup_registers < ASCII_POPUP_MAX_REGISTERS {
+                                    self.ascii_popup_registers += 1;
+                                } else {
+                                    self.beep()?;
+                                }
+                            }
+                            KeyCode::Down => {
+                                // Keeps room for what has already been typed
+                                if self.ascii_popup_registers > 1
+                                    && self.ascii_popup_input.len()
+                                        <= (self.ascii_popup_registers - 1) * 2
+                                {
+                                    self.ascii_popup_registers -= 1;
                                 } else {
                                     self.beep()?;
                                 }
                             }
+                            KeyCode::Backspace => match self.ascii_popup_input.pop() {
+                                Some(_) => {}
+                                None => self.beep()?,
+                            },
                             KeyCode::Enter => {
-                                if let Ok(new_value) = self.goto_popup_input.parse::<usize>() {
-                                    if !(1..=65535).contains(&new_value) {
-                                        self.beep()?;
-                                    } else {
-                                        self.table_go_to_cell((new_value - 1) as u16);
-                                        self.goto_popup_cursor = 0;
-                                        self.goto_popup_input = String::new();
-                                        self.app_mode = AppMode::Main;
-                                    }
+                                let (start, count) = self.ascii_range();
+                                if self.ascii_popup_input.is_empty() {
+                                    self.app_mode = AppMode::Main;
+                                } else if let Some(reason) =
+                                    self.read_only_reason(start, start + (count - 1) as u16)
+                                {
+                                    self.refuse_write(reason).await?;
+                                } else if let Some(words) = ascii_to_registers(
+                                    &self.ascii_popup_input,
+                                    self.ascii_range().1,
+                                ) {
+                                    self.tables[self.selected_top_tab as usize]
+                                        .queue_current_words(&words);
+                                    self.refresh_queue_table();
+                                    self.ascii_popup_input = String::new();
+                                    self.app_mode = AppMode::Main;
                                 } else {
                                     self.beep()?;
                                 }
                             }
                             KeyCode::Char(c) => {
-                                if c.is_ascii_digit() && self.goto_popup_cursor < 5 {
-                                    self.goto_popup_input.push(c);
-                                    self.goto_popup_cursor =
-                                        self.goto_popup_cursor.saturating_add(1);
+                                // Input registers are only shown, never written
+                                if let (SelectedTopTab::HoldingRegisters, ' '..='~', true) = (
+                                    self.selected_top_tab,
+                                    c,
+                                    self.ascii_popup_input.len() < self.ascii_range().1 * 2,
+                                ) {
+                                    self.ascii_popup_input.push(c);
                                 } else {
                                     self.beep()?;
                                 }
                             }
                             _ => {}
                         },
-                        PopupType::SaveMacro(save_macro_mode) => match save_macro_mode {
-                            SaveMacroMode::Main => match key.code {
-                                KeyCode::Esc => {
-                                    self.macro_popup_cursor = 0;
-                                    self.macro_popup_input = String::new();
+                        PopupType::Error(_) => {
+                            if key.code == KeyCode::Enter {
+                                self.app_mode = AppMode::Main;
+                            }
+                        }
+                        PopupType::DeviceIdentification(_) => {
+                            if let KeyCode::Enter | KeyCode::Esc = key.code {
+                                self.app_mode = AppMode::Main;
+                            }
+                        }
+                        PopupType::Settings => match key.code {
+                            KeyCode::Esc | KeyCode::Char(',') => self.app_mode = AppMode::Main,
+                            KeyCode::Up | KeyCode::Char('w') => {
+                                match (self.settings_popup_row as usize).checked_sub(1) {
+                                    Some(row) => {
+                                        self.settings_popup_row =
+                                            Setting::from_repr(row).unwrap_or_default()
+                                    }
+                                    None => self.beep()?,
+                                }
+                            }
+                            KeyCode::Down | KeyCode::Char('s') => {
+                                match Setting::from_repr(self.settings_popup_row as usize + 1) {
+                                    Some(setting) => self.settings_popup_row = setting,
+                                    None => self.beep()?,
+                                }
+                            }
+                            KeyCode::Enter | KeyCode::Char(' ') => {
+                                self.change_setting(self.settings_popup_row).await
+                            }
+                            _ => {}
+                        },
+                        PopupType::ConfirmQuit => match key.code {
+                            KeyCode::Char('y') | KeyCode::Char('Y') => self.exit = true,
+                            KeyCode::Char('n') | KeyCode::Char('N') | KeyCode::Esc => {
+                                self.app_mode = AppMode::Main;
+                            }
+                            _ => {}
+                        },
+                        PopupType::Goto => match key.code {
+                            KeyCode::Char('u') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                                self.goto_popup_cursor = 0;
+                                self.goto_popup_input = String::new();
+                            }
+                            KeyCode::Esc => {
+                                self.goto_popup_cursor = 0;
+                                self.goto_popup_input = String::new();
+                                self.app_mode = AppMode::Main;
+                            }
+                            KeyCode::Backspace => {
+                                if self.goto_popup_cursor > 0 {
+                                    self.goto_popup_input.remove(self.goto_popup_cursor - 1);
+                                    self.goto_popup_cursor =
+                                        self.goto_popup_cursor.saturating_sub(1);
+                                } else {
+                                    self.beep()?;
+                                }
+                            }
+                            KeyCode::Delete => {
+                                if self.goto_popup_cursor < self.goto_popup_input.len() {
+                                    self.goto_popup_input.remove(self.goto_popup_cursor);
+                                } else {
+                                    self.beep()?;
+                                }
+                            }
+                            KeyCode::Left => {
+                                self.goto_popup_cursor = self.goto_popup_cursor.saturating_sub(1)
+                            }
+                            KeyCode::Right => {
+                                self.goto_popup_cursor =
+                                    (self.goto_popup_cursor + 1).min(self.goto_popup_input.len())
+                            }
+                            KeyCode::Home => self.goto_popup_cursor = 0,
+                            KeyCode::End => self.goto_popup_cursor = self.goto_popup_input.len(),
+                            KeyCode::Char('b') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                                // Only a plain address converts, a table prefix is decimal only
+                                let base = self.goto_popup_base.next();
+                                match (self.goto_popup_address(), self.goto_popup_input.is_empty())
+                                {
+                                    (Some(address), _) => {
+                                        self.goto_popup_input = base.format(address as u32 + 1);
+                                        self.goto_popup_cursor = self.goto_popup_input.len();
+                                        self.goto_popup_base = base;
+                                    }
+                                    (None, true) => self.goto_popup_base = base,
+                                    (None, false) => self.beep()?,
+                                }
+                            }
+                            KeyCode::Enter if self.goto_popup_base != InputBase::Decimal => {
+                                match self.goto_popup_address() {
+                                    Some(address)
+                                        if self.tables[self.selected_top_tab as usize]
+                                            .contains(address) =>
+                                    {
+                                        self.table_go_to_cell(address);
+                                        self.goto_popup_cursor = 0;
+                                        self.goto_popup_input = String::new();
+                                        self.app_mode = AppMode::Main;
+                                    }
+                                    _ => self.beep()?,
+                                }
+                            }
+                            KeyCode::Enter => {
+                                if let Some((table, address)) = parse_goto_address(
+                                    &self.goto_popup_input,
+                                )
+                                .filter(|(table, address)| {
+                                    let table = table.unwrap_or(self.selected_top_tab);
+                                    self.tables[table as usize].contains(*address)
+                                }) {
+                                    match table {
+                                        Some(table) => {
+                                            self.queue_go_to_cell(address, table as usize)
+                                        }
+                                        None => self.table_go_to_cell(address),
+                                    }
+                                    self.goto_popup_cursor = 0;
+                                    self.goto_popup_input = String::new();
+                                    self.app_mode = AppMode::Main;
+                                } else {
+                                    self.beep()?;
+                                }
+                            }
+                            KeyCode::Char(c) => {
+                                if self.goto_popup_base.is_digit(c)
+                                    && self.goto_popup_input.len() < self.goto_popup_max_len()
+                                {
+                                    self.goto_popup_input
+                                        .insert(self.goto_popup_cursor, c.to_ascii_uppercase());
+                                    self.goto_popup_cursor =
+                                        self.goto_popup_cursor.saturating_add(1);
+                                } else {
+                                    self.beep()?;
+                                }
+                            }
+                            _ => {}
+                        },
+                        PopupType::RawPdu => match key.code {
+                            KeyCode::Esc => {
+                                self.raw_popup_input = String::new();
+                                self.raw_popup_response = None;
+                                self.app_mode = AppMode::Main;
+                            }
+                            KeyCode::Backspace => {
+                                if self.raw_popup_input.pop().is_none() {
+                                    self.beep()?;
+                                }
+                            }
+                            KeyCode::Enter => {
+                                if let Some((function, data)) = parse_raw_pdu(&self.raw_popup_input)
+                                {
+                                    self.raw_popup_response = Some(String::from("Waiting..."));
+                                    let _ = self
+                                        .sender
+                                        .send(Action::ToModbus(ModbusCommandQueue::Raw(
+                                            function, data,
+                                        )))
+                                        .await;
+                                } else {
+                                    self.beep()?;
+                                }
+                            }
+                            KeyCode::Char(c) => {
+                                // Room for every byte with a space between each
+                                if (c.is_ascii_hexdigit() || c == ' ')
+                                    && self.raw_popup_input.len() < MAX_PDU_BYTES * 3
+                                {
+                                    self.raw_popup_input.push(c.to_ascii_uppercase());
+                                } else {
+                                    self.beep()?;
+                                }
+                            }
+                            _ => {}
+                        },
+                        PopupType::GotoPage => match key.code {
+                            KeyCode::Esc => {
+                                self.page_popup_cursor = 0;
+                                self.page_popup_input = String::new();
+                                self.app_mode = AppMode::Main;
+                            }
+                            KeyCode::Backspace => {
+                                if self.page_popup_cursor > 0 {
+                                    self.page_popup_input.pop();
+                                    self.page_popup_cursor =
+                                        self.page_popup_cursor.saturating_sub(1);
+                                } else {
+                                    self.beep()?;
+                                }
+                            }
+                            KeyCode::Enter => {
+                                let page_count =
+                                    self.tables[self.selected_top_tab as usize].page_count();
+                                if let Ok(new_page) = self.page_popup_input.parse::<usize>() {
+                                    if !(1..=page_count).contains(&new_page) {
+                                        self.beep()?;
+                                    } else {
+                                        self.table_go_to_page(new_page - 1).await;
+                                        self.page_popup_cursor = 0;
+                                        self.page_popup_input = String::new();
+                                        self.app_mode = AppMode::Main;
+                                    }
+                                } else {
+                                    self.beep()?;
+                                }
+                            }
+                            KeyCode::Char(c) => {
+                                if c.is_ascii_digit() && self.page_popup_cursor < 5 {
+                                    self.page_popup_input.push(c);
+                                    self.page_popup_cursor =
+                                        self.page_popup_cursor.saturating_add(1);
+                                } else {
+                                    self.beep()?;
+                                }
+                            }
+                            _ => {}
+                        },
+                        PopupType::Search => match key.code {
+                            KeyCode::Esc => {
+                                self.search_popup_cursor = 0;
+                                self.search_popup_input = String::new();
+                                self.app_mode = AppMode::Main;
+                            }
+                            KeyCode::Backspace => {
+                                if self.search_popup_cursor > 0 {
+                                    self.search_popup_input.pop();
+                                    self.search_popup_cursor =
+                                        self.search_popup_cursor.saturating_sub(1);
+                                } else {
+                                    self.beep()?;
+                                }
+                            }
+                            KeyCode::Enter => {
+                                if let Ok(value) = self.search_popup_input.parse::<u16>() {
+                                    self.search_popup_cursor = 0;
+                                    self.search_popup_input = String::new();
+                                    self.app_mode = AppMode::Main;
+                                    self.last_search = Some(value);
+                                    self.table_search(value).await;
+                                } else {
+                                    self.beep()?;
+                                }
+                            }
+                            KeyCode::Char(c) => {
+                                if c.is_ascii_digit() && self.search_popup_cursor < 5 {
+                                    self.search_popup_input.push(c);
+                                    self.search_popup_cursor =
+                                        self.search_popup_cursor.saturating_add(1);
+                                } else {
+                                    self.beep()?;
+                                }
+                            }
+                            _ => {}
+                        },
+                        PopupType::ReadRange => match key.code {
+                            KeyCode::Esc => {
+                                self.range_popup_cursor = 0;
+                                self.range_popup_input = String::new();
+                                self.app_mode = AppMode::Main;
+                            }
+                            KeyCode::Backspace => {
+                                if self.range_popup_cursor > 0 {
+                                    self.range_popup_input.pop();
+                                    self.range_popup_cursor =
+                                        self.range_popup_cursor.saturating_sub(1);
+                                } else {
+                                    self.beep()?;
+                                }
+                            }
+                            KeyCode::Enter => match parse_read_range(&self.range_popup_input) {
+                                Some((start, count)) => {
+                                    // Chunked by the Modbus task, wherever the page is
+                                    let command = vec![(self.selected_top_tab, start, count)];
+                                    self.sender
+                                        .send(Action::ToModbus(ModbusCommandQueue::Read(command)))
+                                        .await?;
+                                    self.range_popup_cursor = 0;
+                                    self.range_popup_input = String::new();
+                                    self.app_mode = AppMode::Main;
+                                }
+                                None => self.beep()?,
+                            },
+                            KeyCode::Char(c) => {
+                                if (c.is_ascii_digit() || c == ',')
+                                    && self.range_popup_cursor < RANGE_POPUP_MAX_LEN
+                                {
+                                    self.range_popup_input.push(c);
+                                    self.range_popup_cursor =
+                                        self.range_popup_cursor.saturating_add(1);
+                                } else {
+                                    self.beep()?;
+                                }
+                            }
+                            _ => {}
+                        },
+                        PopupType::Fill => match key.code {
+                            KeyCode::Esc => {
+                                self.fill_popup_cursor = 0;
+                                self.fill_popup_input = String::new();
+                                self.app_mode = AppMode::Main;
+                            }
+                            KeyCode::Backspace => {
+                                if self.fill_popup_cursor > 0 {
+                                    self.fill_popup_input.pop();
+                                    self.fill_popup_cursor =
+                                        self.fill_popup_cursor.saturating_sub(1);
+                                } else {
+                                    self.beep()?;
+                                }
+                            }
+                            KeyCode::Enter => {
+                                let new_value = parse_u16_value(&self.fill_popup_input);
+                                match (self.selected_top_tab, new_value) {
+                                    (SelectedTopTab::Coils, Some(value @ (0 | 1)))
+                                    | (SelectedTopTab::HoldingRegisters, Some(value)) => {
+                                        self.table_queue_current_page(value);
+                                        self.fill_popup_cursor = 0;
+                                        self.fill_popup_input = String::new();
+                                        self.app_mode = AppMode::Main;
+                                    }
+                                    _ => self.beep()?,
+                                }
+                            }
+                            KeyCode::Char(c) => {
+                                // Decimal or 0x hex, so "0xFFFF" is the longest
+                                if (c.is_ascii_hexdigit() || c == 'x')
+                                    && self.fill_popup_cursor < FILL_POPUP_MAX_LEN
+                                {
+                                    self.fill_popup_input.push(c);
+                                    self.fill_popup_cursor =
+                                        self.fill_popup_cursor.saturating_add(1);
+                                } else {
+                                    self.beep()?;
+                                }
+                            }
+                            _ => {}
+                        },
+                        PopupType::ImportCsv => match key.code {
+                            KeyCode::Esc => {
+                                self.import_popup_cursor = 0;
+                                self.import_popup_input = String::new();
+                                self.app_mode = AppMode::Main;
+                            }
+                            KeyCode::Backspace => {
+                                if self.import_popup_cursor > 0 {
+                                    self.import_popup_input.pop();
+                                    self.import_popup_cursor =
+                                        self.import_popup_cursor.saturating_sub(1);
+                                } else {
+                                    self.beep()?;
+                                }
+                            }
+                            KeyCode::Enter if self.import_popup_cursor > 0 => {
+                                let mut filename = self.import_popup_input.clone();
+                                if std::path::Path::new(&filename).extension().is_none() {
+                                    filename.push_str(".csv");
+                                }
+                                match import_file(&filename).await {
+                                    Ok((rows, bad_lines)) => {
+                                        self.import_popup_cursor = 0;
+                                        self.import_popup_input = String::new();
+                                        self.app_mode = AppMode::Main;
+                                        self.queue_import(rows, bad_lines).await;
+                                    }
+                                    // Keep the popup open so the name can be fixed
+                                    Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+                                        self.beep()?
+                                    }
+                                    Err(err) => {
+                                        self.app_mode = AppMode::Main;
+                                        let _ =
+                                            self.sender.send(Action::Error(err.to_string())).await;
+                                    }
+                                }
+                            }
+                            KeyCode::Char(c) => {
+                                if (c.is_alphanumeric() || matches!(c, '_' | '-' | '.' | '/'))
+                                    && self.import_popup_cursor < IMPORT_POPUP_MAX_LEN
+                                {
+                                    self.import_popup_input.push(c);
+                                    self.import_popup_cursor =
+                                        self.import_popup_cursor.saturating_add(1);
+                                } else {
+                                    self.beep()?;
+                                }
+                            }
+                            _ => {}
+                        },
+                        PopupType::LoadSnapshot => match key.code {
+                            KeyCode::Esc => {
+                                self.snapshot_popup_cursor = 0;
+                                self.snapshot_popup_input = String::new();
+                                self.app_mode = AppMode::Main;
+                            }
+                            KeyCode::Backspace => {
+                                if self.snapshot_popup_cursor > 0 {
+                                    self.snapshot_popup_input.pop();
+                                    self.snapshot_popup_cursor =
+                                        self.snapshot_popup_cursor.saturating_sub(1);
+                                } else {
+                                    self.beep()?;
+                                }
+                            }
+                            KeyCode::Enter if self.snapshot_popup_cursor > 0 => {
+                                let mut filename = self.snapshot_popup_input.clone();
+                                if std::path::Path::new(&filename).extension().is_none() {
+                                    filename.push_str(".csv");
+                                }
+                                match Snapshot::from_file(&filename).await {
+                                    Ok(snapshot) => {
+                                        self.snapshot_popup_cursor = 0;
+                                        self.snapshot_popup_input = String::new();
+                                        self.snapshot = Some(snapshot);
+                                        self.app_mode = AppMode::Main;
+                                    }
+                                    // Keep the popup open so the name can be fixed
+                                    Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+                                        self.beep()?
+                                    }
+                                    Err(err) => {
+                                        self.app_mode = AppMode::Main;
+                                        let _ = self
+                                            .sender
+                                            .send(Action::Error(format!("{filename}: {err}")))
+                                            .await;
+                                    }
+                                }
+                            }
+                            KeyCode::Char(c) => {
+                                if (c.is_alphanumeric() || matches!(c, '_' | '-' | '.' | '/'))
+                                    && self.snapshot_popup_cursor < IMPORT_POPUP_MAX_LEN
+                                {
+                                    self.snapshot_popup_input.push(c);
+                                    self.snapshot_popup_cursor =
+                                        self.snapshot_popup_cursor.saturating_add(1);
+                                } else {
+                                    self.beep()?;
+                                }
+                            }
+                            _ => {}
+                        },
+                        PopupType::SaveCapture => match key.code {
+                            KeyCode::Esc => {
+                                // Discards the capture, the next recording starts fresh anyway
+                                self.capture_popup_cursor = 0;
+                                self.capture_popup_input = String::new();
+                                self.capture.clear();
+                                self.app_mode = AppMode::Main;
+                            }
+                            KeyCode::Backspace => {
+                                if self.capture_popup_cursor > 0 {
+                                    self.capture_popup_input.pop();
+                                    self.capture_popup_cursor =
+                                        self.capture_popup_cursor.saturating_sub(1);
+                                } else {
+                                    self.beep()?;
+                                }
+                            }
+                            KeyCode::Enter if self.capture_popup_cursor > 0 => {
+                                let source = match (self.current_ip_address, self.current_port) {
+                                    (Some(address), Some(port)) => {
+                                        Some(SocketAddr::new(address, port))
+                                    }
+                                    _ => None,
+                                };
+                                match self
+                                    .capture
+                                    .to_file(self.capture_popup_input.clone(), source)
+                                    .await
+                                {
+                                    Ok(()) => {
+                                        self.capture_popup_cursor = 0;
+                                        self.capture_popup_input = String::new();
+                                        self.capture.clear();
+                                        self.app_mode = AppMode::Main;
+                                    }
+                                    // Keep the popup open so another name can be picked
+                                    Err(err) if err.kind() == std::io::ErrorKind::AlreadyExists => {
+                                        self.beep()?
+                                    }
+                                    Err(err) => {
+                                        self.app_mode = AppMode::Main;
+                                        let _ =
+                                            self.sender.send(Action::Error(err.to_string())).await;
+                                    }
+                                }
+                            }
+                            KeyCode::Char(c) => {
+                                if (c.is_alphanumeric() || matches!(c, '_' | '-'))
+                                    && self.capture_popup_cursor < 24
+                                {
+                                    self.capture_popup_input.push(c);
+                                    self.capture_popup_cursor =
+                                        self.capture_popup_cursor.saturating_add(1);
+                                } else {
+                                    self.beep()?;
+                                }
+                            }
+                            _ => {}
+                        },
+                        PopupType::SaveMacro(save_macro_mode) => match save_macro_mode {
+                            SaveMacroMode::Main => match key.code {
+                                KeyCode::Char('u')
+                                    if key.modifiers.contains(KeyModifiers::CONTROL) =>
+                                {
+                                    self.macro_popup_cursor = 0;
+                                    self.macro_popup_input = String::new();
+                                }
+                                KeyCode::Esc => {
+                                    self.macro_popup_cursor = 0;
+                                    self.macro_popup_input = String::new();
                                     self.app_mode = AppMode::Main;
                                 }
                                 KeyCode::Backspace => {
@@ -936,31 +2717,25 @@ impl App {
                                     }
                                 }
                                 KeyCode::Enter => {
-                                    let magmod_contents = MagModCommandList::new(
-                                        self.current_ip_address
-                                            .expect("This shouldn't be possible")
-                                            .into(),
-                                        self.current_port.expect("This shouldn't be possible"),
-                                        self.queue_table_data
-                                            .iter()
-                                            .map(|queue_item| {
-                                                (
-                                                    queue_item.cell.table_type,
-                                                    queue_item.address,
-                                                    queue_item.cell.queued_content,
-                                                )
-                                            })
-                                            .collect(),
-                                    );
+                                    let Some(magmod_contents) = self.queued_macro() else {
+                                        self.app_mode = AppMode::Main;
+                                        let _ = self
+                                            .sender
+                                            .send(Action::Error(String::from(
+                                                "Connect to a server first",
+                                            )))
+                                            .await;
+                                        return Ok(());
+                                    };
                                     match magmod_contents
                                         .to_file(self.macro_popup_input.clone(), false)
                                         .await
                                     {
-                                        Ok(_) => {
+                                        Ok(skipped_count) => {
                                             self.macro_popup_input = String::new();
                                             self.macro_popup_cursor = 0;
                                             self.app_mode = AppMode::Popup(PopupType::SaveMacro(
-                                                SaveMacroMode::FileSaved,
+                                                SaveMacroMode::FileSaved(skipped_count),
                                             ));
                                         }
                                         Err(err) => {
@@ -973,7 +2748,7 @@ impl App {
                                                 self.app_mode = AppMode::Main;
                                                 let _ = self
                                                     .sender
-                                                    .send(Action::Error(err.kind().to_string()))
+                                                    .send(Action::Error(err.to_string()))
                                                     .await;
                                             }
                                         }
@@ -999,38 +2774,32 @@ impl App {
                                     self.app_mode = AppMode::Main;
                                 }
                                 KeyCode::Char('y') => {
-                                    let magmod_contents = MagModCommandList::new(
-                                        self.current_ip_address
-                                            .expect("This shouldn't be possible")
-                                            .into(),
-                                        self.current_port.expect("This shouldn't be possible"),
-                                        self.queue_table_data
-                                            .iter()
-                                            .map(|queue_item| {
-                                                (
-                                                    queue_item.cell.table_type,
-                                                    queue_item.address,
-                                                    queue_item.cell.queued_content,
-                                                )
-                                            })
-                                            .collect(),
-                                    );
+                                    let Some(magmod_contents) = self.queued_macro() else {
+                                        self.app_mode = AppMode::Main;
+                                        let _ = self
+                                            .sender
+                                            .send(Action::Error(String::from(
+                                                "Connect to a server first",
+                                            )))
+                                            .await;
+                                        return Ok(());
+                                    };
                                     match magmod_contents
                                         .to_file(self.macro_popup_input.clone(), true)
                                         .await
                                     {
-                                        Ok(_) => {
+                                        Ok(skipped_count) => {
                                             self.macro_popup_input = String::new();
                                             self.macro_popup_cursor = 0;
                                             self.app_mode = AppMode::Popup(PopupType::SaveMacro(
-                                                SaveMacroMode::FileSaved,
+                                                SaveMacroMode::FileSaved(skipped_count),
                                             ));
                                         }
                                         Err(err) => {
                                             self.app_mode = AppMode::Main;
                                             let _ = self
                                                 .sender
-                                                .send(Action::Error(err.kind().to_string()))
+                                                .send(Action::Error(err.to_string()))
                                                 .await;
                                         }
                                     };
@@ -1041,7 +2810,7 @@ impl App {
                                 }
                                 _ => {}
                             },
-                            SaveMacroMode::FileSaved => if key.code == KeyCode::Enter {
+                            SaveMacroMode::FileSaved(_) => if key.code == KeyCode::Enter {
                                 self.app_mode = AppMode::Main;
                             },
                         },
@@ -1053,14 +2822,85 @@ impl App {
     }
 
     fn apply_modbus_updates(&mut self, commands: Vec<ModbusWriteCommand>) {
+        self.sync_history_cell();
+        let now = Instant::now();
+        // Locked once per batch, the exporter only needs it while answering a scrape
+        let exported = self.known_values.clone();
+        let mut exported = exported.as_ref().and_then(|values| values.lock().ok());
         for (table_index, address, content) in commands {
+            if self.recording {
+                self.capture.record(table_index, address, content);
+            }
+            if let Some(mqtt) = &self.mqtt {
+                mqtt.publish(table_index, address, content.to_u16());
+            }
+            if let Some(exported) = &mut exported {
+                exported.insert((table_index, address), content.to_u16());
+            }
+            self.check_alarms(table_index, address, content.to_u16());
+            if self.tables[table_index as usize].tick_refresh
+                && self.history_cell == Some((table_index, address))
+            {
+                if self.history_samples.len() == HISTORY_LENGTH {
+                    self.history_samples.pop_front();
+                }
+                self.history_samples.push_back(content.to_u16() as u64);
+            }
             let table = &mut self.tables[table_index as usize];
             table.set_cell(address, content);
+            table.last_read = Some(now);
         }
         self.refresh_queue_table();
     }
 
+    fn check_alarms(&mut self, table: SelectedTopTab, address: u16, value: u16) {
+        let changes = self.alarms.evaluate(table, address, value);
+        if changes.iter().any(|(active, _)| *active) {
+            let _ = self.beep();
+        }
+        for (active, condition) in changes {
+            let state = match active {
+                true => "TRIGGERED",
+                false => "Cleared",
+            };
+            self.log(format!("{state} {condition} (read {value})"));
+        }
+    }
+
+    // Timestamped entry in the Alarms tab's log, newest first
+    fn log(&mut self, message: String) {
+        let secs = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_secs())
+            .unwrap_or_default();
+        if self.alarm_log.len() == ALARM_LOG_LENGTH {
+            self.alarm_log.pop_back();
+        }
+        self.alarm_log.push_front(format!(
+            "{:02}:{:02}:{:02} UTC {message}",
+            secs / 3600 % 24,
+            secs / 60 % 60,
+            secs % 60,
+        ));
+    }
+
+    fn sync_history_cell(&mut self) {
+        // History follows the selected cell, starting over whenever it moves
+        let table = &self.tables[self.selected_top_tab as usize];
+        let selected_cell = Some((self.selected_top_tab, table.table_address));
+        if self.history_cell != selected_cell {
+            self.history_cell = selected_cell;
+            self.history_samples.clear();
+        }
+    }
+
     fn render(&mut self, frame: &mut Frame) {
+        let area = frame.area();
+        if area.width < MIN_TERMINAL_WIDTH || area.height < MIN_TERMINAL_HEIGHT {
+            self.render_too_small(frame, area);
+            return;
+        }
+
         let [header_area, inner_area, footer_area] = Layout::vertical([
             Constraint::Length(1),
             Constraint::Min(0),
@@ -1069,7 +2909,7 @@ impl App {
         .areas(frame.area());
 
         let [top_area, bottom_area] =
-            Layout::vertical([Constraint::Length(11), Constraint::Min(0)]).areas(inner_area);
+            Layout::vertical([Constraint::Length(12), Constraint::Min(0)]).areas(inner_area);
 
         self.set_colors();
 
@@ -1092,26 +2932,49 @@ impl App {
                 match popup_type {
                     PopupType::Connection => self.render_connection_popup(frame, frame.area()),
                     PopupType::Edit => self.render_edit_popup(frame, frame.area()),
+                    PopupType::BitEdit => self.render_bit_popup(frame, frame.area()),
+                    PopupType::Ascii => self.render_ascii_popup(frame, frame.area()),
                     PopupType::Error(message) => {
                         self.render_error_popup(frame, frame.area(), message)
                     }
+                    PopupType::ConfirmQuit => self.render_quit_popup(frame, frame.area()),
+                    PopupType::Settings => self.render_settings_popup(frame, frame.area()),
                     PopupType::Goto => self.render_goto_popup(frame, frame.area()),
+                    PopupType::GotoPage => self.render_page_popup(frame, frame.area()),
+                    PopupType::ReadRange => self.render_range_popup(frame, frame.area()),
+                    PopupType::Search => self.render_search_popup(frame, frame.area()),
+                    PopupType::SaveCapture => self.render_capture_popup(frame, frame.area()),
+                    PopupType::RawPdu => self.render_raw_popup(frame, frame.area()),
+                    PopupType::Fill => self.render_fill_popup(frame, frame.area()),
+                    PopupType::ImportCsv => self.render_import_popup(frame, frame.area()),
+                    PopupType::LoadSnapshot => self.render_snapshot_popup(frame, frame.area()),
+                    PopupType::DeviceIdentification(lines) => {
+                        self.render_device_popup(frame, frame.area(), lines)
+                    }
                     PopupType::SaveMacro(save_macro_mode) => {
                         self.render_macro_popup(frame, frame.area(), save_macro_mode)
                     }
                 }
             }
         }
+
+        if let Some(flash_until) = self.flash_until {
+            match Instant::now() < flash_until {
+                true => {
+                    let flash_area = match self.app_mode {
+                        AppMode::Help => frame.area(),
+                        _ => footer_area,
+                    };
+                    frame
+                        .buffer_mut()
+                        .set_style(flash_area, Style::new().reversed());
+                }
+                false => self.flash_until = None,
+            }
+        }
     }
 
     fn render_header(&self, frame: &mut Frame, header_area: Rect) {
-        let [title_version_area, _, address_area] = Layout::horizontal([
-            Constraint::Length(22),
-            Constraint::Fill(1),
-            Constraint::Length(32),
-        ])
-        .areas(header_area);
-
         let title_version = Line::from(vec![Span::styled(
             format!("Magic ModBus - v{}", env!("CARGO_PKG_VERSION")),
             Style::default(),
@@ -1121,40 +2984,121 @@ impl App {
         let selected_tab_index = self.selected_top_tab as usize;
         let table = &self.tables[selected_tab_index];
 
-        let memory_address = match self.selected_top_tab {
-            SelectedTopTab::Coils => format!("0x0{:04X}", table.table_address + 1),
-            SelectedTopTab::DiscreteInputs => format!("0x1{:04X}", table.table_address + 1),
-            SelectedTopTab::InputRegisters => format!("0x3{:04X}", table.table_address + 1),
-            SelectedTopTab::HoldingRegisters => format!("0x4{:04X}", table.table_address + 1),
-        };
+        let memory_address = self.selected_top_tab.memory_address(table.table_address);
 
         let ip_section_style = match self.connection_status {
             ConnectionStatus::Connected => self.colors.connection_connected_fg,
-            ConnectionStatus::NotConnected => self.colors.connection_not_selected_fg,
+            ConnectionStatus::Connecting
+            | ConnectionStatus::NotConnected
+            | ConnectionStatus::IdleDisconnected
+            | ConnectionStatus::Offline => self.colors.connection_not_selected_fg,
         };
 
-        let ip_section_content = match (self.current_ip_address, self.current_port) {
-            (Some(address), Some(port)) => format!("{}:{}", address, port),
+        let ip_section_content = match (
+            self.current_ip_address,
+            self.current_port,
+            &self.connection_status,
+        ) {
+            (Some(address), Some(port), ConnectionStatus::Offline) => {
+                format!("{} (Offline)", SocketAddr::new(address, port))
+            }
+            (Some(address), Some(port), ConnectionStatus::Connecting) => {
+                format!("{} (Connecting...)", SocketAddr::new(address, port))
+            }
+            (_, _, ConnectionStatus::Connecting) => String::from("Connecting..."),
+            (Some(address), Some(port), _) => SocketAddr::new(address, port).to_string(),
+            (_, _, ConnectionStatus::IdleDisconnected) => String::from("Disconnected (Idle)"),
             _ => String::from("Not Connected!"),
         };
 
-        let ip_cell_address = Line::from(vec![
-            Span::styled(ip_section_content, ip_section_style),
-            Span::raw(" | "),
-            Span::styled(memory_address, Style::default()),
+        let mut ip_cell_address = Line::default();
+        if self.recording {
+            ip_cell_address.push_span(Span::styled(
+                format!("REC {} | ", self.capture.len()),
+                Style::new().fg(Color::Red).bold(),
+            ));
+        }
+        ip_cell_address.push_span(Span::styled(ip_section_content, ip_section_style));
+        ip_cell_address.push_span(Span::raw(" | "));
+        if let (ConnectionStatus::Connected, Some(summary)) = (
+            &self.connection_status,
+            self.latency.summary(Instant::now()),
+        ) {
+            ip_cell_address.push_span(Span::styled(
+                summary,
+                self.latency_style(Style::new().fg(ip_section_style)),
+            ));
+            ip_cell_address.push_span(Span::raw(" | "));
+        }
+        if let Some(rule) = self.current_scaling_rule() {
+            let raw = table.current_cell().display_content().to_u16();
+            ip_cell_address.push_span(Span::raw(format!(
+                "{} {} ({:05}) | ",
+                rule.format(raw),
+                rule.unit,
+                raw
+            )));
+        }
+        // A 32-bit type from the device profile joins the pair its value starts at
+        let (wide_type, pair_start) = match self
+            .profile
+            .data_type(self.selected_top_tab, table.table_address)
+        {
+            Some((data_type, value_start)) if data_type.width() == 2 => {
+                (Some(data_type), value_start)
+            }
+            _ => (None, table.table_address),
+        };
+        if let (SelectedTopTab::InputRegisters | SelectedTopTab::HoldingRegisters, true) =
+            (self.selected_top_tab, pair_start < u16::MAX)
+        {
+            let pair = table.get_visible_data(pair_start, pair_start + 1);
+            let words = [
+                pair[0].display_content().to_u16(),
+                pair[1].display_content().to_u16(),
+            ];
+            let joined = self.word_order.join(words);
+            let joined = match (wide_type, self.display_mode) {
+                (Some(data_type), _) => data_type.format_wide(joined),
+                (None, DisplayMode::Signed) => (joined as i32).to_string(),
+                (None, DisplayMode::Unsigned | DisplayMode::Binary) => joined.to_string(),
+            };
+            ip_cell_address.push_span(Span::raw(format!("{} {} | ", self.word_order, joined)));
+        }
+        if let Some(name) = self.labels.get(self.selected_top_tab, table.table_address) {
+            ip_cell_address.push_span(Span::styled(format!("{name} | "), Style::default().bold()));
+        }
+        ip_cell_address.push_span(Span::styled(memory_address, Style::default()));
+        let ip_cell_address = ip_cell_address.right_aligned();
+
+        let [title_version_area, _, address_area] = Layout::horizontal([
+            Constraint::Length(22),
+            Constraint::Fill(1),
+            Constraint::Length(ip_cell_address.width() as u16),
         ])
-        .right_aligned();
+        .areas(header_area);
 
         frame.render_widget(title_version, title_version_area);
         frame.render_widget(ip_cell_address, address_area);
     }
 
+    // Spikes and stalled requests stand out from the normal latency readout
+    fn latency_style(&self, style: Style) -> Style {
+        match self.latency.stalled(Instant::now()).is_some() || self.latency.is_spike() {
+            true => Style::new().fg(Color::Red).bold(),
+            false => style,
+        }
+    }
+
     fn render_footer(&self, frame: &mut Frame, footer_area: Rect) {
         let lower_footer_text = match self.current_focus {
             CurrentFocus::Top => FOOTER_TEXT[1],
             CurrentFocus::Bottom => match self.selected_bottom_tab {
                 SelectedBottomTab::Connection => FOOTER_TEXT[2],
                 SelectedBottomTab::Queue => FOOTER_TEXT[3],
+                SelectedBottomTab::Watch => FOOTER_TEXT[6],
+                SelectedBottomTab::History => FOOTER_TEXT[7],
+                SelectedBottomTab::Alarms => FOOTER_TEXT[8],
             },
         };
         let test_footer = Text::from(vec![
@@ -1165,16 +3109,30 @@ impl App {
         frame.render_widget(test_footer, footer_area);
     }
 
-    fn render_top_areas(&self, frame: &mut Frame, top_area: Rect) {
-        let [tab_area, cell_area] =
-            Layout::vertical([Constraint::Length(1), Constraint::Min(0)]).areas(top_area);
+    fn render_top_areas(&mut self, frame: &mut Frame, top_area: Rect) {
+        let [tab_area, cell_area, details_area] = Layout::vertical([
+            Constraint::Length(1),
+            Constraint::Min(0),
+            Constraint::Length(1),
+        ])
+        .areas(top_area);
 
         let area_style = match self.current_focus {
             CurrentFocus::Top => self.colors.section_selected_fg,
             CurrentFocus::Bottom => self.colors.section_unselected_fg,
         };
 
-        let titles = SelectedTopTab::iter().map(SelectedTopTab::title);
+        // [P] and [T] mark tables that refresh on page change and every tick
+        let titles = SelectedTopTab::iter().map(|tab| {
+            let table = &self.tables[tab as usize];
+            let flags = match (table.page_refresh, table.tick_refresh) {
+                (false, false) => "",
+                (true, false) => " [P]",
+                (false, true) => " [T]",
+                (true, true) => " [PT]",
+            };
+            tab.title(flags)
+        });
         let selected_tab_index = self.selected_top_tab as usize;
         let top_tabs = Tabs::new(titles)
             .select(selected_tab_index)
@@ -1183,6 +3141,49 @@ impl App {
 
         frame.render_widget(top_tabs, tab_area);
         self.render_table(frame, cell_area);
+        frame.render_widget(
+            Line::styled(self.cell_details(), area_style).centered(),
+            details_area,
+        );
+    }
+
+    // Everything known about the selected cell, from what has already been read
+    fn cell_details(&self) -> String {
+        let table = &self.tables[self.selected_top_tab as usize];
+        let address = table.table_address;
+        let mut details = vec![
+            format!("Address {}", address as u32 + 1),
+            format!("Wire {address}"),
+        ];
+        if let Some(name) = self.labels.get(self.selected_top_tab, address) {
+            details.push(name.to_string());
+        }
+        match table.data.get(&address) {
+            _ if table.illegal_addresses.contains(&address) => {
+                details.push(String::from("Rejected by the device"))
+            }
+            None => details.push(String::from("Not read")),
+            Some(cell) => {
+                let value = cell.display_content().to_u16();
+                match self.selected_top_tab {
+                    SelectedTopTab::Coils | SelectedTopTab::DiscreteInputs => {
+                        details.push(format!("Value {value}"))
+                    }
+                    SelectedTopTab::InputRegisters | SelectedTopTab::HoldingRegisters => {
+                        details.push(format!("Dec {value}"));
+                        details.push(format!("Hex 0x{value:04X}"));
+                        details.push(format!("Bin {}", DisplayMode::Binary.format(value)));
+                    }
+                }
+                if let CellState::Queued = cell.state {
+                    details.push(format!(
+                        "Queued, device has {}",
+                        cell.original_content.to_u16()
+                    ));
+                }
+            }
+        }
+        details.join(" | ")
     }
 
     fn render_bottom_areas(&mut self, frame: &mut Frame, bottom_area: Rect) {
@@ -1194,7 +3195,14 @@ impl App {
             CurrentFocus::Bottom => self.colors.section_selected_fg,
         };
 
-        let titles = SelectedBottomTab::iter().map(SelectedBottomTab::title);
+        let queue_count = self.queue_count();
+        let titles = SelectedBottomTab::iter().map(|tab| match (tab, queue_count) {
+            (SelectedBottomTab::Queue, (0, 0)) => tab.title(""),
+            (SelectedBottomTab::Queue, (bits, words)) => {
+                tab.title(&format!(" ({}: {bits}C {words}R)", bits + words))
+            }
+            _ => tab.title(""),
+        });
         let selected_tab_index = self.selected_bottom_tab as usize;
         let bottom_tabs = Tabs::new(titles)
             .select(selected_tab_index)
@@ -1206,6 +3214,9 @@ impl App {
         match self.selected_bottom_tab {
             SelectedBottomTab::Connection => self.render_connection_tab(frame, main_area),
             SelectedBottomTab::Queue => self.render_queue_tab(frame, main_area),
+            SelectedBottomTab::Watch => self.render_watch_tab(frame, main_area),
+            SelectedBottomTab::History => self.render_history_tab(frame, main_area),
+            SelectedBottomTab::Alarms => self.render_alarms_tab(frame, main_area),
         }
     }
 
@@ -1238,10 +3249,42 @@ impl App {
             Some(port) => port.to_string(),
         };
 
+        let latency = match (
+            &self.connection_status,
+            self.latency.summary(Instant::now()),
+        ) {
+            (ConnectionStatus::Connected, Some(summary)) => summary,
+            _ => String::from("N\\A"),
+        };
+
+        let max_latency = match (&self.connection_status, self.latency.max()) {
+            (ConnectionStatus::Connected, Some(max)) => format!("{} ms", max.as_millis()),
+            _ => String::from("N\\A"),
+        };
+
+        let uptime = match self.connected_since {
+            Some(since) => {
+                let secs = since.elapsed().as_secs();
+                format!("{}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60)
+            }
+            None => String::from("N\\A"),
+        };
+
         let connection_stats = Paragraph::new(vec![
             Line::from(format!("Connection Status: {}", self.connection_status)),
             Line::from(format!("Target Address: {}", address)),
             Line::from(format!("Target Port: {}", port)),
+            Line::from(format!("Connected For: {}", uptime)),
+            Line::from(format!("Dropped Connections: {}", self.connection_drops)),
+            Line::from(match self.last_connection {
+                Some(addr) => format!("Last Connection: {addr} (R to reconnect)"),
+                None => String::from("Last Connection: N\\A"),
+            }),
+            Line::from(vec![
+                Span::raw("Latency: "),
+                Span::styled(latency, self.latency_style(Style::new())),
+            ]),
+            Line::from(format!("Slowest Recent Request: {}", max_latency)),
         ]);
 
         let connection_button = Paragraph::new(vec![
@@ -1286,23 +3329,35 @@ impl App {
         if !self.queue_table_data.is_empty() {
             let mut rows = vec![];
             for queue_item in self.queue_table_data.iter() {
+                let is_marked = self
+                    .queue_marked_items
+                    .contains(&(queue_item.table_index, queue_item.address));
                 rows.push(Row::new(vec![
+                    String::from(if is_marked { "*" } else { " " }),
                     queue_item.cell.table_type.to_string(),
-                    format!("0x{:04X}", queue_item.address + 1),
+                    queue_item.memory_address(),
                     queue_item.original_content(),
                     "->".to_string(),
                     queue_item.queued_content(),
+                    queue_item.cell.write_mode.function_code().to_string(),
+                    self.labels
+                        .get(queue_item.cell.table_type, queue_item.address)
+                        .unwrap_or_default()
+                        .to_string(),
                 ]));
             }
 
             let table = Table::new(
                 rows,
                 [
+                    Constraint::Length(1),
                     Constraint::Length(17),
-                    Constraint::Length(6),
+                    Constraint::Length(7),
                     Constraint::Length(5),
                     Constraint::Length(2),
                     Constraint::Length(5),
+                    Constraint::Length(4),
+                    Constraint::Fill(1),
                 ],
             )
             .block(Block::bordered().style(area_style))
@@ -1310,7 +3365,7 @@ impl App {
 
             frame.render_stateful_widget(table, area, &mut self.queue_table_state);
 
-            if area.height - 2 < self.queue_table_data.len() as u16 {
+            if area.height.saturating_sub(2) < self.queue_table_data.len() as u16 {
                 frame.render_stateful_widget(
                     Scrollbar::default().orientation(ScrollbarOrientation::VerticalRight),
                     area.inner(Margin {
@@ -1328,169 +3383,251 @@ impl App {
         }
     }
 
-    fn render_help_menu(&self, frame: &mut Frame, area: Rect) {
-        let help_menu_block = Block::bordered()
-            .title(format!("Magic ModBus - Help Menu (Page {}/2)", self.help_menu_page + 1))
-            .title_alignment(Alignment::Center)
-            .style(self.colors.section_selected_fg);
-        frame.render_widget(help_menu_block, area);
+    fn render_watch_tab(&mut self, frame: &mut Frame, area: Rect) {
+        let area_style = match self.current_focus {
+            CurrentFocus::Top => self.colors.section_unselected_fg,
+            CurrentFocus::Bottom => self.colors.section_selected_fg,
+        };
 
-        let trimmed_area = trim_borders(area);
+        if self.watch_list.is_empty() {
+            frame.render_widget(
+                Paragraph::new("No Pinned Addresses (press F on a cell to pin it)")
+                    .block(Block::bordered().style(area_style)),
+                area,
+            );
+            return;
+        }
 
-        let [general_area, table_area] = Layout::vertical([
-            Constraint::Length(6),
-            Constraint::Min(8),
-        ])
-            .areas(trimmed_area);
+        let rows = self.watch_list.iter().map(|&(table, address)| {
+            let raw = self.tables[table as usize]
+                .data
+                .get(&address)
+                .map(|cell| cell.display_content().to_u16());
+            let value = match (raw, self.scaling.get(table, address)) {
+                (Some(raw), Some(rule)) => format!("{} {}", rule.format(raw), rule.unit),
+                (Some(raw), None) => format!("{:05}", raw),
+                (None, _) => String::from("-----"),
+            };
+            Row::new(vec![
+                table.to_string(),
+                table.memory_address(address),
+                value,
+                self.labels
+                    .get(table, address)
+                    .unwrap_or_default()
+                    .to_string(),
+            ])
+        });
 
-        let [connection_area, queue_area, _, help_hint_area] = Layout::vertical([
-            Constraint::Length(8),
-            Constraint::Length(8),
-            Constraint::Fill(1),
-            Constraint::Length(1),
-        ])
-            .areas(trimmed_area);
+        let table = Table::new(
+            rows,
+            [
+                Constraint::Length(17),
+                Constraint::Length(7),
+                Constraint::Length(14),
+                Constraint::Fill(1),
+            ],
+        )
+        .block(Block::bordered().style(area_style))
+        .row_highlight_style(Style::new().add_modifier(Modifier::REVERSED));
 
-        // General Controls Section
-        let general_help = Paragraph::new(vec![
-            Line::from(vec![
-                Span::styled("ESC", Style::default().bold()),
-                Span::raw(" - Quit Application"),
-            ]),
-            Line::from(vec![
-                Span::styled("TAB", Style::default().bold()),
-                Span::raw(" - Switch Focus (Top/Bottom panels)"),
-            ]),
-            Line::from(vec![
-                Span::styled("?", Style::default().bold()),
-                Span::raw(" - Toggle Help Menu"),
-            ]),
-            Line::from(vec![
-                Span::styled("Q/E", Style::default().bold()),
-                Span::raw(" - Previous/Next Tab"),
-            ]),
-        ])
-        .block(
-            Block::new()
-                .borders(Borders::BOTTOM)
-                .title("General Controls"),
-        );
+        frame.render_stateful_widget(table, area, &mut self.watch_table_state);
+    }
 
-        // Table Navigation Section
-        let table_help = Paragraph::new(vec![
-            Line::from("Navigation (when focused on top panel):"),
-            Line::from(vec![
-                Span::styled("W/A/S/D", Style::default().bold()),
-                Span::raw(" or "),
-                Span::styled("Arrow Keys", Style::default().bold()),
-                Span::raw(" - Move cursor"),
-            ]),
-            Line::from(vec![
-                Span::styled("Shift+W/S", Style::default().bold()),
-                Span::raw(" or "),
-                Span::styled("Shift+↑/↓", Style::default().bold()),
-                Span::raw(" - Page up/down"),
-            ]),
-            Line::from(vec![
-                Span::styled("G", Style::default().bold()),
-                Span::raw(" - Go to address (1-65535)"),
-            ]),
-            Line::raw(""),
-            Line::from("Data Operations:"),
-            Line::from(vec![
-                Span::styled("SPACE", Style::default().bold()),
-                Span::raw(" - Toggle Coils / Edit Holding Registers"),
-            ]),
-            Line::from(vec![
-                Span::styled("ENTER", Style::default().bold()),
-                Span::raw(" - Apply all queued changes"),
-            ]),
-            Line::from(vec![
-                Span::styled("U", Style::default().bold()),
-                Span::raw(" - Revert current cell"),
-            ]),
-            Line::from(vec![
-                Span::styled("R", Style::default().bold()),
-                Span::raw(" - Read current page"),
-            ]),
-            Line::from(vec![
-                Span::styled("Shift+R", Style::default().bold()),
-                Span::raw(" - Toggle auto page refresh"),
-            ]),
-            Line::from(vec![
-                Span::styled("Shift+T", Style::default().bold()),
-                Span::raw(" - Toggle auto tick refresh"),
-            ]),
-        ])
-        .block(
-            Block::new()
-                // .borders(Borders::BOTTOM)
-                .title("Table Navigation & Data"),
+    fn render_history_tab(&mut self, frame: &mut Frame, area: Rect) {
+        let area_style = match self.current_focus {
+            CurrentFocus::Top => self.colors.section_unselected_fg,
+            CurrentFocus::Bottom => self.colors.section_selected_fg,
+        };
+
+        self.sync_history_cell();
+        let Some((table, address)) = self.history_cell else {
+            return;
+        };
+
+        let mut title = format!(" {}", table.memory_address(address));
+        if let Some(name) = self.labels.get(table, address) {
+            title.push_str(&format!(" - {name}"));
+        }
+        if let (Some(min), Some(max), Some(last)) = (
+            self.history_samples.iter().min(),
+            self.history_samples.iter().max(),
+            self.history_samples.back(),
+        ) {
+            title.push_str(&format!(" | Min {min} | Max {max} | Last {last}"));
+        }
+        title.push(' ');
+        let block = Block::bordered().title(title).style(area_style);
+
+        if self.history_samples.is_empty() {
+            frame.render_widget(
+                Paragraph::new("No Samples Yet (enable tick refresh with Shift+T)").block(block),
+                area,
+            );
+            return;
+        }
+
+        // Show the most recent samples that fit in the pane
+        let width = area.width.saturating_sub(2) as usize;
+        let skip = self.history_samples.len().saturating_sub(width);
+        let samples: Vec<u64> = self.history_samples.iter().skip(skip).copied().collect();
+
+        let sparkline = Sparkline::default()
+            .block(block)
+            .data(&samples)
+            .style(self.colors.table_selected_cell_fg);
+        frame.render_widget(sparkline, area);
+    }
+
+    fn render_alarms_tab(&self, frame: &mut Frame, area: Rect) {
+        let area_style = match self.current_focus {
+            CurrentFocus::Top => self.colors.section_unselected_fg,
+            CurrentFocus::Bottom => self.colors.section_selected_fg,
+        };
+
+        // The log also collects background failures, so it stays visible without alarms
+        if self.alarms.is_empty() && self.alarm_log.is_empty() {
+            frame.render_widget(
+                Paragraph::new("No Alarms Defined (load a file with --alarms)")
+                    .block(Block::bordered().style(area_style)),
+                area,
+            );
+            return;
+        }
+
+        let [alarm_area, log_area] =
+            Layout::horizontal([Constraint::Percentage(50), Constraint::Percentage(50)])
+                .areas(area);
+
+        let rows = self.alarms.iter().map(|alarm| {
+            let state = match alarm.active {
+                true => Span::styled("ALARM", Style::new().fg(Color::Red).bold()),
+                false => Span::raw("OK"),
+            };
+            Row::new(vec![
+                Cell::from(state),
+                Cell::from(alarm.condition()),
+                Cell::from(
+                    self.labels
+                        .get(alarm.table, alarm.address)
+                        .unwrap_or_default()
+                        .to_string(),
+                ),
+            ])
+        });
+        let alarm_table = Table::new(
+            rows,
+            [
+                Constraint::Length(5),
+                Constraint::Length(18),
+                Constraint::Fill(1),
+            ],
         )
-        .wrap(Wrap { trim: true });
+        .block(Block::bordered().title(" Alarms ").style(area_style));
+        frame.render_widget(alarm_table, alarm_area);
 
-        // Connection Tab Section
-        let connection_help = Paragraph::new(vec![
-            Line::from("When focused on Connection tab (bottom panel):"),
-            Line::from(vec![
-                Span::styled("A/D", Style::default().bold()),
-                Span::raw(" or "),
-                Span::styled("←/→", Style::default().bold()),
-                Span::raw(" - Select connection button"),
-            ]),
-            Line::from(vec![
-                Span::styled("ENTER", Style::default().bold()),
-                Span::raw(" - New Connection or Disconnect"),
-            ]),
-            Line::raw(""),
-            Line::from("In Connection Popup:"),
-            Line::from("• Enter IP address and port"),
-            Line::from("• Use UP/DOWN/TAB to switch fields"),
-        ])
-        .block(
-            Block::new()
-                .borders(Borders::BOTTOM)
-                .title("Connection Controls"),
-        );
+        let log = Paragraph::new(
+            self.alarm_log
+                .iter()
+                .map(|entry| Line::raw(entry.as_str()))
+                .collect::<Vec<Line>>(),
+        )
+        .block(Block::bordered().title(" Log ").style(area_style));
+        frame.render_widget(log, log_area);
+    }
 
-        // Queue Tab Section
-        let queue_help = Paragraph::new(vec![
-            Line::from("When focused on Queue tab (bottom panel):"),
-            Line::from(vec![
-                Span::styled("↑/↓", Style::default().bold()),
-                Span::raw(" - Navigate queue items"),
-            ]),
-            Line::from(vec![
-                Span::styled("G", Style::default().bold()),
-                Span::raw(" - Go to selected queue item's address"),
-            ]),
-            Line::from(vec![
-                Span::styled("R", Style::default().bold()),
-                Span::raw(" - Revert selected queue item"),
-            ]),
-            Line::from(vec![
-                Span::styled("M", Style::default().bold()),
-                Span::raw(" - Save queue as macro file"),
-            ]),
-        ])
-        .block(Block::new().title("Queue Controls"));
+    fn render_help_menu(&mut self, frame: &mut Frame, area: Rect) {
+        let help_menu_block = Block::bordered()
+            .title(format!(
+                "Magic ModBus - Help Menu (Page {}/{})",
+                self.help_menu_page + 1,
+                HELP_PAGES.len()
+            ))
+            .title_alignment(Alignment::Center)
+            .style(self.colors.section_selected_fg);
+        frame.render_widget(help_menu_block, area);
 
-        let help_hint = Paragraph::new("Press 'Tab' to change pages").centered();
+        let [sections_area, help_hint_area] =
+            Layout::vertical([Constraint::Fill(1), Constraint::Length(1)])
+                .areas(trim_borders(area));
+
+        let help_hint = match self.help_searching {
+            true => Paragraph::new(Line::from(vec![
+                Span::raw("Search: "),
+                Span::from(self.help_search_input.as_str()),
+                Span::styled(" ", Style::new().reversed()),
+            ])),
+            false => Paragraph::new("(Tab) Change pages | (↑ ↓) Scroll | (/) Search").centered(),
+        };
+        frame.render_widget(help_hint, help_hint_area);
 
-        match self.help_menu_page {
-            0 => {
-                frame.render_widget(general_help, general_area);
-                frame.render_widget(table_help, table_area);
+        if !self.help_search_input.is_empty() {
+            // Searches both pages, keeping only lines that mention the filter
+            let results: Vec<Line> = HELP_PAGES
+                .iter()
+                .flat_map(|page| page.iter())
+                .flat_map(|context| keymap::help_lines(*context))
+                .filter_map(|line| {
+                    highlight_matches(&line, &self.help_search_input, Style::new().reversed())
+                })
+                .collect();
+            let results_help = match results.is_empty() {
+                true => Paragraph::new("No matching keybindings"),
+                false => Paragraph::new(results),
             }
-            _ => {
-                frame.render_widget(connection_help, connection_area);
-                frame.render_widget(queue_help, queue_area);
+            .block(Block::new().title(format!("Matches for \"{}\"", self.help_search_input)))
+            .wrap(Wrap { trim: true });
+
+            frame.render_widget(results_help, sections_area);
+            return;
+        }
+
+        // Sections are generated from the keymap and stacked as tall as their wrapped lines,
+        // so a page longer than the terminal scrolls instead of losing its last bindings
+        let width = sections_area.width;
+        let sections: Vec<(Paragraph, u16)> = HELP_PAGES[self.help_menu_page as usize]
+            .iter()
+            .map(|context| {
+                let section_help = Paragraph::new(keymap::help_lines(*context))
+                    .block(Block::new().borders(Borders::BOTTOM).title(context.title()))
+                    .wrap(Wrap { trim: true });
+                let height = section_help.line_count(width) as u16;
+                (section_help, height)
+            })
+            .collect();
+        let page_height: u16 = sections.iter().map(|(_, height)| height).sum();
+        let mut page = Buffer::empty(Rect::new(0, 0, width, page_height));
+        let mut y = 0;
+        for (section_help, height) in sections {
+            section_help.render(Rect::new(0, y, width, height), &mut page);
+            y += height;
+        }
+
+        let max_scroll = page_height.saturating_sub(sections_area.height);
+        self.help_menu_scroll = self.help_menu_scroll.min(max_scroll);
+        let buffer = frame.buffer_mut();
+        for row in 0..sections_area.height.min(page_height) {
+            for column in 0..width {
+                buffer[(sections_area.x + column, sections_area.y + row)] =
+                    page[(column, self.help_menu_scroll + row)].clone();
             }
         }
-        frame.render_widget(help_hint, help_hint_area);
+
+        if max_scroll > 0 {
+            let mut scroll_state = ScrollbarState::new(max_scroll as usize + 1)
+                .position(self.help_menu_scroll as usize);
+            frame.render_stateful_widget(
+                Scrollbar::default().orientation(ScrollbarOrientation::VerticalRight),
+                area.inner(Margin {
+                    vertical: 1,
+                    horizontal: 0,
+                }),
+                &mut scroll_state,
+            );
+        }
     }
 
-    fn render_table(&self, frame: &mut Frame, table_area: Rect) {
+    fn render_table(&mut self, frame: &mut Frame, table_area: Rect) {
         let selected_tab_index = self.selected_top_tab as usize;
         let mut table = self.tables[selected_tab_index].to_owned();
         let area_style = match self.current_focus {
@@ -1505,16 +3642,68 @@ impl App {
                 CurrentFocus::Bottom => self.colors.table_unselected_cell_fg,
             });
 
-        let block = Block::bordered().style(area_style);
-
         let (_row_height, column_length, max_rows, max_cols) = self.get_table_stats(table_area);
-        table.table_rows = max_rows;
-        table.table_cols = max_cols;
+        // A pinned page keeps its shape and scrolls to the selected cell when it doesn't fit.
+        // The list view always scrolls, one address per row
+        if self.page_size.is_none() && table.list_view.is_none() {
+            table.table_rows = max_rows;
+            table.table_cols = max_cols;
+        }
+
+        let page_indicator = format!(" Page {} / {} ", table.page_offset + 1, table.page_count());
+        let last_read = match table.last_read {
+            Some(last_read) => format!(" Last read: {}s ago ", last_read.elapsed().as_secs()),
+            None => String::from(" Never read "),
+        };
+        let mut block = Block::bordered()
+            .title(Line::from(page_indicator).right_aligned())
+            .title_bottom(Line::from(last_read).right_aligned())
+            .style(area_style);
+        if let SelectedTopTab::InputRegisters | SelectedTopTab::HoldingRegisters =
+            self.selected_top_tab
+        {
+            block = block.title(format!(" {} ", self.display_mode));
+        }
+        // Values the device has returned, queued changes aren't on the device yet
+        let live_value = |address: u16| match table.illegal_addresses.contains(&address) {
+            true => None,
+            false => table
+                .data
+                .get(&address)
+                .map(|cell| cell.original_content.to_u16()),
+        };
+        if let Some(snapshot) = &self.snapshot {
+            let (differ, unread) = snapshot.compare(self.selected_top_tab, live_value);
+            block = block.title_bottom(format!(
+                " {}: {differ} differ, {unread} not read ",
+                snapshot.name
+            ));
+        }
 
-        let start_index = table.page_offset * table.page_size();
-        let end_index = usize::min(start_index + table.page_size(), (u16::MAX - 1) as usize);
+        if table.list_view.is_some() {
+            self.render_list_view(frame, table_area, block, &table, selected_cell_style);
+            return;
+        }
 
-        let visible_data = table.get_visible_data(start_index as u16, end_index as u16);
+        // A page wider than the window scrolls sideways to the selected column
+        let visible_cols = ((table_area.width.saturating_sub(2) as usize) / column_length)
+            .clamp(1, table.table_cols);
+        let selected_col = table.table_state.selected_column().unwrap_or(0);
+        let column_offset = &mut self.tables[selected_tab_index].column_offset;
+        *column_offset =
+            (*column_offset).clamp(selected_col.saturating_sub(visible_cols - 1), selected_col);
+        let column_offset = *column_offset;
+        table
+            .table_state
+            .select_column(Some(selected_col - column_offset));
+
+        let (start_index, end_index) = table.page_range();
+        let start_index = start_index as usize;
+
+        let mut visible_data = table.get_visible_data(start_index as u16, end_index);
+        // The last page keeps its shape, cells past the device's end are filled in dimmed
+        visible_data.resize(table.page_size(), TableCell::new(table.table_type));
+        let selection = table.selection();
 
         let table_rows = visible_data
             .chunks(table.table_cols)
@@ -1523,32 +3712,62 @@ impl App {
                 let row = row_chunk
                     .iter()
                     .enumerate()
+                    .skip(column_offset)
+                    .take(visible_cols)
                     .map(|(j, cell)| {
                         let row_parity = i % 2;
                         let cell_parity = j % 2;
+                        let cell_index = start_index + i * table.table_cols + j;
+                        if !table.index_exists(cell_index) {
+                            return Cell::from(Line::raw("-").centered()).style(
+                                Style::new()
+                                    .fg(Color::DarkGray)
+                                    .add_modifier(Modifier::DIM | Modifier::CROSSED_OUT),
+                            );
+                        }
+                        let cell_address = cell_index as u16;
+                        let cell_value = cell.display_content().to_u16();
                         let cell_content = match self.selected_top_tab {
-                            SelectedTopTab::Coils | SelectedTopTab::DiscreteInputs => {
-                                Line::raw(format!(
-                                    "{}",
-                                    match &cell.state {
-                                        CellState::Normal => cell.original_content.to_u16(),
-                                        CellState::Queued => cell.queued_content.to_u16(),
-                                    }
-                                ))
+                            _ if table.illegal_addresses.contains(&cell_address) => Line::raw("?")
                                 .centered()
-                                .style(Style::new().fg(Color::White))
+                                .style(Style::new().fg(Color::DarkGray)),
+                            SelectedTopTab::Coils | SelectedTopTab::DiscreteInputs => {
+                                Line::raw(format!("{}", cell_value))
+                                    .centered()
+                                    .style(Style::new().fg(Color::White))
                             }
                             SelectedTopTab::InputRegisters | SelectedTopTab::HoldingRegisters => {
-                                Line::raw(format!(
-                                    "{:05}",
-                                    match &cell.state {
-                                        CellState::Normal => cell.original_content.to_u16(),
-                                        CellState::Queued => cell.queued_content.to_u16(),
-                                    }
-                                ))
-                                .centered()
-                                .style(Style::new().fg(Color::White))
+                                let typed = self
+                                    .profile
+                                    .data_type(self.selected_top_tab, cell_address)
+                                    .and_then(|(data_type, _)| {
+                                        data_type.format_register(cell_value, self.display_mode)
+                                    });
+                                match (self.scaling.get(self.selected_top_tab, cell_address), typed)
+                                {
+                                    (Some(rule), _) => Line::raw(rule.format(cell_value))
+                                        .centered()
+                                        .style(Style::new().fg(Color::White).italic()),
+                                    (None, Some(typed)) => Line::raw(typed)
+                                        .centered()
+                                        .style(Style::new().fg(Color::White)),
+                                    (None, None) => Line::raw(self.display_mode.format(cell_value))
+                                        .centered()
+                                        .style(Style::new().fg(Color::White)),
+                                }
+                            }
+                        };
+
+                        // Shows where the device has drifted from the loaded snapshot
+                        let cell_content = match self
+                            .snapshot
+                            .as_ref()
+                            .and_then(|snapshot| snapshot.get(self.selected_top_tab, cell_address))
+                        {
+                            Some(saved) if live_value(cell_address).is_some_and(|v| v != saved) => {
+                                cell_content.patch_style(Style::new().fg(Color::LightMagenta))
                             }
+                            _ => cell_content,
                         };
 
                         let color = match (row_parity + cell_parity) % 2 {
@@ -1562,12 +3781,29 @@ impl App {
                             },
                         };
 
+                        // Active alarms flash between red and the normal background
+                        let color = match self.alarm_blink
+                            && self.alarms.is_active(self.selected_top_tab, cell_address)
+                        {
+                            true => Color::Red,
+                            false => color,
+                        };
+
+                        let color = match selection {
+                            Some((start, end)) if (start..=end).contains(&cell_address) => {
+                                Color::DarkGray
+                            }
+                            _ => color,
+                        };
+
                         match cell.state {
                             CellState::Normal => {
                                 Cell::from(cell_content).style(Style::new().bg(color))
                             }
                             CellState::Queued => Cell::from(cell_content)
                                 .style(Style::new().bg(color).bold().underlined()),
+                            CellState::Asserted => Cell::from(cell_content)
+                                .style(Style::new().bg(color).italic().underlined()),
                         }
                     })
                     .collect::<Vec<Cell>>();
@@ -1575,7 +3811,7 @@ impl App {
             })
             .collect::<Vec<Row>>();
 
-        let widths = vec![Constraint::Length(column_length as u16); table.table_cols];
+        let widths = vec![Constraint::Length(column_length as u16); visible_cols];
 
         let cell_table = Table::new(table_rows, widths)
             .block(block)
@@ -1584,15 +3820,124 @@ impl App {
         frame.render_stateful_widget(cell_table, table_area, &mut table.table_state);
     }
 
-    fn render_connection_popup(&self, frame: &mut Frame, popup_area: Rect) {
-        let area_style = match self.current_focus {
-            CurrentFocus::Top => self.colors.section_unselected_fg,
-            CurrentFocus::Bottom => self.colors.section_selected_fg,
-        };
+    // Every address read so far and the current page, one per row. Scrolls like the Queue
+    // tab, so moving past the page keeps the addresses already read in view
+    fn render_list_view(
+        &mut self,
+        frame: &mut Frame,
+        area: Rect,
+        block: Block,
+        table: &AppTable,
+        selected_style: Style,
+    ) {
+        let (start, end) = table.page_range();
+        let mut addresses: Vec<u16> = table.data.keys().copied().chain(start..=end).collect();
+        addresses.sort_unstable();
+        addresses.dedup();
+        let selected = addresses
+            .binary_search(&table.table_address)
+            .unwrap_or_default();
+
+        // Only the rows on screen are built, a table read end to end has 65536 of them
+        let visible = (area.height.saturating_sub(3) as usize).max(1); // Borders and the header
+        let list_offset = &mut self.tables[self.selected_top_tab as usize].list_offset;
+        *list_offset = (*list_offset).clamp(selected.saturating_sub(visible - 1), selected);
+        let list_offset = *list_offset;
+
+        let header = Row::new(["Address", "Label", "Value", "Queued"]).style(Style::new().bold());
+        let rows = addresses[list_offset..]
+            .iter()
+            .take(visible)
+            .map(|&address| {
+                let cell = table
+                    .data
+                    .get(&address)
+                    .cloned()
+                    .unwrap_or_else(|| TableCell::new(table.table_type));
+                let value = match table.illegal_addresses.contains(&address) {
+                    true => String::from("?"),
+                    false => self.format_value(address, cell.original_content.to_u16()),
+                };
+                let queued = self.format_value(address, cell.queued_content.to_u16());
+                let queued = match cell.state {
+                    CellState::Normal => String::new(),
+                    CellState::Queued => queued,
+                    CellState::Asserted => format!("assert {queued}"),
+                };
+                let value_style = match self.alarms.is_active(self.selected_top_tab, address) {
+                    true => Style::new().fg(Color::Red),
+                    false => Style::new(),
+                };
+                Row::new(vec![
+                    Cell::from(self.selected_top_tab.memory_address(address)),
+                    Cell::from(
+                        self.labels
+                            .get(self.selected_top_tab, address)
+                            .unwrap_or_default(),
+                    ),
+                    Cell::from(value).style(value_style),
+                    Cell::from(queued).style(Style::new().bold()),
+                ])
+            });
+        let widths = [
+            Constraint::Length(9),
+            Constraint::Fill(1),
+            Constraint::Length(22), // Fits a binary register, 0b and four nibbles
+            Constraint::Length(22),
+        ];
 
-        let area = centered_rect(CONNECTION_POPUP_TEXT.len() as u16 + 2, 6, popup_area);
-        frame.render_widget(Clear, area);
-        frame.render_widget(Block::bordered().style(area_style), area);
+        let list = Table::new(rows, widths)
+            .header(header)
+            .block(block)
+            .row_highlight_style(selected_style);
+        let mut list_state = TableState::default().with_selected(Some(selected - list_offset));
+        frame.render_stateful_widget(list, area, &mut list_state);
+
+        if addresses.len() > visible {
+            let mut scroll_state = ScrollbarState::new(addresses.len()).position(selected);
+            frame.render_stateful_widget(
+                Scrollbar::default().orientation(ScrollbarOrientation::VerticalRight),
+                area.inner(Margin {
+                    vertical: 1,
+                    horizontal: 1,
+                }),
+                &mut scroll_state,
+            );
+        }
+    }
+
+    fn render_too_small(&self, frame: &mut Frame, area: Rect) {
+        let message = Paragraph::new(vec![
+            Line::raw("Terminal Too Small"),
+            Line::raw(format!(
+                "{}x{}, needs {MIN_TERMINAL_WIDTH}x{MIN_TERMINAL_HEIGHT}",
+                area.width, area.height
+            )),
+        ])
+        .centered()
+        .wrap(Wrap { trim: true })
+        .style(Style::new().fg(self.colors.section_selected_fg));
+        frame.render_widget(message, centered_rect(area.width, 2, area));
+    }
+
+    fn render_connection_popup(&self, frame: &mut Frame, popup_area: Rect) {
+        let area_style = match self.current_focus {
+            CurrentFocus::Top => self.colors.section_unselected_fg,
+            CurrentFocus::Bottom => self.colors.section_selected_fg,
+        };
+
+        let recent = self.recent_connections.entries();
+        let recent_height = match recent.len() {
+            0 => 0,
+            len => len as u16 + 1,
+        };
+        let area = centered_rect(
+            CONNECTION_POPUP_TEXT.len() as u16 + 2,
+            6 + recent_height,
+            popup_area,
+        );
+        frame.render_widget(Clear, area);
+        frame.render_widget(Block::bordered().style(area_style), area);
 
         let (address_cursor_style, address_field_style) = match self.connecting_popup_field {
             ConnectingField::Address => (
@@ -1612,45 +3957,51 @@ impl App {
 
         // Refit the area to account for the borders
         let trimmed_area = trim_borders(area);
-        let address_line = Line::from(vec![
+        let mut address_spans = vec![
             Span::styled("Address:", address_field_style),
             Span::raw(" "),
-            Span::from(&self.address_input[..self.address_input_cursor]),
-            Span::styled(
-                format!(
-                    "{}",
-                    &self
-                        .address_input
-                        .chars()
-                        .nth(self.address_input_cursor)
-                        .unwrap()
-                ),
-                address_cursor_style,
-            ),
-            Span::from(&self.address_input[(self.address_input_cursor + 1)..]),
-        ]);
-        let port_line = Line::from(vec![
+        ];
+        address_spans.extend(cursor_spans(
+            &self.address_input,
+            self.address_input_cursor,
+            Style::default(),
+            address_cursor_style,
+        ));
+        let mut port_spans = vec![
             Span::raw("   "),
             Span::styled("Port:", port_field_style),
             Span::raw(" "),
-            Span::from(&self.port_input[..self.port_input_cursor]),
-            Span::styled(
-                format!(
-                    "{}",
-                    &self.port_input.chars().nth(self.port_input_cursor).unwrap()
-                ),
-                port_cursor_style,
-            ),
-            Span::from(&self.port_input[(self.port_input_cursor + 1)..]),
-        ]);
-
-        let popup_content = Paragraph::new(vec![
-            Line::from(CONNECTION_POPUP_TEXT),
+        ];
+        port_spans.extend(cursor_spans(
+            &self.port_input,
+            self.port_input_cursor,
+            Style::default(),
+            port_cursor_style,
+        ));
+        let address_line = Line::from(address_spans);
+        let port_line = Line::from(port_spans);
+
+        let mut lines = vec![
+            Line::from(match self.connection_popup_offline {
+                true => OFFLINE_POPUP_TEXT,
+                false => CONNECTION_POPUP_TEXT,
+            }),
             Line::from("-".repeat(CONNECTION_POPUP_TEXT.len())),
             address_line,
             port_line,
-        ])
-        .style(area_style);
+        ];
+        if !recent.is_empty() {
+            lines.push(Line::from("Recent (Up/Down):"));
+        }
+        for (index, addr) in recent.iter().enumerate() {
+            let style = match self.recent_index == Some(index) {
+                true => Style::from(area_style).add_modifier(Modifier::REVERSED),
+                false => Style::from(area_style),
+            };
+            lines.push(Line::styled(format!("  {addr}"), style));
+        }
+
+        let popup_content = Paragraph::new(lines).style(area_style);
 
         frame.render_widget(popup_content, trimmed_area);
     }
@@ -1659,17 +4010,132 @@ impl App {
         let text_style = Style::new()
             .bg(self.colors.table_normal_cell_bg)
             .fg(Color::White);
-        let area = centered_rect(23, 4, popup_area);
+        let title = match self.current_scaling_rule() {
+            None if self.edit_type().is_some() => String::from(" Set Typed Value "),
+            None if self.edit_popup_wide => format!(" 32-bit Value ({}) ", self.word_order),
+            Some(rule) if !rule.unit.is_empty() => format!(" Set Value ({}) ", rule.unit),
+            Some(_) => String::from(" Set Scaled Value "),
+            None if self.edit_popup_base != InputBase::Decimal => {
+                String::from(" 16 or 32-bit Value ")
+            }
+            None => match self.display_mode {
+                DisplayMode::Unsigned => String::from(" 16 or 32-bit Value "),
+                DisplayMode::Signed => String::from(" Signed 16-bit Value "),
+                DisplayMode::Binary => String::from(" Value (0b for binary) "),
+            },
+        };
+        // What the register accepts, so a value that won't fit isn't a surprise on Enter
+        let range = match (self.current_scaling_rule(), self.edit_type()) {
+            (Some(rule), _) => format!(" {} ", rule.range()),
+            (None, Some((data_type, _))) => format!(" {} ", data_type.range()),
+            (None, None) => String::new(),
+        };
+        let area = centered_rect(
+            (self.edit_popup_max_len() as u16 + 3)
+                .max(23)
+                .max(range.len() as u16 + 11),
+            4,
+            popup_area,
+        );
         frame.render_widget(Clear, area);
 
         let popup_content = Paragraph::new(vec![
-            Line::raw(" Set Value (0-65535) "),
-            Line::from(vec![
-                Span::styled(&self.edit_popup_input[..self.edit_popup_cursor], text_style),
-                Span::styled(" ".repeat(5 - self.edit_popup_cursor), text_style),
-            ])
+            Line::raw(title),
+            input_line(
+                &self.edit_popup_input,
+                self.edit_popup_cursor,
+                self.edit_popup_max_len(),
+                text_style,
+            )
             .centered(),
         ])
+        .block(
+            Block::bordered()
+                .title(Line::from(format!(" {} ", self.edit_base())).right_aligned())
+                .title_bottom(Line::from(range).left_aligned())
+                .title_bottom(Line::from(" ^B base ").right_aligned()),
+        )
+        .style(Style::new().fg(self.colors.section_selected_fg));
+        frame.render_widget(popup_content, area);
+    }
+
+    fn render_bit_popup(&self, frame: &mut Frame, popup_area: Rect) {
+        let text_style = Style::new()
+            .bg(self.colors.table_normal_cell_bg)
+            .fg(Color::White);
+        let area = centered_rect(30, 5, popup_area);
+        frame.render_widget(Clear, area);
+
+        // Most significant bit first, grouped by nibble
+        let mut bits = vec![];
+        for bit in (0..16).rev() {
+            let style = match bit == self.bit_popup_cursor {
+                true => text_style.reversed(),
+                false => text_style,
+            };
+            bits.push(Span::styled(
+                ((self.bit_popup_value >> bit) & 1).to_string(),
+                style,
+            ));
+            if bit % 4 == 0 && bit != 0 {
+                bits.push(Span::styled(" ", text_style));
+            }
+        }
+        let mode = match self.bit_popup_masked {
+            true => "Mask Write",
+            false => "Full Write",
+        };
+
+        let popup_content = Paragraph::new(vec![
+            Line::raw(format!(" Set Bits (Bit {}) ", self.bit_popup_cursor)),
+            Line::from(bits).centered(),
+            Line::raw(format!(" {} | (M) {mode} ", self.bit_popup_value)),
+        ])
+        .block(Block::bordered())
+        .style(Style::new().fg(self.colors.section_selected_fg));
+        frame.render_widget(popup_content, area);
+    }
+
+    fn render_ascii_popup(&self, frame: &mut Frame, popup_area: Rect) {
+        let text_style = Style::new()
+            .bg(self.colors.table_normal_cell_bg)
+            .fg(Color::White);
+        let (start, count) = self.ascii_range();
+        let end = start + (count - 1) as u16;
+        let current = registers_to_ascii(
+            &self.tables[self.selected_top_tab as usize]
+                .get_visible_data(start, end)
+                .iter()
+                .map(|cell| cell.display_content().to_u16())
+                .collect::<Vec<_>>(),
+        );
+
+        let title = format!(
+            " ASCII {}-{} (Up/Down Length) ",
+            self.selected_top_tab.memory_address(start),
+            self.selected_top_tab.memory_address(end)
+        );
+        let width = usize::max(title.len(), count * 2 + 2);
+        let area = centered_rect(width as u16 + 2, 5, popup_area);
+        frame.render_widget(Clear, area);
+
+        let input_line = match self.selected_top_tab {
+            SelectedTopTab::HoldingRegisters => Line::from(vec![
+                Span::raw(" "),
+                Span::styled(&self.ascii_popup_input, text_style),
+                Span::styled(
+                    " ".repeat(count * 2 - self.ascii_popup_input.len()),
+                    text_style,
+                ),
+            ]),
+            _ => Line::raw(" Read only"),
+        };
+
+        let popup_content = Paragraph::new(vec![
+            Line::raw(title),
+            Line::raw(format!(" {current}")),
+            input_line,
+        ])
         .block(Block::bordered())
         .style(Style::new().fg(self.colors.section_selected_fg));
         frame.render_widget(popup_content, area);
@@ -1696,7 +4162,160 @@ impl App {
         frame.render_widget(popup_content, area);
     }
 
+    fn render_device_popup(&self, frame: &mut Frame, popup_area: Rect, lines: Vec<String>) {
+        let width = lines
+            .iter()
+            .map(|line| line.len())
+            .max()
+            .unwrap_or(0)
+            .max(20)
+            + 4;
+        let area = centered_rect(width as u16, lines.len() as u16 + 4, popup_area);
+        frame.render_widget(Clear, area);
+
+        let mut content = vec![];
+        for line in lines {
+            content.push(Line::from(vec![
+                Span::raw(" "),
+                Span::styled(line, Style::new().fg(Color::White)),
+            ]));
+        }
+        content.push(Line::raw(""));
+        content.push(Line::raw("Press Enter To Close").centered());
+
+        let popup_content = Paragraph::new(content)
+            .block(Block::bordered().title(" Device Identification "))
+            .style(Style::new().fg(self.colors.section_selected_fg));
+        frame.render_widget(popup_content, area);
+    }
+
+    fn render_settings_popup(&self, frame: &mut Frame, popup_area: Rect) {
+        let area = centered_rect(48, Setting::iter().count() as u16 + 4, popup_area);
+        frame.render_widget(Clear, area);
+
+        let mut content = vec![];
+        for setting in Setting::iter() {
+            let style = match setting == self.settings_popup_row {
+                true => Style::new().add_modifier(Modifier::REVERSED),
+                false => Style::new(),
+            };
+            content.push(Line::styled(
+                format!(
+                    " {:<24}{:>20} ",
+                    setting.to_string(),
+                    self.setting_value(setting)
+                ),
+                style,
+            ));
+        }
+        content.push(Line::raw(""));
+        content.push(Line::raw("(↑ ↓) Select | (Enter) Change | (Esc) Close").centered());
+
+        let popup_content = Paragraph::new(content)
+            .block(Block::bordered().title(" Settings "))
+            .style(Style::new().fg(self.colors.section_selected_fg));
+        frame.render_widget(popup_content, area);
+    }
+
+    fn render_quit_popup(&self, frame: &mut Frame, popup_area: Rect) {
+        let message = format!(
+            " {} queued change(s) will be discarded. Quit? (Y/N) ",
+            self.queue_table_data.len()
+        );
+        let area = centered_rect((message.len() + 2) as u16, 4, popup_area);
+        frame.render_widget(Clear, area);
+
+        let popup_content = Paragraph::new(vec![
+            Line::styled(
+                "Warning",
+                Style::new()
+                    .fg(self.colors.section_selected_fg)
+                    .add_modifier(Modifier::BOLD | Modifier::UNDERLINED),
+            )
+            .centered(),
+            Line::from(message),
+        ])
+        .block(Block::bordered())
+        .style(Style::new().fg(self.colors.section_selected_fg));
+        frame.render_widget(popup_content, area);
+    }
+
     fn render_goto_popup(&self, frame: &mut Frame, popup_area: Rect) {
+        let text_style = Style::new()
+            .bg(self.colors.table_normal_cell_bg)
+            .fg(Color::White);
+        let area = centered_rect(34, 5, popup_area);
+        frame.render_widget(Clear, area);
+
+        let popup_content = Paragraph::new(vec![
+            Line::raw(" Seek to an address (1-65536) "),
+            Line::raw(" 0/1/3/4 + 5 digits picks a table "),
+            input_line(
+                &self.goto_popup_input,
+                self.goto_popup_cursor,
+                self.goto_popup_max_len(),
+                text_style,
+            )
+            .centered(),
+        ])
+        .block(
+            Block::bordered()
+                .title(Line::from(format!(" {} ", self.goto_popup_base)).right_aligned())
+                .title_bottom(Line::from(" ^B base ").right_aligned()),
+        )
+        .style(Style::new().fg(self.colors.section_selected_fg));
+        frame.render_widget(popup_content, area);
+    }
+
+    fn render_page_popup(&self, frame: &mut Frame, popup_area: Rect) {
+        let text_style = Style::new()
+            .bg(self.colors.table_normal_cell_bg)
+            .fg(Color::White);
+        let page_count = self.tables[self.selected_top_tab as usize].page_count();
+        let area = centered_rect(32, 4, popup_area);
+        frame.render_widget(Clear, area);
+
+        let popup_content = Paragraph::new(vec![
+            Line::raw(format!(" Jump to a page (1-{page_count}) ")),
+            Line::from(vec![
+                Span::styled(&self.page_popup_input[..self.page_popup_cursor], text_style),
+                Span::styled(" ".repeat(5 - self.page_popup_cursor), text_style),
+            ])
+            .centered(),
+        ])
+        .block(Block::bordered())
+        .style(Style::new().fg(self.colors.section_selected_fg));
+        frame.render_widget(popup_content, area);
+    }
+
+    fn render_range_popup(&self, frame: &mut Frame, popup_area: Rect) {
+        let text_style = Style::new()
+            .bg(self.colors.table_normal_cell_bg)
+            .fg(Color::White);
+        let area = centered_rect(34, 5, popup_area);
+        frame.render_widget(Clear, area);
+
+        let popup_content = Paragraph::new(vec![
+            Line::raw(format!(" Read into {} ", self.selected_top_tab)),
+            Line::raw(" First address,count (1-65536) "),
+            Line::from(vec![
+                Span::styled(
+                    &self.range_popup_input[..self.range_popup_cursor],
+                    text_style,
+                ),
+                Span::styled(
+                    " ".repeat(RANGE_POPUP_MAX_LEN - self.range_popup_cursor),
+                    text_style,
+                ),
+            ])
+            .centered(),
+        ])
+        .block(Block::bordered())
+        .style(Style::new().fg(self.colors.section_selected_fg));
+        frame.render_widget(popup_content, area);
+    }
+
+    fn render_search_popup(&self, frame: &mut Frame, popup_area: Rect) {
         let text_style = Style::new()
             .bg(self.colors.table_normal_cell_bg)
             .fg(Color::White);
@@ -1704,12 +4323,156 @@ impl App {
         frame.render_widget(Clear, area);
 
         let popup_content = Paragraph::new(vec![
-            Line::raw(" Seek to an address (1-65535) "),
+            Line::raw(" Search for a value (0-65535) "),
+            Line::from(vec![
+                Span::styled(
+                    &self.search_popup_input[..self.search_popup_cursor],
+                    text_style,
+                ),
+                Span::styled(" ".repeat(5 - self.search_popup_cursor), text_style),
+            ])
+            .centered(),
+        ])
+        .block(Block::bordered())
+        .style(Style::new().fg(self.colors.section_selected_fg));
+        frame.render_widget(popup_content, area);
+    }
+
+    fn render_fill_popup(&self, frame: &mut Frame, popup_area: Rect) {
+        let text_style = Style::new()
+            .bg(self.colors.table_normal_cell_bg)
+            .fg(Color::White);
+        let range = match self.selected_top_tab {
+            SelectedTopTab::Coils => "0-1",
+            _ => "0-65535 or 0x hex",
+        };
+        let area = centered_rect(34, 5, popup_area);
+        frame.render_widget(Clear, area);
+
+        let popup_content = Paragraph::new(vec![
+            Line::raw(" Queue on every cell of this page "),
+            Line::raw(format!(" ({range}) ")).centered(),
+            Line::from(vec![
+                Span::styled(&self.fill_popup_input[..self.fill_popup_cursor], text_style),
+                Span::styled(
+                    " ".repeat(FILL_POPUP_MAX_LEN - self.fill_popup_cursor),
+                    text_style,
+                ),
+            ])
+            .centered(),
+        ])
+        .block(Block::bordered())
+        .style(Style::new().fg(self.colors.section_selected_fg));
+        frame.render_widget(popup_content, area);
+    }
+
+    fn render_import_popup(&self, frame: &mut Frame, popup_area: Rect) {
+        let text_style = Style::new()
+            .bg(self.colors.table_normal_cell_bg)
+            .fg(Color::White);
+        let area = centered_rect(IMPORT_POPUP_MAX_LEN as u16 + 8, 5, popup_area);
+        frame.render_widget(Clear, area);
+
+        let popup_content = Paragraph::new(vec![
+            Line::raw(" Queue table,address,value rows from "),
+            Line::from(vec![
+                Span::styled(
+                    &self.import_popup_input[..self.import_popup_cursor],
+                    text_style,
+                ),
+                Span::styled(
+                    " ".repeat(IMPORT_POPUP_MAX_LEN - self.import_popup_cursor),
+                    text_style,
+                ),
+            ])
+            .centered(),
+            Line::raw(" (Enter) Import .csv | (Esc) Cancel "),
+        ])
+        .block(Block::bordered())
+        .style(Style::new().fg(self.colors.section_selected_fg));
+        frame.render_widget(popup_content, area);
+    }
+
+    fn render_snapshot_popup(&self, frame: &mut Frame, popup_area: Rect) {
+        let text_style = Style::new()
+            .bg(self.colors.table_normal_cell_bg)
+            .fg(Color::White);
+        let area = centered_rect(IMPORT_POPUP_MAX_LEN as u16 + 8, 5, popup_area);
+        frame.render_widget(Clear, area);
+
+        let popup_content = Paragraph::new(vec![
+            Line::raw(" Compare the tables against snapshot "),
+            Line::from(vec![
+                Span::styled(
+                    &self.snapshot_popup_input[..self.snapshot_popup_cursor],
+                    text_style,
+                ),
+                Span::styled(
+                    " ".repeat(IMPORT_POPUP_MAX_LEN - self.snapshot_popup_cursor),
+                    text_style,
+                ),
+            ])
+            .centered(),
+            Line::raw(" (Enter) Load .csv | (Esc) Cancel "),
+        ])
+        .block(Block::bordered())
+        .style(Style::new().fg(self.colors.section_selected_fg));
+        frame.render_widget(popup_content, area);
+    }
+
+    fn render_raw_popup(&self, frame: &mut Frame, popup_area: Rect) {
+        let text_style = Style::new()
+            .bg(self.colors.table_normal_cell_bg)
+            .fg(Color::White);
+        let area = centered_rect(54, 9, popup_area);
+        frame.render_widget(Clear, area);
+
+        // Long payloads scroll, keeping the end of the input in view
+        let input_width = 50;
+        let input_start = self.raw_popup_input.len().saturating_sub(input_width - 1);
+        let input = &self.raw_popup_input[input_start..];
+
+        let popup_content = Paragraph::new(vec![
+            Line::styled(
+                " EXPERT: sent as is, without any checks ",
+                Style::new().fg(Color::Red).bold(),
+            ),
+            Line::raw(" Function code then payload, in hex (41 00 0A) "),
+            Line::from(vec![
+                Span::styled(input, text_style),
+                Span::styled(" ".repeat(input_width - input.len()), text_style),
+            ])
+            .centered(),
+            Line::raw(""),
+            Line::raw(format!(
+                " Response: {}",
+                self.raw_popup_response.as_deref().unwrap_or("N\\A")
+            )),
+        ])
+        .wrap(Wrap { trim: false })
+        .block(Block::bordered().title(" Raw PDU "))
+        .style(Style::new().fg(self.colors.section_selected_fg));
+        frame.render_widget(popup_content, area);
+    }
+
+    fn render_capture_popup(&self, frame: &mut Frame, popup_area: Rect) {
+        let text_style = Style::new()
+            .bg(self.colors.table_normal_cell_bg)
+            .fg(Color::White);
+        let area = centered_rect(32, 5, popup_area);
+        frame.render_widget(Clear, area);
+
+        let popup_content = Paragraph::new(vec![
+            Line::raw(format!(" Save {} captured value(s) ", self.capture.len())),
             Line::from(vec![
-                Span::styled(&self.edit_popup_input[..self.edit_popup_cursor], text_style),
-                Span::styled(" ".repeat(5 - self.edit_popup_cursor), text_style),
+                Span::styled(
+                    &self.capture_popup_input[..self.capture_popup_cursor],
+                    text_style,
+                ),
+                Span::styled(" ".repeat(24 - self.capture_popup_cursor), text_style),
             ])
             .centered(),
+            Line::raw(" (Enter) Save .csv | (Esc) Discard "),
         ])
         .block(Block::bordered())
         .style(Style::new().fg(self.colors.section_selected_fg));
@@ -1774,252 +4537,2103 @@ impl App {
                 .block(Block::bordered())
                 .style(Style::new().fg(self.colors.section_selected_fg));
             }
-            SaveMacroMode::FileSaved => {
+            SaveMacroMode::FileSaved(0) => {
                 area = centered_rect((file_saved_message.len() + 2) as u16, 3, popup_area);
                 frame.render_widget(Clear, area);
 
-                popup_content = Paragraph::new(vec![Line::from(file_saved_message)])
-                    .block(Block::bordered())
-                    .style(Style::new().fg(self.colors.section_selected_fg));
-            }
+                popup_content = Paragraph::new(vec![Line::from(file_saved_message)])
+                    .block(Block::bordered())
+                    .style(Style::new().fg(self.colors.section_selected_fg));
+            }
+            SaveMacroMode::FileSaved(skipped_count) => {
+                let skipped_message =
+                    format!(" Warning - {skipped_count} unsupported command(s) were left out. ");
+                area = centered_rect(
+                    (usize::max(file_saved_message.len(), skipped_message.len()) + 2) as u16,
+                    4,
+                    popup_area,
+                );
+                frame.render_widget(Clear, area);
+
+                popup_content = Paragraph::new(vec![
+                    Line::from(file_saved_message),
+                    Line::styled(skipped_message, Style::new().bold()),
+                ])
+                .block(Block::bordered())
+                .style(Style::new().fg(self.colors.section_selected_fg));
+            }
+        }
+        frame.render_widget(popup_content, area);
+    }
+
+    async fn table_page_up(&mut self) {
+        let table = &mut self.tables[self.selected_top_tab as usize];
+        table.page_up().await;
+    }
+
+    async fn table_move_up(&mut self) {
+        let table = &mut self.tables[self.selected_top_tab as usize];
+        table.move_up().await;
+    }
+
+    async fn table_page_down(&mut self) {
+        let table = &mut self.tables[self.selected_top_tab as usize];
+        table.page_down().await;
+    }
+
+    async fn table_move_down(&mut self) {
+        let table = &mut self.tables[self.selected_top_tab as usize];
+        table.move_down().await;
+    }
+
+    fn table_move_left(&mut self) {
+        let table = &mut self.tables[self.selected_top_tab as usize];
+        table.move_left();
+    }
+
+    fn table_move_right(&mut self) {
+        let table = &mut self.tables[self.selected_top_tab as usize];
+        table.move_right();
+    }
+
+    async fn table_go_to_page(&mut self, page_offset: usize) {
+        let table = &mut self.tables[self.selected_top_tab as usize];
+        table.go_to_page(page_offset).await;
+    }
+
+    async fn table_go_to_first(&mut self) {
+        let table = &mut self.tables[self.selected_top_tab as usize];
+        table.go_to_first().await;
+    }
+
+    async fn table_go_to_last(&mut self) {
+        let table = &mut self.tables[self.selected_top_tab as usize];
+        table.go_to_last().await;
+    }
+
+    fn table_go_to_cell(&mut self, cell_address: u16) {
+        let table = &mut self.tables[self.selected_top_tab as usize];
+        table.go_to_cell(cell_address);
+    }
+
+    async fn table_search(&mut self, value: u16) {
+        let table = &mut self.tables[self.selected_top_tab as usize];
+        match table.find_next(value) {
+            Some(address) => table.go_to_cell(address),
+            None => {
+                let _ = self
+                    .sender
+                    .send(Action::Error(format!("No read address holds {value}")))
+                    .await;
+            }
+        }
+    }
+
+    fn queue_go_to_cell(&mut self, cell_address: u16, table_index: usize) {
+        let table = &mut self.tables[table_index];
+        self.selected_top_tab = SelectedTopTab::iter().nth(table_index).unwrap();
+        table.go_to_cell(cell_address)
+    }
+
+    async fn modbus_apply_queued(&mut self, keep: bool) {
+        let commands = self.table_get_queued_commands();
+        for (table, address, _) in &commands {
+            match keep {
+                true => self.queue_kept_items.insert((*table as usize, *address)),
+                false => self.queue_kept_items.remove(&(*table as usize, *address)),
+            };
+        }
+        self.modbus_write(commands).await;
+    }
+
+    async fn modbus_apply_marked(&mut self) {
+        let commands = self
+            .queue_table_data
+            .iter()
+            .filter(|queue_item| {
+                self.queue_marked_items
+                    .contains(&(queue_item.table_index, queue_item.address))
+            })
+            .map(|queue_item| {
+                (
+                    queue_item.cell.table_type,
+                    queue_item.address,
+                    queue_item.cell.queued_content,
+                )
+            })
+            .collect::<Vec<ModbusWriteCommand>>();
+        for (table, address, _) in &commands {
+            self.queue_kept_items.remove(&(*table as usize, *address));
+        }
+        self.modbus_write(commands).await;
+    }
+
+    async fn modbus_write(&mut self, mut commands: Vec<ModbusWriteCommand>) {
+        // Split out cells queued for mask write (0x16) and read/write multiple (0x17)
+        commands.sort_by_key(|&(table, address, _)| (table, address));
+        let mut write_commands = vec![];
+        let mut mask_commands = vec![];
+        let mut read_write_commands: Vec<ModbusReadWriteCommand> = vec![];
+        for (table, address, content) in commands {
+            let Some(cell) = self.tables[table as usize].data.get(&address) else {
+                continue;
+            };
+            match (cell.write_mode, cell.write_masks()) {
+                (WriteMode::Masked, Some((and_mask, or_mask))) => {
+                    mask_commands.push((address, and_mask, or_mask, content.to_u16()))
+                }
+                // Neighbouring registers are sent together as one block
+                (WriteMode::ReadWrite, _) => match read_write_commands.last_mut() {
+                    Some((start, values))
+                        if *start as u32 + values.len() as u32 == address as u32
+                            && values.len() < MAX_READ_WRITE_WORDS as usize =>
+                    {
+                        values.push(content.to_u16())
+                    }
+                    _ => read_write_commands.push((address, vec![content.to_u16()])),
+                },
+                _ => write_commands.push((table, address, content)),
+            }
+        }
+
+        if !mask_commands.is_empty() {
+            let _ = self
+                .sender
+                .send(Action::ToModbus(ModbusCommandQueue::MaskWrite(
+                    mask_commands,
+                )))
+                .await;
+        }
+        if !read_write_commands.is_empty() {
+            let _ = self
+                .sender
+                .send(Action::ToModbus(ModbusCommandQueue::ReadWrite(
+                    read_write_commands,
+                )))
+                .await;
+        }
+        if !write_commands.is_empty() {
+            let queue = match self.verify_writes {
+                true => ModbusCommandQueue::VerifiedWrite(write_commands),
+                false => ModbusCommandQueue::Write(write_commands),
+            };
+            let _ = self.sender.send(Action::ToModbus(queue)).await;
+        }
+    }
+
+    fn table_apply_queued_cells(&mut self, commands: Vec<ModbusWriteCommand>) {
+        // Only the written cells are applied, anything else stays queued. Cells written
+        // with the keep option also stay queued so the same set can be written again
+        for (table, address, _) in commands {
+            if self.queue_kept_items.remove(&(table as usize, address)) {
+                continue;
+            }
+            if let Some(cell) = self.tables[table as usize].data.get_mut(&address) {
+                if let CellState::Queued = cell.state {
+                    cell.apply();
+                }
+            }
+        }
+        self.refresh_queue_table();
+    }
+
+    // Queued bits (coils, discrete input assertions) and words (registers)
+    fn queue_count(&self) -> (usize, usize) {
+        let bits = self
+            .queue_table_data
+            .iter()
+            .filter(|queue_item| matches!(queue_item.cell.queued_content, CellType::Coil(_)))
+            .count();
+        (bits, self.queue_table_data.len() - bits)
+    }
+
+    fn refresh_queue_table(&mut self) {
+        self.queue_table_data = vec![];
+        for table in &self.tables {
+            let mut table_queued = table.get_queue_items();
+            self.queue_table_data.append(&mut table_queued);
+        }
+        if self.queue_item_index >= self.queue_table_data.len() && !self.queue_table_data.is_empty()
+        {
+            self.queue_item_index = self.queue_table_data.len() - 1;
+        }
+
+        // Forget marks on items that are no longer queued
+        let queue_table_data = &self.queue_table_data;
+        self.queue_marked_items.retain(|(table_index, address)| {
+            queue_table_data.iter().any(|queue_item| {
+                queue_item.table_index == *table_index && queue_item.address == *address
+            })
+        });
+    }
+
+    fn queue_toggle_read_write(&mut self) -> Result<()> {
+        let queue_item = &self.queue_table_data[self.queue_item_index];
+        let (table_index, address) = (queue_item.table_index, queue_item.address);
+        let toggled = self.tables[table_index]
+            .data
+            .get_mut(&address)
+            .is_some_and(|cell| cell.toggle_read_write());
+        self.refresh_queue_table();
+        if !toggled {
+            self.beep()?;
+        }
+        Ok(())
+    }
+
+    fn queue_toggle_mark(&mut self) {
+        let queue_item = &self.queue_table_data[self.queue_item_index];
+        let key = (queue_item.table_index, queue_item.address);
+        if !self.queue_marked_items.remove(&key) {
+            self.queue_marked_items.insert(key);
+        }
+    }
+
+    fn watch_toggle_current_cell(&mut self) {
+        let table = &self.tables[self.selected_top_tab as usize];
+        let item = (self.selected_top_tab, table.table_address);
+        match self.watch_list.iter().position(|watched| *watched == item) {
+            Some(index) => {
+                self.watch_list.remove(index);
+            }
+            None => self.watch_list.push(item),
+        }
+        self.watch_clamp_selection();
+    }
+
+    fn selected_watch_item(&self) -> Option<&(SelectedTopTab, u16)> {
+        self.watch_list.get(self.watch_table_state.selected()?)
+    }
+
+    fn watch_remove_selected_item(&mut self) {
+        if let Some(index) = self.watch_table_state.selected() {
+            if index < self.watch_list.len() {
+                self.watch_list.remove(index);
+            }
+        }
+        self.watch_clamp_selection();
+    }
+
+    fn watch_clamp_selection(&mut self) {
+        let selected = match self.watch_list.len() {
+            0 => None,
+            len => Some(self.watch_table_state.selected().unwrap_or(0).min(len - 1)),
+        };
+        self.watch_table_state.select(selected);
+    }
+
+    fn watch_select_next_item(&mut self) {
+        if !self.watch_list.is_empty() {
+            let next = match self.watch_table_state.selected() {
+                Some(i) => (i + 1) % self.watch_list.len(),
+                None => 0,
+            };
+            self.watch_table_state.select(Some(next));
+        }
+    }
+
+    fn watch_select_previous_item(&mut self) {
+        if !self.watch_list.is_empty() {
+            let previous = match self.watch_table_state.selected() {
+                Some(0) | None => self.watch_list.len() - 1,
+                Some(i) => i - 1,
+            };
+            self.watch_table_state.select(Some(previous));
+        }
+    }
+
+    async fn modbus_read_watch_list(&mut self) {
+        if self.watch_list.is_empty() {
+            return;
+        }
+
+        // Merge neighbouring pinned addresses into single range reads
+        let mut pinned = self.watch_list.clone();
+        pinned.sort();
+        let mut commands: Vec<ModbusReadCommand> = vec![];
+        for (table, address) in pinned {
+            match commands.last_mut() {
+                Some((last_table, start, count))
+                    if *last_table == table && *start as u32 + *count as u32 == address as u32 =>
+                {
+                    *count += 1;
+                }
+                _ => commands.push((table, address, 1)),
+            }
+        }
+
+        if let ConnectionStatus::Connected = self.connection_status {
+            let _ = self
+                .sender
+                .send(Action::ToModbus(ModbusCommandQueue::Read(commands)))
+                .await;
+        }
+    }
+
+    fn queue_select_next_item(&mut self) {
+        self.queue_item_index = match self.queue_table_state.selected() {
+            Some(i) => {
+                if i >= self.queue_table_data.len() - 1 {
+                    0
+                } else {
+                    i + 1
+                }
+            }
+            None => 0,
+        };
+        self.queue_table_state.select(Some(self.queue_item_index));
+        self.queue_scroll_state = self.queue_scroll_state.position(self.queue_item_index);
+    }
+
+    fn queue_select_previous_item(&mut self) {
+        self.queue_item_index = match self.queue_table_state.selected() {
+            Some(i) => {
+                if i == 0 {
+                    self.queue_table_data.len() - 1
+                } else {
+                    i - 1
+                }
+            }
+            None => 0,
+        };
+
+        self.queue_table_state.select(Some(self.queue_item_index));
+        self.queue_scroll_state = self.queue_scroll_state.position(self.queue_item_index);
+    }
+
+    fn queue_revert_item(&mut self) {
+        let item_address = self.queue_table_data[self.queue_item_index].address;
+        let table_index = self.queue_table_data[self.queue_item_index].table_index;
+
+        let table = &mut self.tables[table_index];
+
+        if let Some(item) = table.data.get_mut(&item_address) {
+            item.revert();
+        }
+
+        self.refresh_queue_table();
+    }
+
+    fn table_get_queued_commands(&self) -> Vec<ModbusWriteCommand> {
+        let mut commands: Vec<ModbusWriteCommand> = vec![];
+        for table in &self.tables {
+            let table_commands: Vec<ModbusWriteCommand> = table
+                .get_queue_items()
+                .into_iter()
+                .map(|queue_item| {
+                    (
+                        table.table_type,
+                        queue_item.address,
+                        queue_item.cell.queued_content,
+                    )
+                })
+                .collect();
+            commands.extend(table_commands);
+        }
+        commands
+    }
+
+    fn table_queue_current_cell(&mut self, new_value: u16) {
+        let table = &mut self.tables[self.selected_top_tab as usize];
+        match table.table_type {
+            SelectedTopTab::Coils | SelectedTopTab::DiscreteInputs => {
+                match new_value {
+                    0 => table.queue_current_cell(CellType::Coil(false)),
+                    _ => table.queue_current_cell(CellType::Coil(true)),
+                };
+            }
+            SelectedTopTab::InputRegisters | SelectedTopTab::HoldingRegisters => {
+                table.queue_current_cell(CellType::Word(new_value))
+            }
+        }
+        self.refresh_queue_table();
+    }
+
+    // Stages every row through the current cell, then puts each table's selection back
+    async fn queue_import(&mut self, rows: Vec<ImportRow>, mut bad_lines: Vec<usize>) {
+        let selected_top_tab = self.selected_top_tab;
+        let selected_addresses = self
+            .tables
+            .iter()
+            .map(|table| table.table_address)
+            .collect::<Vec<u16>>();
+
+        let mut queued = 0;
+        let mut read_only = 0;
+        let mut outside = 0;
+        for (table, address, value) in rows {
+            if !self.tables[table as usize].contains(address) {
+                outside += 1;
+                continue;
+            }
+            self.selected_top_tab = table;
+            self.tables[table as usize].go_to_cell(address);
+            match self.is_read_only(address, address) {
+                true => read_only += 1,
+                false => {
+                    self.table_queue_current_cell(value);
+                    queued += 1;
+                }
+            }
+        }
+
+        self.selected_top_tab = selected_top_tab;
+        for (table, address) in self.tables.iter_mut().zip(selected_addresses) {
+            table.go_to_cell(address);
+        }
+
+        if bad_lines.is_empty() && read_only == 0 && outside == 0 {
+            return;
+        }
+        let mut message = format!("Queued {queued} row(s)");
+        if !bad_lines.is_empty() {
+            let count = bad_lines.len();
+            bad_lines.truncate(IMPORT_MAX_LISTED_LINES);
+            let listed = bad_lines
+                .iter()
+                .map(|line| line.to_string())
+                .collect::<Vec<String>>()
+                .join(", ");
+            let more = match count > IMPORT_MAX_LISTED_LINES {
+                true => ", ...",
+                false => "",
+            };
+            message.push_str(&format!(
+                ", skipped {count} bad line(s) (line {listed}{more})"
+            ));
+        }
+        if read_only > 0 {
+            message.push_str(&format!(", skipped {read_only} read-only address(es)"));
+        }
+        if outside > 0 {
+            message.push_str(&format!(
+                ", skipped {outside} address(es) past the end of the table"
+            ));
+        }
+        let _ = self.sender.send(Action::Error(message)).await;
+    }
+
+    // Steps from the queued value, so repeated presses add up before applying
+    async fn table_nudge_current_cell(&mut self, up: bool) -> Result<()> {
+        if !self.can_queue() {
+            let _ = self
+                .sender
+                .send(Action::Error(String::from("Connect to a server first.")))
+                .await;
+            return Ok(());
+        }
+        let address = self.tables[self.selected_top_tab as usize].table_address;
+        match (
+            self.selected_top_tab,
+            self.read_only_reason(address, address),
+        ) {
+            (SelectedTopTab::Coils, _) => return self.beep(),
+            (_, Some(reason)) => return self.refuse_write(reason).await,
+            (_, None) => {}
+        }
+        let table = &self.tables[self.selected_top_tab as usize];
+        let value = table.current_cell().display_content().to_u16();
+        let new_value = match up {
+            true => value.saturating_add(self.nudge_step),
+            false => value.saturating_sub(self.nudge_step),
+        };
+        if new_value == value {
+            return self.beep();
+        }
+        self.table_queue_current_cell(new_value);
+        Ok(())
+    }
+
+    fn table_queue_current_page(&mut self, new_value: u16) {
+        let table = &mut self.tables[self.selected_top_tab as usize];
+        match table.table_type {
+            SelectedTopTab::Coils | SelectedTopTab::DiscreteInputs => {
+                table.queue_current_page(CellType::Coil(new_value != 0))
+            }
+            SelectedTopTab::InputRegisters | SelectedTopTab::HoldingRegisters => {
+                table.queue_current_page(CellType::Word(new_value))
+            }
+        }
+        self.refresh_queue_table();
+    }
+
+    fn table_queue_current_bits(&mut self, new_value: u16) {
+        let table = &mut self.tables[self.selected_top_tab as usize];
+        table.queue_current_bits(new_value, self.bit_popup_masked);
+        self.refresh_queue_table();
+    }
+
+    fn table_revert_current_cell(&mut self) {
+        let table = &mut self.tables[self.selected_top_tab as usize];
+        table.revert_current_cell();
+        self.refresh_queue_table();
+    }
+
+    fn table_revert_current_page(&mut self) -> Result<()> {
+        let table = &mut self.tables[self.selected_top_tab as usize];
+        if table.revert_current_page() == 0 {
+            return self.beep();
+        }
+        self.refresh_queue_table();
+        Ok(())
+    }
+
+    fn table_toggle_current_cell(&mut self) {
+        let table = &mut self.tables[self.selected_top_tab as usize];
+        table.toggle_current_coil();
+        self.refresh_queue_table();
+    }
+
+    fn table_toggle_selection(&mut self) {
+        let table = &mut self.tables[self.selected_top_tab as usize];
+        table.toggle_selection();
+        self.refresh_queue_table();
+    }
+
+    fn table_toggle_current_assertion(&mut self) {
+        let table = &mut self.tables[self.selected_top_tab as usize];
+        table.toggle_current_assertion();
+        self.refresh_queue_table();
+    }
+
+    async fn modbus_read_current_page(&mut self) {
+        let table = &self.tables[self.selected_top_tab as usize];
+
+        if let ConnectionStatus::Connected = self.connection_status {
+            let command: Vec<ModbusReadCommand> = vec![table.current_page_read()];
+            let _ = self
+                .sender
+                .send(Action::ToModbus(ModbusCommandQueue::Read(command)))
+                .await;
+        }
+    }
+
+    // Every table with tick refresh on, in one request batch
+    // Due pages and scheduled ranges go out as one batch. Nothing is marked read while
+    // disconnected, so everything is due again once a connection is up
+    async fn modbus_read_scheduled(&mut self) {
+        let ConnectionStatus::Connected = self.connection_status else {
+            return;
+        };
+
+        let now = Instant::now();
+        let mut commands = self.poll_schedule.due_ranges(now);
+        for table in &self.tables {
+            if table.tick_refresh && self.poll_schedule.table_due(table.table_type, now) {
+                commands.push(table.current_page_read());
+            }
+        }
+
+        if !commands.is_empty() {
+            let commands = coalesce(commands);
+            let _ = self
+                .sender
+                .send(Action::ToModbus(ModbusCommandQueue::Read(commands)))
+                .await;
+        }
+    }
+
+    async fn next_top_tab(&mut self) {
+        self.select_top_tab(self.selected_top_tab.next()).await;
+    }
+
+    async fn previous_top_tab(&mut self) {
+        self.select_top_tab(self.selected_top_tab.previous()).await;
+    }
+
+    // Reading on arrival is optional, a slow link may not want a read per tab switch
+    async fn select_top_tab(&mut self, table: SelectedTopTab) {
+        let changed = self.selected_top_tab != table;
+        self.selected_top_tab = table;
+        if changed && self.read_on_tab_switch {
+            self.modbus_read_current_page().await;
+        }
+    }
+
+    fn next_bottom_tab(&mut self) {
+        self.selected_bottom_tab = self.selected_bottom_tab.next();
+    }
+
+    fn previous_bottom_tab(&mut self) {
+        self.selected_bottom_tab = self.selected_bottom_tab.previous();
+    }
+
+    fn get_table_stats(&self, area: Rect) -> (usize, usize, usize, usize) {
+        let row_height: usize = 1;
+        let column_length: usize = match self.selected_top_tab {
+            SelectedTopTab::Coils | SelectedTopTab::DiscreteInputs => 3,
+            // Scaled values can be wider than the display mode's format, keep a space either side
+            SelectedTopTab::InputRegisters | SelectedTopTab::HoldingRegisters => usize::max(
+                self.display_mode.column_length(),
+                self.scaling
+                    .max_width(self.selected_top_tab)
+                    .map_or(0, |width| width + 2),
+            ),
+        };
+        let max_rows = (area.height as usize).saturating_sub(2) / row_height;
+        let max_cols = match self.selected_top_tab {
+            SelectedTopTab::Coils | SelectedTopTab::DiscreteInputs => 16,
+            SelectedTopTab::InputRegisters | SelectedTopTab::HoldingRegisters => {
+                self.display_mode.register_columns()
+            }
+        };
+
+        (row_height, column_length, max_rows, max_cols)
+    }
+
+    // A value the way the list view shows it: scaled with its unit, typed by the profile, or
+    // in the display mode
+    fn format_value(&self, address: u16, raw: u16) -> String {
+        let table = self.selected_top_tab;
+        match table {
+            SelectedTopTab::Coils | SelectedTopTab::DiscreteInputs => raw.to_string(),
+            SelectedTopTab::InputRegisters | SelectedTopTab::HoldingRegisters => {
+                match self.scaling.get(table, address) {
+                    Some(rule) => format!("{} {}", rule.format(raw), rule.unit)
+                        .trim_end()
+                        .to_string(),
+                    None => self
+                        .profile
+                        .data_type(table, address)
+                        .and_then(|(data_type, _)| {
+                            data_type.format_register(raw, self.display_mode)
+                        })
+                        .unwrap_or_else(|| self.display_mode.text(raw)),
+                }
+            }
+        }
+    }
+
+    fn current_cell_value(&self) -> String {
+        let table = &self.tables[self.selected_top_tab as usize];
+        let raw = table.current_cell().display_content().to_u16();
+        match (self.current_scaling_rule(), self.selected_top_tab) {
+            (Some(rule), _) => rule.format(raw),
+            (None, SelectedTopTab::InputRegisters | SelectedTopTab::HoldingRegisters) => {
+                self.display_mode.text(raw)
+            }
+            (None, _) => raw.to_string(),
+        }
+    }
+
+    fn copy_to_clipboard(&mut self, text: String) -> Result<(), arboard::Error> {
+        let mut clipboard = match self.clipboard.take() {
+            Some(clipboard) => clipboard,
+            None => Clipboard::new()?,
+        };
+        let result = clipboard.set_text(text);
+        self.clipboard = Some(clipboard);
+        result
+    }
+
+    fn paste_from_clipboard(&mut self) -> Result<String, arboard::Error> {
+        let mut clipboard = match self.clipboard.take() {
+            Some(clipboard) => clipboard,
+            None => Clipboard::new()?,
+        };
+        let result = clipboard.get_text();
+        self.clipboard = Some(clipboard);
+        result
+    }
+
+    // Validates clipboard text as an Edit popup value, hex is converted to decimal
+    fn clipboard_edit_value(&mut self) -> Option<String> {
+        let text = self.paste_from_clipboard().ok()?;
+        let text = text.trim();
+        let value = match self.current_scaling_rule() {
+            Some(rule) => text
+                .parse::<f64>()
+                .ok()
+                .and_then(|value| rule.to_raw(value))
+                .map(|_| text.to_string()),
+            None => parse_u16_value(text).map(|value| self.edit_base().format(value as u32)),
+        }?;
+        (value.len() <= self.edit_popup_max_len()).then_some(value)
+    }
+
+    fn edit_popup_max_len(&self) -> usize {
+        if let Some((data_type, _)) = self.edit_type() {
+            if data_type.is_numeric() {
+                return data_type.input_len();
+            }
+        }
+        match (
+            self.edit_base(),
+            self.edit_popup_wide,
+            self.edit_popup_input.starts_with("0b"),
+        ) {
+            (InputBase::Hexadecimal, _, _) => 8,
+            (InputBase::Binary, _, _) => 32,
+            (InputBase::Decimal, true, _) => EDIT_POPUP_WIDE_MAX_LEN,
+            (InputBase::Decimal, false, true) => EDIT_POPUP_BINARY_MAX_LEN,
+            (InputBase::Decimal, false, false) => EDIT_POPUP_MAX_LEN,
+        }
+    }
+
+    fn edit_base(&self) -> InputBase {
+        match self.edit_base_is_fixed() {
+            true => InputBase::Decimal,
+            false => self.edit_popup_base,
+        }
+    }
+
+    // Scaled values are typed in engineering units and numeric types as the number they
+    // hold, so both are always decimal
+    fn edit_base_is_fixed(&self) -> bool {
+        self.current_scaling_rule().is_some()
+            || self
+                .edit_type()
+                .is_some_and(|(data_type, _)| data_type.is_numeric())
+    }
+
+    // The profile's type for the selected register and the first register of its value.
+    // Scaling takes priority, like it does in the table
+    fn edit_type(&self) -> Option<(DataType, u16)> {
+        let table = &self.tables[self.selected_top_tab as usize];
+        match self.current_scaling_rule() {
+            Some(_) => None,
+            None => self
+                .profile
+                .data_type(self.selected_top_tab, table.table_address),
+        }
+    }
+
+    // Queues the Edit popup's value if it fits the register's type, a 32-bit type fills
+    // its whole register pair
+    fn queue_typed_edit(&mut self) -> Result<()> {
+        let Some((data_type, value_start)) = self.edit_type() else {
+            return self.beep();
+        };
+        let value = match data_type.is_numeric() {
+            true => data_type.parse_value(&self.edit_popup_input),
+            false => self
+                .edit_popup_value()
+                .filter(|value| *value <= u16::MAX as u32),
+        };
+        let Some(value) = value else {
+            return self.beep();
+        };
+
+        match data_type.width() {
+            2 => self.queue_wide_edit(value_start, value),
+            _ => {
+                self.table_queue_current_cell(value as u16);
+                self.edit_popup_cursor = 0;
+                self.edit_popup_input = String::new();
+                self.app_mode = AppMode::Main;
+                Ok(())
+            }
+        }
+    }
+
+    // Splits a 32-bit value across the register at `start` and the next one
+    fn queue_wide_edit(&mut self, start: u16, value: u32) -> Result<()> {
+        let total_address_space = self.tables[self.selected_top_tab as usize].total_address_space;
+        if start as usize + 2 > total_address_space {
+            self.edit_popup_cursor = 0;
+            self.edit_popup_input = String::new();
+            self.app_mode = AppMode::Popup(PopupType::Error(format!(
+                "No register after {total_address_space} to pair with."
+            )));
+            return Ok(());
+        }
+        if self.is_read_only(start, start + 1) {
+            return self.beep();
+        }
+
+        let words = self.word_order.split(value);
+        let table = &mut self.tables[self.selected_top_tab as usize];
+        let address = table.table_address;
+        table.go_to_cell(start);
+        table.queue_current_words(&words);
+        table.go_to_cell(address);
+        self.refresh_queue_table();
+        self.edit_popup_cursor = 0;
+        self.edit_popup_input = String::new();
+        self.app_mode = AppMode::Main;
+        Ok(())
+    }
+
+    // The typed bit pattern, spanning the register pair in the 32-bit mode
+    fn edit_popup_value(&self) -> Option<u32> {
+        match (self.edit_base(), self.edit_popup_wide) {
+            (InputBase::Decimal, true) => parse_wide_value(&self.edit_popup_input),
+            (InputBase::Decimal, false) => self
+                .display_mode
+                .parse(&self.edit_popup_input)
+                .map(u32::from),
+            (base, _) => base.parse(&self.edit_popup_input),
+        }
+    }
+
+    // Decimal follows the display mode, like the cell the value is going into
+    fn edit_popup_text(&self, base: InputBase, value: u32) -> String {
+        match (base, self.edit_popup_wide, u16::try_from(value)) {
+            (InputBase::Decimal, false, Ok(word)) => self.display_mode.text(word),
+            _ => base.format(value),
+        }
+    }
+
+    fn goto_popup_max_len(&self) -> usize {
+        match self.goto_popup_base {
+            InputBase::Decimal => GOTO_POPUP_MAX_LEN,
+            InputBase::Hexadecimal => 5, // 0x10000 is 65536
+            InputBase::Binary => 17,
+        }
+    }
+
+    // The 0-based address typed into the Goto popup, None when it names another table
+    fn goto_popup_address(&self) -> Option<u16> {
+        match self.goto_popup_base {
+            InputBase::Decimal => match parse_goto_address(&self.goto_popup_input)? {
+                (None, address) => Some(address),
+                (Some(_), _) => None,
+            },
+            base => u16::try_from(base.parse(&self.goto_popup_input)?.checked_sub(1)?).ok(),
+        }
+    }
+
+    fn current_scaling_rule(&self) -> Option<&ScalingRule> {
+        let table = &self.tables[self.selected_top_tab as usize];
+        self.scaling.get(self.selected_top_tab, table.table_address)
+    }
+
+    // Offline authoring queues against a typed target, only sending needs a device
+    fn can_queue(&self) -> bool {
+        matches!(
+            self.connection_status,
+            ConnectionStatus::Connected | ConnectionStatus::Offline
+        )
+    }
+
+    // The queue as a macro for the current target, None until there is one
+    fn queued_macro(&self) -> Option<MagModCommandList> {
+        let (Some(ip_addr), Some(port)) = (self.current_ip_address, self.current_port) else {
+            return None;
+        };
+        let commands = self
+            .queue_table_data
+            .iter()
+            .map(|queue_item| {
+                (
+                    queue_item.cell.table_type,
+                    queue_item.address,
+                    queue_item.cell.queued_content,
+                )
+            })
+            .collect();
+        Some(MagModCommandList::new(ip_addr, port, commands).with_unit_id(self.unit_id))
+    }
+
+    // Closes any connection and takes the target that macros will be saved with
+    async fn start_offline(&mut self, addr: SocketAddr) {
+        self.stop_modbus_task().await;
+        self.connection_target = None;
+        self.connection_status = ConnectionStatus::Offline;
+        self.current_ip_address = Some(addr.ip());
+        self.current_port = Some(addr.port());
+    }
+
+    // The settings popup and the table hotkeys both change settings through here
+    async fn change_setting(&mut self, setting: Setting) {
+        match setting {
+            Setting::DisplayMode => {
+                self.display_mode = self.display_mode.next();
+                // A pinned page size keeps its columns in every mode
+                for table in [
+                    SelectedTopTab::InputRegisters,
+                    SelectedTopTab::HoldingRegisters,
+                ] {
+                    if self.page_size.is_none() {
+                        self.tables[table as usize]
+                            .set_columns(self.display_mode.register_columns());
+                    }
+                }
+            }
+            Setting::WordOrder => self.word_order = self.word_order.next(),
+            Setting::Theme => self.theme = self.theme.next(),
+            Setting::Bell => self.bell = self.bell.next(),
+            Setting::WrapAround => {
+                self.wrap_around = match self.wrap_around {
+                    true => false,
+                    false => true,
+                };
+                for table in self.tables.iter_mut() {
+                    table.wrap_around = self.wrap_around;
+                }
+            }
+            Setting::VerifyWrites => {
+                self.verify_writes = match self.verify_writes {
+                    true => false,
+                    false => true,
+                }
+            }
+            Setting::ConfirmQuit => {
+                self.confirm_quit = match self.confirm_quit {
+                    true => false,
+                    false => true,
+                }
+            }
+            Setting::PrefetchOnConnect => {
+                self.prefetch_on_connect = match self.prefetch_on_connect {
+                    true => false,
+                    false => true,
+                }
+            }
+            Setting::ReadOnTabSwitch => {
+                self.read_on_tab_switch = match self.read_on_tab_switch {
+                    true => false,
+                    false => true,
+                }
+            }
+        }
+
+        // Only the setting that was changed is saved, the rest keep what the file had
+        self.saved_settings.copy_setting(setting, &self.settings());
+        let saved = match &self.settings_path {
+            Some(path) => self.saved_settings.save(path).await,
+            None => Ok(()),
+        };
+        if let Err(err) = saved {
+            self.app_mode =
+                AppMode::Popup(PopupType::Error(format!("Couldn't Save Settings: {err}")));
+        }
+    }
+
+    fn settings(&self) -> Settings {
+        Settings {
+            display_mode: self.display_mode,
+            word_order: self.word_order,
+            theme: self.theme,
+            bell: self.bell,
+            wrap_around: self.wrap_around,
+            verify_writes: self.verify_writes,
+            confirm_quit: self.confirm_quit,
+            prefetch_on_connect: self.prefetch_on_connect,
+            read_on_tab_switch: self.read_on_tab_switch,
+        }
+    }
+
+    fn setting_value(&self, setting: Setting) -> String {
+        let on_off = |enabled: bool| match enabled {
+            true => String::from("On"),
+            false => String::from("Off"),
+        };
+        match setting {
+            Setting::DisplayMode => self.display_mode.to_string(),
+            Setting::WordOrder => self.word_order.to_string(),
+            Setting::Theme => self.theme.to_string(),
+            Setting::Bell => self.bell.to_string(),
+            Setting::WrapAround => on_off(self.wrap_around),
+            Setting::VerifyWrites => on_off(self.verify_writes),
+            Setting::ConfirmQuit => on_off(self.confirm_quit),
+            Setting::PrefetchOnConnect => on_off(self.prefetch_on_connect),
+            Setting::ReadOnTabSwitch => on_off(self.read_on_tab_switch),
+        }
+    }
+
+    // True when the device profile marks any address in the range as read-only
+    fn is_read_only(&self, start: u16, end: u16) -> bool {
+        (start..=end).any(|address| self.profile.is_read_only(self.selected_top_tab, address))
+    }
+
+    // Why the current table can't be written from start to end, None when it can
+    fn read_only_reason(&self, start: u16, end: u16) -> Option<String> {
+        match self.selected_top_tab {
+            SelectedTopTab::DiscreteInputs | SelectedTopTab::InputRegisters => Some(format!(
+                "{} are read-only, only Coils and Holding Registers can be written.",
+                self.selected_top_tab
+            )),
+            table => (start..=end)
+                .find(|address| self.profile.is_read_only(table, *address))
+                .map(|address| {
+                    format!(
+                        "{table} address {} is read-only in the profile.",
+                        address as u32 + 1
+                    )
+                }),
+        }
+    }
+
+    // A popup says why, so a refused key isn't mistaken for one that did nothing
+    async fn refuse_write(&mut self, reason: String) -> Result<()> {
+        self.beep()?;
+        self.sender.send(Action::Error(reason)).await?;
+        Ok(())
+    }
+
+    fn toggle_recording(&mut self) {
+        // Starting clears the last capture, stopping offers to save this one
+        match self.recording {
+            true => {
+                self.recording = false;
+                if !self.capture.is_empty() {
+                    self.app_mode = AppMode::Popup(PopupType::SaveCapture);
+                }
+            }
+            false => {
+                self.capture.clear();
+                self.recording = true;
+            }
+        }
+    }
+
+    fn set_colors(&mut self) {
+        self.colors = AppColors::from_config(
+            self.theme.palette(self.selected_top_tab),
+            &self.color_config,
+        );
+    }
+
+    fn beep(&mut self) -> Result<()> {
+        match self.bell {
+            Bell::Audible => {
+                print!("\x07");
+                std::io::stdout().flush()?;
+            }
+            Bell::Visual => self.flash_until = Some(Instant::now() + VISUAL_BELL_DURATION),
+            Bell::Off => {}
+        }
+        Ok(())
+    }
+
+    // Start and length of the ASCII popup's run, cut short at the end of the address space
+    fn ascii_range(&self) -> (u16, usize) {
+        let table = &self.tables[self.selected_top_tab as usize];
+        let start = table.table_address;
+        let count = self
+            .ascii_popup_registers
+            .min(table.total_address_space - start as usize);
+        (start, count)
+    }
+
+    // Every way of quitting goes through here, so queued changes aren't lost to a stray Esc
+    fn request_exit(&mut self) {
+        match self.confirm_quit && !self.queue_table_data.is_empty() {
+            true => self.app_mode = AppMode::Popup(PopupType::ConfirmQuit),
+            false => self.exit = true,
+        }
+    }
+
+    // Starts from the most recent connection when nothing has been typed yet
+    fn open_connection_popup(&mut self) {
+        self.app_mode = AppMode::Popup(PopupType::Connection);
+        if self.address_input.trim().is_empty() && self.port_input.trim().is_empty() {
+            self.select_recent(0);
         }
-        frame.render_widget(popup_content, area);
     }
 
-    async fn table_page_up(&mut self) {
-        let table = &mut self.tables[self.selected_top_tab as usize];
-        table.page_up().await;
+    fn select_recent(&mut self, index: usize) {
+        let Some(addr) = self.recent_connections.get(index) else {
+            return;
+        };
+        self.recent_index = Some(index);
+
+        // The popup joins address and port with ':', so IPv6 needs its brackets
+        self.address_input = match addr {
+            SocketAddr::V4(v4) => format!("{} ", v4.ip()),
+            SocketAddr::V6(v6) => format!("[{}] ", v6.ip()),
+        };
+        self.address_input_cursor = self.address_input.len() - 1;
+        self.port_input = format!("{} ", addr.port());
+        self.port_input_cursor = self.port_input.len() - 1;
     }
 
-    async fn table_move_up(&mut self) {
-        let table = &mut self.tables[self.selected_top_tab as usize];
-        table.move_up().await;
+    fn close_connection_popup(&mut self) {
+        self.app_mode = AppMode::Main;
+
+        self.address_input = String::from(" ");
+        self.address_input_cursor = 0;
+
+        self.port_input = String::from(" ");
+        self.port_input_cursor = 0;
+
+        self.connecting_popup_field = ConnectingField::Address;
+        self.recent_index = None;
     }
 
-    async fn table_page_down(&mut self) {
-        let table = &mut self.tables[self.selected_top_tab as usize];
-        table.page_down().await;
+    // Looked up off the UI task, a slow DNS server shouldn't freeze the screen
+    fn resolve_and_connect(&self, host: String, port: u16) {
+        let sender = self.sender.clone();
+        tokio::spawn(async move {
+            let action = match resolve_host(&host, port).await {
+                Ok(addr) => Action::Connect(addr),
+                Err(err) => Action::Error(format!("Couldn't Resolve {host}: {err}")),
+            };
+            let _ = sender.send(action).await;
+        });
     }
 
-    async fn table_move_down(&mut self) {
-        let table = &mut self.tables[self.selected_top_tab as usize];
-        table.move_down().await;
+    // Hostnames as well as IPv4 and IPv6 literals, with an optional zone after '%'
+    fn is_address_char(&self, c: char) -> bool {
+        c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '_' | ':' | '[' | ']' | '%')
     }
+}
 
-    fn table_move_left(&mut self) {
-        let table = &mut self.tables[self.selected_top_tab as usize];
-        table.move_left();
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::server::MockServer;
+    use ratatui::{Terminal, backend::TestBackend, crossterm::event::KeyEvent};
+
+    async fn press(app: &mut App, code: KeyCode) {
+        app.on_crossterm_event(Event::Key(KeyEvent::from(code)))
+            .await
+            .unwrap();
     }
 
-    fn table_move_right(&mut self) {
-        let table = &mut self.tables[self.selected_top_tab as usize];
-        table.move_right();
+    async fn press_ctrl(app: &mut App, c: char) {
+        let key = KeyEvent::new(KeyCode::Char(c), KeyModifiers::CONTROL);
+        app.on_crossterm_event(Event::Key(key)).await.unwrap();
     }
 
-    fn table_go_to_cell(&mut self, cell_address: u16) {
-        let table = &mut self.tables[self.selected_top_tab as usize];
-        table.go_to_cell(cell_address);
+    #[tokio::test]
+    async fn empty_port_does_not_connect() {
+        let mut app = App::new();
+        app.app_mode = AppMode::Popup(PopupType::Connection);
+        app.address_input = String::from("127.0.0.1 ");
+        app.address_input_cursor = 9;
+
+        press(&mut app, KeyCode::Enter).await;
+
+        assert!(app.receiver.try_recv().is_err());
+        assert!(matches!(
+            app.app_mode,
+            AppMode::Popup(PopupType::Connection)
+        ));
     }
 
-    fn queue_go_to_cell(&mut self, cell_address: u16, table_index: usize) {
-        let table = &mut self.tables[table_index];
-        self.selected_top_tab = SelectedTopTab::iter().nth(table_index).unwrap();
-        table.go_to_cell(cell_address)
+    #[tokio::test]
+    async fn hostnames_are_resolved_before_connecting() {
+        let mut app = App::new();
+        app.app_mode = AppMode::Popup(PopupType::Connection);
+        app.address_input = String::from(" ");
+        for c in "localhost".chars() {
+            press(&mut app, KeyCode::Char(c)).await;
+        }
+        app.port_input = String::from("502 ");
+
+        press(&mut app, KeyCode::Enter).await;
+
+        assert!(matches!(app.app_mode, AppMode::Main));
+        assert!(matches!(
+            app.receiver.recv().await,
+            Some(Action::Connect(addr)) if addr.ip().is_loopback() && addr.port() == 502
+        ));
     }
 
-    async fn modbus_apply_queued(&mut self) {
-        let commands = self.table_get_queued_commands();
-        let _ = self
-            .sender
-            .send(Action::ToModbus(ModbusCommandQueue::Write(commands)))
-            .await;
+    #[tokio::test]
+    async fn filled_connection_popup_connects() {
+        let mut app = App::new();
+        app.app_mode = AppMode::Popup(PopupType::Connection);
+        app.address_input = String::from("127.0.0.1 ");
+        app.port_input = String::from("502 ");
+
+        press(&mut app, KeyCode::Enter).await;
+
+        assert!(matches!(
+            app.receiver.try_recv(),
+            Ok(Action::Connect(addr)) if addr.port() == 502
+        ));
     }
 
-    fn table_apply_queued_cells(&mut self) {
-        for table in &mut self.tables {
-            let queued_keys: Vec<u16> = table
-                .data
+    #[tokio::test]
+    async fn settings_popup_changes_the_selected_setting() {
+        let mut app = App::new();
+        press(&mut app, KeyCode::Char(',')).await;
+        assert!(matches!(app.app_mode, AppMode::Popup(PopupType::Settings)));
+
+        press(&mut app, KeyCode::Up).await;
+        assert_eq!(app.settings_popup_row, Setting::DisplayMode);
+        press(&mut app, KeyCode::Enter).await;
+        assert_eq!(app.display_mode, DisplayMode::Signed);
+
+        for _ in 0..4 {
+            press(&mut app, KeyCode::Down).await;
+        }
+        assert_eq!(app.settings_popup_row, Setting::WrapAround);
+        press(&mut app, KeyCode::Enter).await;
+        assert!(!app.wrap_around);
+        assert!(app.tables.iter().all(|table| !table.wrap_around));
+
+        press(&mut app, KeyCode::Esc).await;
+        assert!(matches!(app.app_mode, AppMode::Main));
+    }
+
+    #[tokio::test]
+    async fn flags_for_one_run_are_not_saved() {
+        let path = std::env::temp_dir().join(format!("run_flags_{}", std::process::id()));
+        let saved_settings = Settings::default();
+        let mut settings = saved_settings;
+        settings.theme = Theme::Sky; // --theme sky
+        let mut app = App::new().with_settings(settings, saved_settings, Some(path.clone()));
+
+        app.change_setting(Setting::Bell).await;
+        let reloaded = Settings::from_file(&path).await;
+        let _ = std::fs::remove_file(&path);
+
+        assert!(matches!(app.theme, Theme::Sky));
+        assert!(matches!(reloaded.theme, Theme::PerTab));
+        assert!(matches!(reloaded.bell, Bell::Visual));
+    }
+
+    fn rendered_text(app: &mut App, width: u16, height: u16) -> String {
+        let mut terminal = Terminal::new(TestBackend::new(width, height)).unwrap();
+        terminal.draw(|frame| app.render(frame)).unwrap();
+        terminal
+            .backend()
+            .buffer()
+            .content()
+            .iter()
+            .map(|cell| cell.symbol())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn help_scrolls_to_the_last_table_binding() {
+        let last_binding = keymap::KEYMAP
+            .iter()
+            .rev()
+            .find(|binding| binding.context == KeyContext::Table)
+            .unwrap();
+        // Rows are joined without breaks, so a wrapped description is matched by its last word
+        let last_word = last_binding.description.rsplit(' ').next().unwrap();
+        let mut app = App::new();
+        press(&mut app, KeyCode::Char('?')).await;
+
+        let (width, height) = (MIN_TERMINAL_WIDTH, MIN_TERMINAL_HEIGHT);
+        assert!(rendered_text(&mut app, width, height).contains("Quit Application"));
+        assert!(!rendered_text(&mut app, width, height).contains(last_word));
+
+        press(&mut app, KeyCode::End).await;
+        assert!(rendered_text(&mut app, width, height).contains(last_word));
+        // Clamped while rendering, so scrolling back up starts from the bottom
+        let bottom = app.help_menu_scroll;
+        press(&mut app, KeyCode::Up).await;
+        assert_eq!(app.help_menu_scroll, bottom - 1);
+    }
+
+    #[tokio::test]
+    async fn list_view_shows_labels_and_queued_values() {
+        let mut app = App::new();
+        app.selected_top_tab = SelectedTopTab::HoldingRegisters;
+        app.labels.add(
+            SelectedTopTab::HoldingRegisters,
+            1,
+            1,
+            String::from("Pump Setpoint"),
+        );
+        app.table_go_to_cell(1);
+        app.table_queue_current_cell(1234);
+        assert!(!rendered_text(&mut app, 120, 30).contains("Label"));
+
+        press(&mut app, KeyCode::Char('l')).await;
+        let text = rendered_text(&mut app, 120, 30);
+        assert!(text.contains("Label"));
+        assert!(text.contains("0x40002"));
+        assert!(text.contains("Pump Setpoint"));
+        assert!(text.contains("1234"));
+
+        press(&mut app, KeyCode::Char('l')).await;
+        assert!(
+            app.tables[SelectedTopTab::HoldingRegisters as usize]
+                .list_view
+                .is_none()
+        );
+    }
+
+    #[tokio::test]
+    async fn list_view_scrolls_past_the_page() {
+        let mut app = App::new();
+        app.selected_top_tab = SelectedTopTab::HoldingRegisters;
+        press(&mut app, KeyCode::Char('l')).await;
+        let table = &mut app.tables[SelectedTopTab::HoldingRegisters as usize];
+        for address in 0..128 {
+            table.set_cell(address, CellType::Word(address));
+        }
+
+        app.table_go_to_cell(64);
+        let text = rendered_text(&mut app, 120, 30);
+        let memory_address = |address| SelectedTopTab::HoldingRegisters.memory_address(address);
+        assert!(text.contains(&memory_address(64)));
+        // Read on the page before, so still listed above the selection
+        assert!(text.contains(&memory_address(63)));
+
+        // Scrolling back up keeps the rows where they were until the selection leaves them
+        press(&mut app, KeyCode::Char('w')).await;
+        let text = rendered_text(&mut app, 120, 30);
+        assert!(text.contains(&memory_address(64)));
+    }
+
+    #[test]
+    fn cells_past_the_address_space_are_dimmed() {
+        let mut app = App::new();
+        app.selected_top_tab = SelectedTopTab::HoldingRegisters;
+        let struck_cells = |app: &mut App| {
+            let mut terminal = Terminal::new(TestBackend::new(120, 30)).unwrap();
+            terminal.draw(|frame| app.render(frame)).unwrap();
+            terminal
+                .backend()
+                .buffer()
+                .content()
                 .iter()
-                .filter_map(|(key, value)| {
-                    if let CellState::Queued = value.state {
-                        Some(*key)
-                    } else {
-                        None
-                    }
+                .filter(|cell| {
+                    cell.symbol() == "-" && cell.modifier.contains(Modifier::CROSSED_OUT)
                 })
-                .collect();
+                .count()
+        };
+        assert_eq!(struck_cells(&mut app), 0);
 
-            for key in queued_keys {
-                if let Some(cell) = table.data.get_mut(&key) {
-                    cell.apply();
-                }
-            }
-        }
-        self.refresh_queue_table();
+        app.tables[app.selected_top_tab as usize].set_address_space(3);
+        assert!(struck_cells(&mut app) > 0);
+
+        // Text runs stop at the last address too
+        app.tables[app.selected_top_tab as usize].go_to_cell(1);
+        app.ascii_popup_registers = 8;
+        assert_eq!(app.ascii_range(), (1, 2));
     }
 
-    fn refresh_queue_table(&mut self) {
-        self.queue_table_data = vec![];
-        for table in &self.tables {
-            let mut table_queued = table.get_queue_items();
-            self.queue_table_data.append(&mut table_queued);
+    #[tokio::test]
+    async fn wide_pinned_pages_scroll_to_the_selected_column() {
+        let mut app = App::new().with_page_size(Some((2, 40)));
+        let highlighted_cells = |app: &mut App| {
+            let mut terminal =
+                Terminal::new(TestBackend::new(MIN_TERMINAL_WIDTH, MIN_TERMINAL_HEIGHT)).unwrap();
+            terminal.draw(|frame| app.render(frame)).unwrap();
+            terminal
+                .backend()
+                .buffer()
+                .content()
+                .iter()
+                .filter(|cell| cell.modifier.contains(Modifier::REVERSED))
+                .count()
+        };
+        let first_column = highlighted_cells(&mut app);
+        assert!(first_column > 0);
+
+        for _ in 0..39 {
+            press(&mut app, KeyCode::Char('d')).await;
         }
-        if self.queue_item_index >= self.queue_table_data.len() && !self.queue_table_data.is_empty()
-        {
-            self.queue_item_index = self.queue_table_data.len() - 1;
+        let table = &app.tables[SelectedTopTab::Coils as usize];
+        assert_eq!(table.table_state.selected_column(), Some(39));
+        assert_eq!(highlighted_cells(&mut app), first_column);
+        assert!(app.tables[SelectedTopTab::Coils as usize].column_offset > 0);
+    }
+
+    #[test]
+    fn tiny_terminals_only_get_a_message() {
+        let mut app = App::new();
+        rendered_text(&mut app, 1, 1);
+        for (width, height) in [(20, 5), (MIN_TERMINAL_WIDTH, 10), (120, 20)] {
+            assert!(rendered_text(&mut app, width, height).contains("Terminal Too Small"));
         }
+        app.app_mode = AppMode::Popup(PopupType::Connection);
+        assert!(!rendered_text(&mut app, 80, 24).contains("Terminal Too Small"));
     }
 
-    fn queue_select_next_item(&mut self) {
-        self.queue_item_index = match self.queue_table_state.selected() {
-            Some(i) => {
-                if i >= self.queue_table_data.len() - 1 {
-                    0
-                } else {
-                    i + 1
-                }
-            }
-            None => 0,
+    #[tokio::test]
+    async fn empty_connection_inputs_still_render() {
+        let mut app = App::new();
+        app.app_mode = AppMode::Popup(PopupType::Connection);
+        app.address_input = String::from("1 ");
+        app.address_input_cursor = 2; // Past the trailing space
+        assert!(rendered_text(&mut app, 80, 24).contains("Address: 1"));
+
+        app.address_input.clear();
+        app.address_input_cursor = 0;
+        press(&mut app, KeyCode::Right).await;
+        press(&mut app, KeyCode::Delete).await;
+        assert!(rendered_text(&mut app, 80, 24).contains("Address:"));
+
+        app.connecting_popup_field = ConnectingField::Port;
+        app.port_input.clear();
+        app.port_input_cursor = 3;
+        press(&mut app, KeyCode::Delete).await;
+        assert!(rendered_text(&mut app, 80, 24).contains("Port:"));
+    }
+
+    #[test]
+    fn cell_details_show_every_base() {
+        let mut app = App::new();
+        app.selected_top_tab = SelectedTopTab::HoldingRegisters;
+        let table = &mut app.tables[SelectedTopTab::HoldingRegisters as usize];
+        table.go_to_cell(39);
+        assert_eq!(app.cell_details(), "Address 40 | Wire 39 | Not read");
+
+        let table = &mut app.tables[SelectedTopTab::HoldingRegisters as usize];
+        table.set_cell(39, CellType::Word(0x04D2));
+        assert_eq!(
+            app.cell_details(),
+            "Address 40 | Wire 39 | Dec 1234 | Hex 0x04D2 | Bin 0000 0100 1101 0010"
+        );
+
+        app.table_queue_current_cell(7);
+        assert!(app.cell_details().ends_with("| Queued, device has 1234"));
+    }
+
+    #[tokio::test]
+    async fn offline_popup_sets_the_macro_target() {
+        let mut app = App::new();
+        app.connection_popup_offline = true;
+        app.app_mode = AppMode::Popup(PopupType::Connection);
+        app.address_input = String::from("10.0.0.2 ");
+        app.port_input = String::from("1502 ");
+
+        press(&mut app, KeyCode::Enter).await;
+
+        assert!(app.receiver.try_recv().is_err());
+        assert!(matches!(app.connection_status, ConnectionStatus::Offline));
+        assert_eq!(app.current_ip_address, Some("10.0.0.2".parse().unwrap()));
+        assert_eq!(app.current_port, Some(1502));
+        assert!(app.can_queue());
+    }
+
+    #[tokio::test]
+    async fn recent_ipv6_targets_work_offline() {
+        let mut recent = RecentConnections::default();
+        recent.push("[::1]:502".parse().unwrap());
+        let mut app = App::new().with_recent_connections(recent);
+        app.open_connection_popup();
+        app.connection_popup_offline = true;
+
+        press(&mut app, KeyCode::Enter).await;
+
+        assert!(matches!(app.connection_status, ConnectionStatus::Offline));
+        assert_eq!(app.current_ip_address, Some("::1".parse().unwrap()));
+        app.selected_top_tab = SelectedTopTab::HoldingRegisters;
+        app.table_queue_current_cell(7);
+        assert!(app.queued_macro().is_some());
+    }
+
+    #[tokio::test]
+    async fn saving_a_macro_needs_a_target() {
+        let mut app = App::new();
+        app.selected_top_tab = SelectedTopTab::HoldingRegisters;
+        app.table_queue_current_cell(7);
+        assert!(app.queued_macro().is_none());
+
+        app.app_mode = AppMode::Popup(PopupType::SaveMacro(SaveMacroMode::Main));
+        press(&mut app, KeyCode::Char('x')).await;
+        press(&mut app, KeyCode::Enter).await;
+        assert!(matches!(app.app_mode, AppMode::Main));
+        assert!(matches!(app.receiver.try_recv(), Ok(Action::Error(_))));
+
+        // Connecting to an IPv6 literal or a hostname that resolved to one
+        app.current_ip_address = Some("::1".parse().unwrap());
+        app.current_port = Some(502);
+        assert_eq!(
+            app.queued_macro(),
+            Some(MagModCommandList::new(
+                "::1".parse().unwrap(),
+                502,
+                vec![(SelectedTopTab::HoldingRegisters, 0, CellType::Word(7))]
+            ))
+        );
+    }
+
+    #[tokio::test]
+    async fn saved_macros_keep_the_unit_id() {
+        let mut app = App::new().with_unit_id(3);
+        app.selected_top_tab = SelectedTopTab::HoldingRegisters;
+        app.table_queue_current_cell(7);
+        app.current_ip_address = Some("10.0.0.2".parse().unwrap());
+        app.current_port = Some(502);
+
+        let path = std::env::temp_dir().join(format!("unit_id_{}", std::process::id()));
+        app.queued_macro()
+            .unwrap()
+            .to_file(path.to_string_lossy().to_string(), true)
+            .await
+            .unwrap();
+        let file_path = path.with_extension("magmod");
+        let saved = MagModCommandList::from_file(&file_path).await;
+        let _ = std::fs::remove_file(&file_path);
+
+        assert_eq!(
+            saved.unwrap(),
+            MagModCommandList::new(
+                "10.0.0.2".parse().unwrap(),
+                502,
+                vec![(SelectedTopTab::HoldingRegisters, 0, CellType::Word(7))]
+            )
+            .with_unit_id(3)
+        );
+    }
+
+    #[tokio::test]
+    async fn connection_popup_offers_recent_connections() {
+        let mut recent = RecentConnections::default();
+        recent.push("10.0.0.2:1502".parse().unwrap());
+        recent.push("[::1]:502".parse().unwrap());
+        let mut app = App::new().with_recent_connections(recent);
+
+        app.open_connection_popup();
+        assert_eq!(app.address_input, "[::1] ");
+        assert_eq!(app.port_input, "502 ");
+
+        press(&mut app, KeyCode::Down).await;
+        press(&mut app, KeyCode::Enter).await;
+
+        assert!(matches!(
+            app.receiver.try_recv(),
+            Ok(Action::Connect(addr)) if addr == "10.0.0.2:1502".parse().unwrap()
+        ));
+    }
+
+    #[tokio::test]
+    async fn ctrl_c_quits_from_a_popup() {
+        let mut app = App::new();
+        app.app_mode = AppMode::Popup(PopupType::Goto);
+        press_ctrl(&mut app, 'c').await;
+        assert!(app.exit);
+
+        let mut app = App::new();
+        app.table_queue_current_cell(1);
+        press_ctrl(&mut app, 'c').await;
+        assert!(matches!(
+            app.app_mode,
+            AppMode::Popup(PopupType::ConfirmQuit)
+        ));
+        press_ctrl(&mut app, 'c').await;
+        assert!(app.exit);
+    }
+
+    #[tokio::test]
+    async fn connected_only_after_the_handshake() {
+        let server = MockServer::bind("127.0.0.1:0".parse().unwrap())
+            .await
+            .unwrap();
+        let addr = server.local_addr().unwrap();
+        tokio::spawn(server.run());
+        let mut app = App::new();
+
+        app.start_modbus_task(addr).await.unwrap();
+        assert!(matches!(
+            app.connection_status,
+            ConnectionStatus::Connecting
+        ));
+        assert!(rendered_text(&mut app, 160, 40).contains("(Connecting...)"));
+
+        let Some(Action::Connected(connected)) = app.receiver.recv().await else {
+            panic!("expected the task to report the connection");
         };
-        self.queue_table_state.select(Some(self.queue_item_index));
-        self.queue_scroll_state = self.queue_scroll_state.position(self.queue_item_index);
+        // A late message from an earlier attempt changes nothing
+        app.on_connected("127.0.0.1:1".parse().unwrap()).await;
+        assert!(matches!(
+            app.connection_status,
+            ConnectionStatus::Connecting
+        ));
+        app.on_connected(connected).await;
+        assert!(matches!(app.connection_status, ConnectionStatus::Connected));
+        app.stop_modbus_task().await;
     }
 
-    fn queue_select_previous_item(&mut self) {
-        self.queue_item_index = match self.queue_table_state.selected() {
-            Some(i) => {
-                if i == 0 {
-                    self.queue_table_data.len() - 1
-                } else {
-                    i - 1
-                }
+    #[tokio::test]
+    async fn prefetch_reads_the_table_on_screen_first() {
+        let server = MockServer::bind("127.0.0.1:0".parse().unwrap())
+            .await
+            .unwrap();
+        let addr = server.local_addr().unwrap();
+        tokio::spawn(server.run());
+        let mut app = App::new().with_prefetch(true);
+        app.selected_top_tab = SelectedTopTab::HoldingRegisters;
+
+        app.start_modbus_task(addr).await.unwrap();
+        let mut reads = vec![];
+        while reads.len() < 2 {
+            if let Some(Action::FromModbus(ModbusCommandQueue::Write(values))) =
+                app.receiver.recv().await
+            {
+                let mut tables: Vec<SelectedTopTab> =
+                    values.iter().map(|(table, _, _)| *table).collect();
+                tables.sort();
+                tables.dedup();
+                reads.push(tables);
             }
-            None => 0,
-        };
+        }
+        app.stop_modbus_task().await;
+
+        assert_eq!(reads[0], [SelectedTopTab::HoldingRegisters]);
+        assert_eq!(
+            reads[1],
+            [
+                SelectedTopTab::Coils,
+                SelectedTopTab::DiscreteInputs,
+                SelectedTopTab::InputRegisters
+            ]
+        );
+    }
 
-        self.queue_table_state.select(Some(self.queue_item_index));
-        self.queue_scroll_state = self.queue_scroll_state.position(self.queue_item_index);
+    #[tokio::test]
+    async fn quitting_with_a_queue_asks_first() {
+        let mut app = App::new();
+        app.table_queue_current_cell(1);
+        assert_eq!(app.queue_table_data.len(), 1);
+
+        press(&mut app, KeyCode::Esc).await;
+        assert!(matches!(
+            app.app_mode,
+            AppMode::Popup(PopupType::ConfirmQuit)
+        ));
+        press(&mut app, KeyCode::Char('n')).await;
+        assert!(!app.exit);
+
+        press(&mut app, KeyCode::Esc).await;
+        press(&mut app, KeyCode::Char('y')).await;
+        assert!(app.exit);
+
+        let mut app = App::new().with_confirm_quit(false);
+        app.table_queue_current_cell(1);
+        press(&mut app, KeyCode::Esc).await;
+        assert!(app.exit);
     }
 
-    fn queue_revert_item(&mut self) {
-        let item_address = self.queue_table_data[self.queue_item_index].address;
-        let table_index = self.queue_table_data[self.queue_item_index].table_index;
+    #[tokio::test]
+    async fn ascii_popup_queues_the_whole_run() {
+        let mut app = App::new();
+        app.connection_status = ConnectionStatus::Connected;
+        app.selected_top_tab = SelectedTopTab::HoldingRegisters;
+        // Left over from a longer string, so it has to be cleared
+        app.tables[SelectedTopTab::HoldingRegisters as usize].set_cell(3, CellType::Word(0x4142));
+
+        press(&mut app, KeyCode::Char('A')).await;
+        press(&mut app, KeyCode::Down).await;
+        for c in "PUMP-1".chars() {
+            press(&mut app, KeyCode::Char(c)).await;
+        }
+        press(&mut app, KeyCode::Enter).await;
+
+        let mut queued: Vec<(u16, u16)> = app
+            .queue_table_data
+            .iter()
+            .map(|item| (item.address, item.cell.queued_content.to_u16()))
+            .collect();
+        queued.sort();
+        // Registers that already hold their new value aren't queued
+        assert_eq!(queued, [(0, 0x5055), (1, 0x4D50), (2, 0x2D31), (3, 0x0000)]);
+    }
 
-        let table = &mut self.tables[table_index];
+    #[tokio::test]
+    async fn range_reads_ignore_the_page() {
+        let mut app = App::new();
+        app.connection_status = ConnectionStatus::Connected;
+        app.selected_top_tab = SelectedTopTab::InputRegisters;
 
-        if let Some(item) = table.data.get_mut(&item_address) {
-            item.revert();
+        press(&mut app, KeyCode::Char('B')).await;
+        for c in "30001,300".chars() {
+            press(&mut app, KeyCode::Char(c)).await;
         }
+        press(&mut app, KeyCode::Enter).await;
+
+        assert!(matches!(app.app_mode, AppMode::Main));
+        assert!(matches!(
+            app.receiver.try_recv(),
+            Ok(Action::ToModbus(ModbusCommandQueue::Read(commands)))
+                if commands == [(SelectedTopTab::InputRegisters, 30000, 300)]
+        ));
+    }
 
-        self.refresh_queue_table();
+    #[tokio::test]
+    async fn tab_switches_read_the_new_table_when_enabled() {
+        let mut app = App::new();
+        app.connection_status = ConnectionStatus::Connected;
+
+        press(&mut app, KeyCode::Char('e')).await;
+        assert!(app.receiver.try_recv().is_err());
+
+        app.read_on_tab_switch = true;
+        press(&mut app, KeyCode::Char('e')).await;
+        assert!(matches!(
+            app.receiver.try_recv(),
+            Ok(Action::ToModbus(ModbusCommandQueue::Read(commands)))
+                if commands[0].0 == SelectedTopTab::InputRegisters
+        ));
+
+        // Jumping to the table already shown doesn't read it again
+        press(&mut app, KeyCode::Char('3')).await;
+        assert!(app.receiver.try_recv().is_err());
+        press(&mut app, KeyCode::Char('4')).await;
+        assert!(matches!(
+            app.receiver.try_recv(),
+            Ok(Action::ToModbus(ModbusCommandQueue::Read(commands)))
+                if commands[0].0 == SelectedTopTab::HoldingRegisters
+        ));
     }
 
-    fn table_get_queued_commands(&self) -> Vec<ModbusWriteCommand> {
-        let mut commands: Vec<ModbusWriteCommand> = vec![];
-        for table in &self.tables {
-            let table_commands: Vec<ModbusWriteCommand> = table
-                .get_queue_items()
-                .into_iter()
-                .map(|queue_item| {
-                    (
-                        table.table_type,
-                        queue_item.address,
-                        queue_item.cell.queued_content,
-                    )
-                })
-                .collect();
-            commands.extend(table_commands);
+    #[tokio::test]
+    async fn read_only_writes_say_why() {
+        let profile = DeviceProfile::parse("[holding_registers 1-10]\naccess = read-only\n");
+        let mut app = App::new().with_profile(profile.unwrap());
+        app.connection_status = ConnectionStatus::Connected;
+        app.selected_top_tab = SelectedTopTab::HoldingRegisters;
+
+        press(&mut app, KeyCode::Char(' ')).await;
+        assert!(matches!(
+            app.receiver.try_recv(),
+            Ok(Action::Error(message)) if message.contains("address 1 is read-only")
+        ));
+
+        // Text typed into the ASCII popup is refused the same way, offline or not
+        app.connection_status = ConnectionStatus::Offline;
+        press(&mut app, KeyCode::Char('A')).await;
+        assert!(matches!(app.app_mode, AppMode::Popup(PopupType::Ascii)));
+        press(&mut app, KeyCode::Char('h')).await;
+        press(&mut app, KeyCode::Enter).await;
+        assert!(matches!(
+            app.receiver.try_recv(),
+            Ok(Action::Error(message)) if message.contains("address 1 is read-only")
+        ));
+        app.app_mode = AppMode::Main;
+        app.connection_status = ConnectionStatus::Connected;
+
+        app.selected_top_tab = SelectedTopTab::InputRegisters;
+        press(&mut app, KeyCode::Char('+')).await;
+        assert!(matches!(
+            app.receiver.try_recv(),
+            Ok(Action::Error(message)) if message.starts_with("Input Registers are read-only")
+        ));
+        // Space on an input still pins an assertion
+        press(&mut app, KeyCode::Char(' ')).await;
+        assert!(app.receiver.try_recv().is_err());
+        assert_eq!(app.queue_table_data.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn typed_edits_must_fit_the_type() {
+        let profile = DeviceProfile::parse(
+            "[holding_registers 1]\ntype = i16\n[holding_registers 3-4]\ntype = i32\n",
+        );
+        let mut app = App::new().with_profile(profile.unwrap());
+        app.connection_status = ConnectionStatus::Connected;
+        app.selected_top_tab = SelectedTopTab::HoldingRegisters;
+        let table = SelectedTopTab::HoldingRegisters as usize;
+
+        press(&mut app, KeyCode::Char(' ')).await;
+        assert!(rendered_text(&mut app, 120, 30).contains("i16: -32768 to 32767"));
+        for c in "40000".chars() {
+            press(&mut app, KeyCode::Char(c)).await;
         }
-        commands
+        press(&mut app, KeyCode::Enter).await;
+        assert!(matches!(app.app_mode, AppMode::Popup(PopupType::Edit)));
+
+        press_ctrl(&mut app, 'u').await;
+        for c in "-5".chars() {
+            press(&mut app, KeyCode::Char(c)).await;
+        }
+        press(&mut app, KeyCode::Enter).await;
+        assert!(matches!(app.app_mode, AppMode::Main));
+        assert_eq!(
+            app.tables[table].data[&0].queued_content,
+            CellType::Word(0xFFFB)
+        );
+
+        // Editing either half of a 32-bit value writes the whole pair
+        app.table_go_to_cell(3);
+        press(&mut app, KeyCode::Char(' ')).await;
+        for c in "-2".chars() {
+            press(&mut app, KeyCode::Char(c)).await;
+        }
+        press(&mut app, KeyCode::Enter).await;
+        let words = app.word_order.split(0xFFFF_FFFE);
+        assert_eq!(
+            app.tables[table].data[&2].queued_content,
+            CellType::Word(words[0])
+        );
+        assert_eq!(
+            app.tables[table].data[&3].queued_content,
+            CellType::Word(words[1])
+        );
+        assert_eq!(app.tables[table].table_address, 3);
     }
 
-    fn table_queue_current_cell(&mut self, new_value: u16) {
-        let table = &mut self.tables[self.selected_top_tab as usize];
-        match table.table_type {
-            SelectedTopTab::Coils | SelectedTopTab::DiscreteInputs => {
-                match new_value {
-                    0 => table.queue_current_cell(CellType::Coil(false)),
-                    _ => table.queue_current_cell(CellType::Coil(true)),
-                };
+    #[tokio::test]
+    async fn tick_refresh_polls_tables_that_are_not_shown() {
+        let mut app = App::new();
+        app.connection_status = ConnectionStatus::Connected;
+        press(&mut app, KeyCode::Char('T')).await;
+        press(&mut app, KeyCode::Char('e')).await;
+        assert_eq!(app.selected_top_tab, SelectedTopTab::DiscreteInputs);
+
+        app.modbus_read_scheduled().await;
+
+        match app.receiver.try_recv() {
+            Ok(Action::ToModbus(ModbusCommandQueue::Read(commands))) => {
+                assert_eq!(commands.len(), 1);
+                assert_eq!(commands[0].0, SelectedTopTab::Coils);
             }
-            SelectedTopTab::InputRegisters | SelectedTopTab::HoldingRegisters => {
-                table.queue_current_cell(CellType::Word(new_value))
+            _ => panic!("Expected a read of the coils page"),
+        }
+    }
+
+    #[tokio::test]
+    async fn due_polls_go_out_in_one_batch() {
+        let mut app = App::new()
+            .with_poll_schedule(PollSchedule::parse("input_registers,10-12,500").unwrap());
+        app.connection_status = ConnectionStatus::Connected;
+        press(&mut app, KeyCode::Char('T')).await;
+
+        app.modbus_read_scheduled().await;
+        match app.receiver.try_recv() {
+            Ok(Action::ToModbus(ModbusCommandQueue::Read(commands))) => {
+                assert_eq!(commands.len(), 2);
+                assert_eq!(commands[0].0, SelectedTopTab::Coils);
+                assert_eq!(commands[1], (SelectedTopTab::InputRegisters, 9, 3));
             }
+            _ => panic!("Expected one read for the page and the scheduled range"),
         }
-        self.refresh_queue_table();
+
+        // Neither interval has passed yet
+        app.modbus_read_scheduled().await;
+        assert!(app.receiver.try_recv().is_err());
     }
 
-    fn table_revert_current_cell(&mut self) {
-        let table = &mut self.tables[self.selected_top_tab as usize];
-        table.revert_current_cell();
-        self.refresh_queue_table();
+    #[tokio::test]
+    async fn queue_count_splits_bits_and_words() {
+        let mut app = App::new();
+        app.table_queue_current_cell(1);
+        app.selected_top_tab = SelectedTopTab::HoldingRegisters;
+        app.table_queue_current_cell(7);
+        app.table_go_to_cell(1);
+        app.table_queue_current_cell(8);
+        assert_eq!(app.queue_count(), (1, 2));
+
+        app.table_revert_current_cell();
+        assert_eq!(app.queue_count(), (1, 1));
     }
 
-    fn table_toggle_current_cell(&mut self) {
-        let table = &mut self.tables[self.selected_top_tab as usize];
-        table.toggle_current_coil();
-        self.refresh_queue_table();
+    #[tokio::test]
+    async fn revert_page_leaves_other_pages_queued() {
+        let mut app = App::new();
+        app.selected_top_tab = SelectedTopTab::HoldingRegisters;
+        let page_size = app.tables[SelectedTopTab::HoldingRegisters as usize].page_size() as u16;
+        for address in [0, page_size - 1, page_size] {
+            app.table_go_to_cell(address);
+            app.table_queue_current_cell(5);
+        }
+        app.table_go_to_cell(1);
+
+        press(&mut app, KeyCode::Char('U')).await;
+
+        assert_eq!(app.queue_table_data.len(), 1);
+        assert_eq!(app.queue_table_data[0].address, page_size);
     }
 
-    async fn modbus_read_current_page(&mut self) {
-        let table = &self.tables[self.selected_top_tab as usize];
-        let amount = (table.table_rows * table.table_cols) as u16;
+    #[tokio::test]
+    async fn signed_edit_queues_twos_complement() {
+        let mut app = App::new();
+        app.selected_top_tab = SelectedTopTab::HoldingRegisters;
+        press(&mut app, KeyCode::Char('x')).await;
 
-        if let ConnectionStatus::Connected = self.connection_status {
-            let command: Vec<ModbusReadCommand> = vec![(
-                self.selected_top_tab,
-                table.table_address / amount * amount,
-                amount,
-            )];
-            let _ = self
-                .sender
-                .send(Action::ToModbus(ModbusCommandQueue::Read(command)))
-                .await;
+        for (text, expected) in [("-40", 65496), ("-32768", 0x8000), ("32767", 0x7FFF)] {
+            app.app_mode = AppMode::Popup(PopupType::Edit);
+            for c in text.chars() {
+                press(&mut app, KeyCode::Char(c)).await;
+            }
+            press(&mut app, KeyCode::Enter).await;
+            assert_eq!(
+                app.tables[SelectedTopTab::HoldingRegisters as usize]
+                    .current_cell()
+                    .queued_content,
+                CellType::Word(expected)
+            );
+        }
+
+        // Out of range for i16, and not split into a register pair while signed
+        app.app_mode = AppMode::Popup(PopupType::Edit);
+        for c in "32768".chars() {
+            press(&mut app, KeyCode::Char(c)).await;
         }
+        press(&mut app, KeyCode::Enter).await;
+        assert!(matches!(app.app_mode, AppMode::Popup(PopupType::Edit)));
     }
 
-    fn next_top_tab(&mut self) {
-        self.selected_top_tab = self.selected_top_tab.next();
+    #[tokio::test]
+    async fn oversized_edits_only_pair_in_the_wide_mode() {
+        let mut app = App::new();
+        app.selected_top_tab = SelectedTopTab::HoldingRegisters;
+        app.app_mode = AppMode::Popup(PopupType::Edit);
+        for c in "70000".chars() {
+            press(&mut app, KeyCode::Char(c)).await;
+        }
+        press(&mut app, KeyCode::Enter).await;
+        assert!(matches!(app.app_mode, AppMode::Popup(PopupType::Edit)));
+        assert!(app.queue_table_data.is_empty());
+
+        press(&mut app, KeyCode::Tab).await;
+        for c in "70000".chars() {
+            press(&mut app, KeyCode::Char(c)).await;
+        }
+        press(&mut app, KeyCode::Enter).await;
+        assert!(matches!(app.app_mode, AppMode::Main));
+        assert_eq!(app.queue_table_data.len(), 2);
     }
 
-    fn previous_top_tab(&mut self) {
-        self.selected_top_tab = self.selected_top_tab.previous();
+    #[tokio::test]
+    async fn hex_edits_pair_like_decimal_ones() {
+        let mut app = App::new();
+        app.selected_top_tab = SelectedTopTab::HoldingRegisters;
+        app.edit_popup_base = InputBase::Hexadecimal;
+        app.app_mode = AppMode::Popup(PopupType::Edit);
+        app.edit_popup_input = String::from("11170");
+        press(&mut app, KeyCode::Enter).await;
+        assert!(matches!(app.app_mode, AppMode::Popup(PopupType::Edit)));
+
+        app.edit_popup_wide = true;
+        press(&mut app, KeyCode::Enter).await;
+        assert!(matches!(app.app_mode, AppMode::Main));
+        assert_eq!(app.queue_table_data.len(), 2);
+
+        app.tables[SelectedTopTab::HoldingRegisters as usize].go_to_cell(u16::MAX);
+        app.app_mode = AppMode::Popup(PopupType::Edit);
+        app.edit_popup_input = String::from("1");
+        press(&mut app, KeyCode::Enter).await;
+        assert!(matches!(
+            app.app_mode,
+            AppMode::Popup(PopupType::Error(message)) if message.contains("No register after 65536")
+        ));
     }
 
-    fn next_bottom_tab(&mut self) {
-        self.selected_bottom_tab = self.selected_bottom_tab.next();
+    #[tokio::test]
+    async fn wide_values_stop_at_the_address_space() {
+        let profile = DeviceProfile::parse("[holding_registers 9-10]\ntype = u32\n");
+        let mut app = App::new().with_profile(profile.unwrap());
+        app.selected_top_tab = SelectedTopTab::HoldingRegisters;
+        let table = &mut app.tables[SelectedTopTab::HoldingRegisters as usize];
+        table.set_address_space(9);
+        table.go_to_cell(8);
+
+        app.app_mode = AppMode::Popup(PopupType::Edit);
+        app.edit_popup_input = String::from("70000");
+        press(&mut app, KeyCode::Enter).await;
+        assert!(matches!(
+            &app.app_mode,
+            AppMode::Popup(PopupType::Error(message)) if message.contains("No register after 9")
+        ));
+
+        app.profile = DeviceProfile::default();
+        app.edit_popup_wide = true;
+        app.app_mode = AppMode::Popup(PopupType::Edit);
+        app.edit_popup_input = String::from("70000");
+        press(&mut app, KeyCode::Enter).await;
+        assert!(matches!(
+            &app.app_mode,
+            AppMode::Popup(PopupType::Error(message)) if message.contains("No register after 9")
+        ));
+        assert!(app.queue_table_data.is_empty());
+
+        // The run of words never leaves the table, whoever queues it
+        let table = &mut app.tables[SelectedTopTab::HoldingRegisters as usize];
+        table.queue_current_words(&[1, 2, 3]);
+        assert!(!table.data.contains_key(&9));
     }
 
-    fn previous_bottom_tab(&mut self) {
-        self.selected_bottom_tab = self.selected_bottom_tab.previous();
+    #[tokio::test]
+    async fn visual_bell_flashes_on_invalid_input() {
+        let mut app = App::new().with_bell(Bell::Visual);
+        app.app_mode = AppMode::Popup(PopupType::Goto);
+        app.goto_popup_input = String::from("0");
+
+        press(&mut app, KeyCode::Enter).await;
+
+        assert!(app.flash_until.is_some());
+        assert!(matches!(app.app_mode, AppMode::Popup(PopupType::Goto)));
     }
 
-    fn get_table_stats(&self, area: Rect) -> (usize, usize, usize, usize) {
-        let row_height: usize = 1;
-        let column_length: usize = match self.selected_top_tab {
-            SelectedTopTab::Coils | SelectedTopTab::DiscreteInputs => 3,
-            SelectedTopTab::InputRegisters | SelectedTopTab::HoldingRegisters => 7,
-        };
-        let max_rows = (area.height as usize).saturating_sub(2) / row_height;
-        let max_cols = match self.selected_top_tab {
-            SelectedTopTab::Coils | SelectedTopTab::DiscreteInputs => 16,
-            SelectedTopTab::InputRegisters | SelectedTopTab::HoldingRegisters => 8,
-        };
+    #[tokio::test]
+    async fn goto_reaches_last_address() {
+        let mut app = App::new();
+        app.app_mode = AppMode::Popup(PopupType::Goto);
+        app.goto_popup_input = String::from("65536");
 
-        (row_height, column_length, max_rows, max_cols)
+        press(&mut app, KeyCode::Enter).await;
+
+        let table = &app.tables[app.selected_top_tab as usize];
+        assert!(matches!(app.app_mode, AppMode::Main));
+        assert_eq!(table.table_address, 65535);
+        assert_eq!(table.current_page_read(), (table.table_type, 65408, 128));
     }
 
-    fn set_colors(&mut self) {
-        self.colors = AppColors::new(&PALETTES[self.selected_top_tab as usize]);
+    #[tokio::test]
+    async fn ctrl_u_clears_the_popup_inputs() {
+        let mut app = App::new();
+        app.app_mode = AppMode::Popup(PopupType::Goto);
+        for c in "1234".chars() {
+            press(&mut app, KeyCode::Char(c)).await;
+        }
+        press_ctrl(&mut app, 'u').await;
+        assert_eq!(
+            (app.goto_popup_input.as_str(), app.goto_popup_cursor),
+            ("", 0)
+        );
+        assert!(matches!(app.app_mode, AppMode::Popup(PopupType::Goto)));
+
+        app.app_mode = AppMode::Popup(PopupType::Edit);
+        app.edit_popup_input = String::from("99");
+        app.edit_popup_cursor = 2;
+        press_ctrl(&mut app, 'u').await;
+        assert_eq!(
+            (app.edit_popup_input.as_str(), app.edit_popup_cursor),
+            ("", 0)
+        );
+
+        app.app_mode = AppMode::Popup(PopupType::SaveMacro(SaveMacroMode::Main));
+        app.macro_popup_input = String::from("setup");
+        app.macro_popup_cursor = 5;
+        press_ctrl(&mut app, 'u').await;
+        assert_eq!(
+            (app.macro_popup_input.as_str(), app.macro_popup_cursor),
+            ("", 0)
+        );
     }
 
-    fn beep(&self) -> Result<()> {
-        print!("\x07");
-        std::io::stdout().flush()?;
-        Ok(())
+    #[tokio::test]
+    async fn popup_inputs_edit_at_the_cursor() {
+        let mut app = App::new();
+        app.app_mode = AppMode::Popup(PopupType::Goto);
+        for c in "1234".chars() {
+            press(&mut app, KeyCode::Char(c)).await;
+        }
+        press(&mut app, KeyCode::Left).await;
+        press(&mut app, KeyCode::Left).await;
+        press(&mut app, KeyCode::Backspace).await;
+        press(&mut app, KeyCode::Char('9')).await;
+        assert_eq!(app.goto_popup_input, "1934");
+        press(&mut app, KeyCode::Home).await;
+        press(&mut app, KeyCode::Delete).await;
+        press(&mut app, KeyCode::End).await;
+        press(&mut app, KeyCode::Char('5')).await;
+        assert_eq!(
+            (app.goto_popup_input.as_str(), app.goto_popup_cursor),
+            ("9345", 4)
+        );
+        assert!(rendered_text(&mut app, 80, 24).contains("9345"));
+
+        app.app_mode = AppMode::Popup(PopupType::Edit);
+        for c in "25".chars() {
+            press(&mut app, KeyCode::Char(c)).await;
+        }
+        press(&mut app, KeyCode::Right).await;
+        press(&mut app, KeyCode::Left).await;
+        press(&mut app, KeyCode::Char('0')).await;
+        assert_eq!(
+            (app.edit_popup_input.as_str(), app.edit_popup_cursor),
+            ("205", 2)
+        );
     }
 
-    fn is_address_char(&self, c: char) -> bool {
-        matches!(c, 'A'..='F' | 'a'..='f' | '0'..='9' | '.' | ':' | '[' | ']' | '%')
+    #[tokio::test]
+    async fn go_to_last_matches_go_to_cell() {
+        let mut app = App::new();
+        for table in app.tables.iter_mut() {
+            table.go_to_last().await;
+            let last_address = table.table_address;
+            let last_page = table.page_offset;
+
+            table.go_to_cell(65535);
+
+            assert_eq!(last_address, 65535);
+            assert_eq!(table.page_offset, last_page);
+            assert_eq!(table.page_offset + 1, table.page_count());
+        }
     }
 }
@@ -12,7 +12,11 @@
 //!    See the License for the specific language governing permissions and
 //!    limitations under the License.
 
+use crate::utils::invalid_line;
 use ratatui::style::{Color, palette::tailwind};
+use std::{path::Path, str::FromStr};
+use tokio::fs;
+
 pub const PALETTES: [tailwind::Palette; 4] = [
     tailwind::ROSE,    // Coil Outputs
     tailwind::AMBER,   // Digital Outputs
@@ -52,4 +56,66 @@ impl AppColors {
             table_unselected_cell_fg: tailwind::SLATE.c500,
         }
     }
+
+    // Config overrides win over the palette, unset fields keep the palette color
+    pub fn from_config(color: &tailwind::Palette, config: &ColorConfig) -> Self {
+        let mut colors = Self::new(color);
+        for (field, value) in &config.colors {
+            colors.set(field, *value);
+        }
+        colors
+    }
+
+    // Returns false for names that aren't an AppColors field
+    fn set(&mut self, field: &str, value: Color) -> bool {
+        let slot = match field {
+            "connection_connected_fg" => &mut self.connection_connected_fg,
+            "connection_not_selected_fg" => &mut self.connection_not_selected_fg,
+            "section_selected_fg" => &mut self.section_selected_fg,
+            "section_unselected_fg" => &mut self.section_unselected_fg,
+            "table_normal_cell_bg" => &mut self.table_normal_cell_bg,
+            "table_alt_cell_bg" => &mut self.table_alt_cell_bg,
+            "table_unselected_normal_cell_bg" => &mut self.table_unselected_normal_cell_bg,
+            "table_unselected_alt_cell_bg" => &mut self.table_unselected_alt_cell_bg,
+            "table_selected_cell_fg" => &mut self.table_selected_cell_fg,
+            "table_unselected_cell_fg" => &mut self.table_unselected_cell_fg,
+            _ => return false,
+        };
+        *slot = value;
+        true
+    }
+}
+
+#[derive(Default)]
+pub struct ColorConfig {
+    colors: Vec<(String, Color)>, // AppColors field name, Color
+}
+
+impl ColorConfig {
+    // Line format: field,color
+    // Colors are #RRGGBB hex, a 0-255 terminal index or a named color like "light_blue"
+    pub async fn from_file<P: AsRef<Path>>(filename: P) -> std::io::Result<Self> {
+        let file = fs::read_to_string(filename).await?;
+        let mut colors = vec![];
+
+        for (index, line) in file.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let Some((field, value)) = line.split_once(',') else {
+                return Err(invalid_line(index, "Expected field,color"));
+            };
+            let field = field.trim().to_lowercase();
+            let value =
+                Color::from_str(value.trim()).map_err(|_| invalid_line(index, "Invalid color"))?;
+            if !AppColors::new(&PALETTES[0]).set(&field, value) {
+                return Err(invalid_line(index, "Unknown color field"));
+            }
+            colors.push((field, value));
+        }
+
+        Ok(Self { colors })
+    }
 }
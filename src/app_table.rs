@@ -13,11 +13,15 @@
 //!    limitations under the License.
 
 use crate::{
-    enums::{Action, CellState, CellType, SelectedTopTab},
+    enums::{Action, CellState, CellType, SelectedTopTab, WriteMode},
     queue::QueueItem,
+    utils::ModbusReadCommand,
 };
 use ratatui::widgets::TableState;
-use std::collections::HashMap;
+use std::{
+    collections::{HashMap, HashSet},
+    time::Instant,
+};
 use tokio::sync::mpsc::Sender;
 
 #[derive(Clone)]
@@ -26,6 +30,7 @@ pub struct TableCell {
     pub queued_content: CellType,
     pub state: CellState,
     pub table_type: SelectedTopTab,
+    pub write_mode: WriteMode,
 }
 
 impl TableCell {
@@ -45,15 +50,36 @@ impl TableCell {
             },
             state: CellState::Normal,
             table_type,
+            write_mode: WriteMode::Single,
+        }
+    }
+
+    pub fn display_content(&self) -> CellType {
+        match self.state {
+            CellState::Normal | CellState::Asserted => self.original_content,
+            CellState::Queued => self.queued_content,
         }
     }
 
     pub fn apply(&mut self) {
         self.original_content = self.queued_content;
-        self.state = CellState::Normal
+        self.state = CellState::Normal;
+        self.write_mode = WriteMode::Single
+    }
+
+    pub fn write_masks(&self) -> Option<(u16, u16)> {
+        // AND/OR masks that only touch the bits changed since the last read
+        match (self.original_content, self.queued_content) {
+            (CellType::Word(original), CellType::Word(queued)) => {
+                let changed = original ^ queued;
+                Some((!changed, queued & changed))
+            }
+            _ => None,
+        }
     }
 
     fn queue(&mut self, new_value: CellType) {
+        self.write_mode = WriteMode::Single;
         self.queued_content = new_value;
         self.state = if self.queued_content == self.original_content {
             CellState::Normal
@@ -64,7 +90,22 @@ impl TableCell {
 
     pub fn revert(&mut self) {
         self.queued_content = self.original_content;
-        self.state = CellState::Normal
+        self.state = CellState::Normal;
+        self.write_mode = WriteMode::Single
+    }
+
+    pub fn toggle_read_write(&mut self) -> bool {
+        // Only queued holding registers can be sent with 0x17
+        if self.table_type != SelectedTopTab::HoldingRegisters
+            || !matches!(self.state, CellState::Queued)
+        {
+            return false;
+        }
+        self.write_mode = match self.write_mode {
+            WriteMode::ReadWrite => WriteMode::Single,
+            _ => WriteMode::ReadWrite,
+        };
+        true
     }
 
     fn set(&mut self, new_value: CellType) {
@@ -73,12 +114,23 @@ impl TableCell {
                 self.original_content = new_value;
                 self.queued_content = new_value;
             }
-            CellState::Queued => {
+            CellState::Queued | CellState::Asserted => {
                 self.original_content = new_value;
             }
         }
     }
 
+    fn toggle_assertion(&mut self) {
+        // Used for read-only tables, pins the current value as the expected value
+        match self.state {
+            CellState::Asserted => self.revert(),
+            _ => {
+                self.queued_content = self.original_content;
+                self.state = CellState::Asserted
+            }
+        }
+    }
+
     fn toggle(&mut self) {
         // Used for coils, not words
         match self.queued_content {
@@ -107,7 +159,16 @@ pub struct AppTable {
     pub table_type: SelectedTopTab,
     pub total_address_space: usize,
     pub data: HashMap<u16, TableCell>,
+    pub illegal_addresses: HashSet<u16>, // Rejected by the device on the last read of them
     pub page_offset: usize,
+    pub page_refresh: bool, // Reads the page every time you change pages
+    pub tick_refresh: bool, // Reads the page every tick, even while another table is shown
+    pub wrap_around: bool,  // Moving past either end jumps to the other, or stops there when off
+    pub last_read: Option<Instant>, // Any value of this table arriving from the device
+    pub selection_anchor: Option<u16>, // The range runs from here to the selected address
+    pub list_view: Option<(usize, usize)>, // Grid rows and columns to go back to, None in the grid
+    pub list_offset: usize, // First row of the list view on screen
+    pub column_offset: usize, // First grid column on screen, when the page is wider than the window
     pub sender: Sender<Action>,
 }
 
@@ -122,9 +183,18 @@ impl AppTable {
             table_state: TableState::default().with_selected_cell(Some((0, 0))),
             table_address: 0,
             table_type,
-            total_address_space: 65535, // 1 - 65535
+            total_address_space: u16::MAX as usize + 1, // 0 - 65535 on the wire
             data: HashMap::new(),
+            illegal_addresses: HashSet::new(),
             page_offset: 0,
+            page_refresh: false,
+            tick_refresh: false,
+            wrap_around: true,
+            last_read: None,
+            selection_anchor: None,
+            list_view: None,
+            list_offset: 0,
+            column_offset: 0,
             sender,
         }
     }
@@ -140,14 +210,60 @@ impl AppTable {
             .collect::<Vec<TableCell>>()
     }
 
+    pub fn current_cell(&self) -> TableCell {
+        self.data
+            .get(&self.table_address)
+            .cloned()
+            .unwrap_or_else(|| TableCell::new(self.table_type))
+    }
+
     pub fn page_size(&self) -> usize {
         self.table_rows * self.table_cols
     }
 
+    // Devices with fewer addresses stop the last page early instead of showing 65536 cells
+    pub fn set_address_space(&mut self, total_address_space: usize) {
+        self.total_address_space = total_address_space.clamp(1, u16::MAX as usize + 1);
+        self.go_to_cell(self.table_address);
+    }
+
+    pub fn contains(&self, address: u16) -> bool {
+        self.index_exists(address as usize)
+    }
+
+    // Indexes past the device's last address still fill out the last page, but can't be
+    // selected or edited
+    pub fn index_exists(&self, index: usize) -> bool {
+        index < self.total_address_space
+    }
+
+    // First and last address on the current page, clamped so the last page stops at 65535
+    pub fn page_range(&self) -> (u16, u16) {
+        let start_index = self.page_offset * self.page_size();
+        let end_index = usize::min(start_index + self.page_size(), self.total_address_space) - 1;
+        (start_index as u16, end_index as u16)
+    }
+
+    pub fn current_page_read(&self) -> ModbusReadCommand {
+        let amount = self.page_size();
+        let start = self.table_address as usize / amount * amount;
+        (
+            self.table_type,
+            start as u16,
+            usize::min(amount, self.total_address_space - start) as u16,
+        )
+    }
+
+    pub fn page_count(&self) -> usize {
+        self.total_address_space.div_ceil(self.page_size())
+    }
+
     pub async fn page_up(&mut self) {
         let (last_page_offset, _last_row, _last_col) = self.last_cell();
         if self.page_offset > 0 {
             self.page_offset -= 1;
+        } else if !self.wrap_around {
+            return;
         } else {
             self.page_offset = last_page_offset;
             if last_page_offset == 0 {
@@ -164,6 +280,8 @@ impl AppTable {
         let (selected_row, _) = self.table_state.selected_cell().unwrap_or((0, 0));
         if selected_row > 0 {
             self.table_state.select_previous();
+        } else if self.page_offset == 0 && !self.wrap_around {
+            return;
         } else {
             self.page_up().await;
             self.table_state.select(Some(self.table_rows - 1));
@@ -176,8 +294,10 @@ impl AppTable {
         if (self.page_offset + 1) * self.page_size() < self.total_address_space {
             self.page_offset += 1;
             self.go_to_existing_cell();
-        } else {
+        } else if self.wrap_around {
             self.page_offset = 0;
+        } else {
+            return;
         }
         self.set_memory_address(self.current_cell_index() as u16);
 
@@ -187,6 +307,9 @@ impl AppTable {
     pub async fn move_down(&mut self) {
         let (selected_row, selected_col) = self.table_state.selected_cell().unwrap_or((0, 0));
         let (last_page_offset, last_row, _last_col) = self.last_cell();
+        if !self.wrap_around && selected_row == last_row && self.page_offset == last_page_offset {
+            return;
+        }
         if selected_row < (self.table_rows - 1) {
             if selected_row != last_row || self.page_offset != last_page_offset {
                 self.table_state.select_next();
@@ -211,7 +334,7 @@ impl AppTable {
         let selected_column = self.table_state.selected_column().unwrap_or(0);
         if selected_column > 0 {
             self.table_state.select_previous_column();
-        } else {
+        } else if self.wrap_around {
             self.table_state.select_column(Some(self.table_cols - 1));
             self.go_to_existing_cell();
         }
@@ -224,19 +347,114 @@ impl AppTable {
             && self.cell_exists(self.page_offset, selected_row, selected_col + 1)
         {
             self.table_state.select_next_column();
-        } else {
+        } else if self.wrap_around {
             self.table_state.select_column(Some(0));
         }
         self.set_memory_address(self.current_cell_index() as u16); // Needs to be calculated
     }
 
+    // Keeps the selected address, moving it to wherever it lands with the new row width
+    pub fn set_columns(&mut self, table_cols: usize) {
+        match self.list_view {
+            // The list follows the grid's page, so it grows or shrinks with it
+            Some((table_rows, _)) => {
+                self.list_view = Some((table_rows, table_cols));
+                self.set_page_size(table_rows * table_cols, 1);
+            }
+            None => self.set_page_size(self.table_rows, table_cols),
+        }
+    }
+
+    // One address per row over the same page, so paging and page reads don't change
+    pub fn toggle_list_view(&mut self) {
+        match self.list_view.take() {
+            Some((table_rows, table_cols)) => self.set_page_size(table_rows, table_cols),
+            None => {
+                self.list_view = Some((self.table_rows, self.table_cols));
+                self.set_page_size(self.page_size(), 1);
+            }
+        }
+    }
+
+    pub fn set_page_size(&mut self, table_rows: usize, table_cols: usize) {
+        self.table_rows = table_rows;
+        self.table_cols = table_cols;
+        self.go_to_cell(self.table_address);
+    }
+
     pub fn go_to_cell(&mut self, cell_address: u16) {
+        let cell_address = u16::min(cell_address, (self.total_address_space - 1) as u16);
         let (page_offset, row, col) = self.index_to_cell(cell_address as usize);
         self.page_offset = page_offset;
         self.table_state.select_cell(Some((row, col)));
         self.set_memory_address(cell_address);
     }
 
+    pub async fn go_to_page(&mut self, page_offset: usize) {
+        let (last_page_offset, _last_row, _last_col) = self.last_cell();
+        self.page_offset = usize::min(page_offset, last_page_offset);
+        self.go_to_existing_cell();
+        self.set_memory_address(self.current_cell_index() as u16);
+
+        let _ = self.sender.send(Action::PageRefresh).await;
+    }
+
+    pub async fn go_to_first(&mut self) {
+        self.go_to_cell(0);
+
+        let _ = self.sender.send(Action::PageRefresh).await;
+    }
+
+    pub async fn go_to_last(&mut self) {
+        let (last_page_offset, last_row, last_col) = self.last_cell();
+        self.page_offset = last_page_offset;
+        self.table_state.select_cell(Some((last_row, last_col)));
+        self.set_memory_address(self.current_cell_index() as u16);
+
+        let _ = self.sender.send(Action::PageRefresh).await;
+    }
+
+    // Only cells that have been read or queued are searched, unread addresses default to 0
+    pub fn find_next(&self, value: u16) -> Option<u16> {
+        let mut matches: Vec<u16> = self
+            .data
+            .iter()
+            .filter(|(_, cell)| cell.display_content().to_u16() == value)
+            .map(|(address, _)| *address)
+            .collect();
+        matches.sort_unstable();
+
+        matches
+            .iter()
+            .find(|address| **address > self.table_address)
+            .or(matches.first())
+            .copied()
+    }
+
+    // The nearest queued address after (or before) the selected one, wrapping around the table
+    pub fn find_queued(&self, forward: bool) -> Option<u16> {
+        let mut queued: Vec<u16> = self
+            .data
+            .iter()
+            .filter(|(_, cell)| matches!(cell.state, CellState::Queued))
+            .map(|(address, _)| *address)
+            .collect();
+        queued.sort_unstable();
+
+        match forward {
+            true => queued
+                .iter()
+                .find(|address| **address > self.table_address)
+                .or(queued.first()),
+            false => queued
+                .iter()
+                .rev()
+                .find(|address| **address < self.table_address)
+                .or(queued.last()),
+        }
+        .copied()
+    }
+
     pub fn queue_current_cell(&mut self, new_value: CellType) {
         let current_index = self.current_cell_index();
         let cell = self
@@ -246,11 +464,46 @@ impl AppTable {
         cell.queue(new_value);
     }
 
+    pub fn queue_current_page(&mut self, new_value: CellType) {
+        let (start_index, end_index) = self.page_range();
+        for address in start_index..=end_index {
+            let cell = self
+                .data
+                .entry(address)
+                .or_insert(TableCell::new(self.table_type));
+            cell.queue(new_value);
+        }
+    }
+
+    // Consecutive registers starting at the current one, for values that span several registers
+    pub fn queue_current_words(&mut self, words: &[u16]) {
+        let current_index = self.current_cell_index();
+        for (address, word) in (current_index..self.total_address_space).zip(words) {
+            let cell = self
+                .data
+                .entry(address as u16)
+                .or_insert(TableCell::new(self.table_type));
+            cell.queue(CellType::Word(*word));
+        }
+    }
+
+    pub fn queue_current_bits(&mut self, new_value: u16, masked: bool) {
+        let current_index = self.current_cell_index();
+        let cell = self
+            .data
+            .entry(current_index as u16)
+            .or_insert(TableCell::new(self.table_type));
+        cell.queue(CellType::Word(new_value));
+        if masked && matches!(cell.state, CellState::Queued) {
+            cell.write_mode = WriteMode::Masked;
+        }
+    }
+
     pub fn get_queue_items(&self) -> Vec<QueueItem> {
         self.data
             .iter()
             .filter_map(|(key, value)| {
-                if let CellState::Queued = value.state {
+                if let CellState::Queued | CellState::Asserted = value.state {
                     Some(QueueItem {
                         address: *key,
                         cell: value.clone(),
@@ -264,6 +517,7 @@ impl AppTable {
     }
 
     pub fn set_cell(&mut self, cell_index: u16, new_value: CellType) {
+        self.illegal_addresses.remove(&cell_index);
         let cell = self
             .data
             .entry(cell_index)
@@ -271,6 +525,11 @@ impl AppTable {
         cell.set(new_value);
     }
 
+    pub fn set_illegal(&mut self, start: u16, count: u16) {
+        self.illegal_addresses
+            .extend((start as u32..start as u32 + count as u32).map(|address| address as u16));
+    }
+
     pub fn revert_current_cell(&mut self) {
         let current_index = self.current_cell_index();
         if let Some(cell) = self.data.get_mut(&(current_index as u16)) {
@@ -278,6 +537,23 @@ impl AppTable {
         }
     }
 
+    // Reverts the queued cells on the current page, returning how many there were
+    pub fn revert_current_page(&mut self) -> usize {
+        let (start_index, end_index) = self.page_range();
+        let mut reverted = 0;
+        for (_, cell) in self
+            .data
+            .iter_mut()
+            .filter(|(address, _)| (start_index..=end_index).contains(address))
+        {
+            if let CellState::Queued = cell.state {
+                cell.revert();
+                reverted += 1;
+            }
+        }
+        reverted
+    }
+
     pub fn toggle_current_coil(&mut self) {
         let current_index = self.current_cell_index();
         let cell = self
@@ -287,8 +563,40 @@ impl AppTable {
         cell.toggle();
     }
 
+    // First and last address of the selected range, inclusive
+    pub fn selection(&self) -> Option<(u16, u16)> {
+        self.selection_anchor.map(|anchor| {
+            (
+                u16::min(anchor, self.table_address),
+                u16::max(anchor, self.table_address),
+            )
+        })
+    }
+
+    pub fn toggle_selection(&mut self) {
+        if let Some((start, end)) = self.selection() {
+            for address in start..=end {
+                let cell = self
+                    .data
+                    .entry(address)
+                    .or_insert(TableCell::new(self.table_type));
+                cell.toggle();
+            }
+        }
+        self.selection_anchor = None;
+    }
+
+    pub fn toggle_current_assertion(&mut self) {
+        let current_index = self.current_cell_index();
+        let cell = self
+            .data
+            .entry(current_index as u16)
+            .or_insert(TableCell::new(self.table_type));
+        cell.toggle_assertion();
+    }
+
     fn cell_exists(&self, page_offset: usize, row: usize, col: usize) -> bool {
-        self.cell_to_index(page_offset, row, col) < self.total_address_space
+        self.index_exists(self.cell_to_index(page_offset, row, col))
     }
 
     fn cell_to_index(&self, page_offset: usize, row: usize, col: usize) -> usize {
@@ -296,7 +604,7 @@ impl AppTable {
     }
 
     fn current_cell_exists(&self) -> bool {
-        self.current_cell_index() < self.total_address_space
+        self.index_exists(self.current_cell_index())
     }
 
     fn current_cell_index(&self) -> usize {
@@ -334,3 +642,179 @@ impl AppTable {
         self.table_address = value;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::sync::mpsc;
+
+    fn last_cell_table(wrap_around: bool) -> AppTable {
+        let (sender, _receiver) = mpsc::channel(100);
+        let mut table = AppTable::new(sender, SelectedTopTab::HoldingRegisters);
+        table.wrap_around = wrap_around;
+        table.go_to_cell(u16::MAX);
+        table
+    }
+
+    #[test]
+    fn fill_queues_the_whole_page() {
+        let (sender, _receiver) = mpsc::channel(100);
+        let mut table = AppTable::new(sender, SelectedTopTab::HoldingRegisters);
+        table.set_cell(3, CellType::Word(7));
+        table.go_to_cell(10);
+
+        table.queue_current_page(CellType::Word(7));
+
+        // The register already holding 7 has nothing to write
+        assert_eq!(table.get_queue_items().len(), table.page_size() - 1);
+        assert!(!table.data.contains_key(&(table.page_size() as u16)));
+    }
+
+    #[test]
+    fn finds_queued_cells_in_both_directions() {
+        let (sender, _receiver) = mpsc::channel(100);
+        let mut table = AppTable::new(sender, SelectedTopTab::HoldingRegisters);
+        assert_eq!(table.find_queued(true), None);
+        for address in [5, 40, 300] {
+            table.go_to_cell(address);
+            table.queue_current_cell(CellType::Word(1));
+        }
+        table.set_cell(20, CellType::Word(9));
+
+        table.go_to_cell(40);
+        assert_eq!(table.find_queued(true), Some(300));
+        assert_eq!(table.find_queued(false), Some(5));
+
+        table.go_to_cell(300);
+        assert_eq!(table.find_queued(true), Some(5));
+        table.go_to_cell(5);
+        assert_eq!(table.find_queued(false), Some(300));
+    }
+
+    #[test]
+    fn page_size_keeps_the_selected_address() {
+        let (sender, _receiver) = mpsc::channel(100);
+        let mut table = AppTable::new(sender, SelectedTopTab::HoldingRegisters);
+        table.go_to_cell(100);
+
+        table.set_page_size(4, 10);
+
+        assert_eq!(table.table_address, 100);
+        assert_eq!(table.page_offset, 2);
+        assert_eq!(table.page_range(), (80, 119));
+        assert_eq!(table.table_state.selected_cell(), Some((2, 0)));
+    }
+
+    #[test]
+    fn selection_toggles_every_coil_in_range() {
+        let (sender, _receiver) = mpsc::channel(100);
+        let mut table = AppTable::new(sender, SelectedTopTab::Coils);
+        table.set_cell(4, CellType::Coil(true));
+        table.go_to_cell(5);
+        table.selection_anchor = Some(table.table_address);
+        table.move_left();
+        table.move_left();
+        assert_eq!(table.selection(), Some((3, 5)));
+
+        table.toggle_selection();
+
+        assert_eq!(table.selection(), None);
+        let mut queued: Vec<(u16, CellType)> = table
+            .get_queue_items()
+            .iter()
+            .map(|item| (item.address, item.cell.queued_content))
+            .collect();
+        queued.sort_by_key(|(address, _)| *address);
+        assert_eq!(
+            queued,
+            vec![
+                (3, CellType::Coil(true)),
+                (4, CellType::Coil(false)),
+                (5, CellType::Coil(true)),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn pages_stop_at_a_small_address_space() {
+        let (sender, _receiver) = mpsc::channel(100);
+        let mut table = AppTable::new(sender, SelectedTopTab::HoldingRegisters);
+        table.set_page_size(8, 8);
+        table.set_address_space(100);
+
+        assert_eq!(table.page_count(), 2);
+        table.page_down().await;
+        assert_eq!(table.page_range(), (64, 99));
+        assert_eq!(table.current_page_read(), (table.table_type, 64, 36));
+
+        table.go_to_last().await;
+        assert_eq!(table.table_address, 99);
+        table.move_right();
+        assert_eq!(table.table_address, 96);
+        table.page_down().await;
+        assert_eq!(table.page_offset, 0);
+
+        table.go_to_cell(500);
+        assert_eq!(table.table_address, 99);
+        assert!(table.contains(99));
+        assert!(!table.contains(100));
+
+        table.wrap_around = false;
+        table.move_down().await;
+        table.page_down().await;
+        assert_eq!(table.table_address, 99);
+    }
+
+    #[tokio::test]
+    async fn list_view_keeps_the_page() {
+        let (sender, _receiver) = mpsc::channel(100);
+        let mut table = AppTable::new(sender, SelectedTopTab::HoldingRegisters);
+        table.go_to_cell(70);
+        let page = table.page_range();
+
+        table.toggle_list_view();
+        assert_eq!(table.page_range(), page);
+        assert_eq!(table.table_address, 70);
+        table.move_down().await;
+        assert_eq!(table.table_address, 71);
+
+        // A display mode change while in the list still reaches the grid
+        table.set_columns(4);
+        assert_eq!((table.table_rows, table.table_cols), (32, 1));
+        table.toggle_list_view();
+        assert_eq!((table.table_rows, table.table_cols), (8, 4));
+        assert_eq!(table.table_address, 71);
+    }
+
+    #[tokio::test]
+    async fn wraps_past_the_last_cell() {
+        let mut table = last_cell_table(true);
+        table.move_right();
+        assert_eq!(table.table_address, u16::MAX - 7);
+
+        let mut table = last_cell_table(true);
+        table.move_down().await;
+        assert_eq!(table.table_address, 7);
+
+        let mut table = last_cell_table(true);
+        table.page_down().await;
+        assert_eq!(table.page_offset, 0);
+    }
+
+    #[tokio::test]
+    async fn stops_at_the_last_cell() {
+        let mut table = last_cell_table(false);
+        table.move_right();
+        table.move_down().await;
+        table.page_down().await;
+        assert_eq!(table.table_address, u16::MAX);
+        assert_eq!(table.page_offset, table.page_count() - 1);
+
+        table.go_to_cell(0);
+        table.move_left();
+        table.move_up().await;
+        table.page_up().await;
+        assert_eq!(table.table_address, 0);
+        assert_eq!(table.page_offset, 0);
+    }
+}
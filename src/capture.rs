@@ -0,0 +1,251 @@
+//!   Copyright 2025 Isaac Schlaegel
+//!
+//!    Licensed under the Apache License, Version 2.0 (the "License");
+//!    you may not use this file except in compliance with the License.
+//!    You may obtain a copy of the License at
+//!
+//!        http://www.apache.org/licenses/LICENSE-2.0
+//!
+//!    Unless required by applicable law or agreed to in writing, software
+//!    distributed under the License is distributed on an "AS IS" BASIS,
+//!    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//!    See the License for the specific language governing permissions and
+//!    limitations under the License.
+
+use crate::{
+    enums::{CellType, SelectedTopTab},
+    utils::{invalid_line, parse_u16_value},
+};
+use std::{collections::BTreeMap, net::SocketAddr, path::Path};
+use tokio::{
+    fs::{self, File},
+    io::AsyncWriteExt,
+};
+
+// Values read from the device while recording, kept apart from the write queue
+#[derive(Default)]
+pub struct Capture {
+    values: BTreeMap<(SelectedTopTab, u16), CellType>, // Table, Address -> Last read value
+}
+
+impl Capture {
+    pub fn record(&mut self, table: SelectedTopTab, address: u16, content: CellType) {
+        self.values.insert((table, address), content);
+    }
+
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.values.clear();
+    }
+
+    // Line format: table,address,value - the same table names and 1-based
+    // addresses as --labels, so snapshots can be diffed with any text tool
+    pub async fn to_file(
+        &self,
+        mut filename: String,
+        source: Option<SocketAddr>,
+    ) -> std::io::Result<()> {
+        let mut path_buf = std::env::current_dir()?;
+        filename = filename.trim().to_string();
+        filename.push_str(".csv");
+        path_buf.push(filename);
+
+        let mut contents =
+            String::from("# Magic Modbus capture - values read from the device, not writes\n");
+        if let Some(source) = source {
+            contents.push_str(&format!("# Source: {source}\n"));
+        }
+        contents.push_str("# table,address,value\n");
+        for ((table, address), content) in &self.values {
+            contents.push_str(&format!(
+                "{},{},{}\n",
+                table.to_string().to_lowercase().replace(' ', "_"),
+                *address as u32 + 1,
+                content.to_u16()
+            ));
+        }
+
+        let mut file = File::create_new(&path_buf).await?;
+        file.write_all(contents.as_bytes()).await
+    }
+}
+
+// One table,address,value line to stage in the write queue
+pub type ImportRow = (SelectedTopTab, u16, u16); // Table, Address, Value
+
+pub async fn import_file<P: AsRef<Path>>(
+    filename: P,
+) -> std::io::Result<(Vec<ImportRow>, Vec<usize>)> {
+    Ok(parse_import(&fs::read_to_string(filename).await?))
+}
+
+// Reads the capture format back: table,address,value with 1-based addresses and
+// decimal or 0x hex values. Only coils (0 or 1) and holding registers can be
+// written, anything else is skipped and its 1-based line number returned
+pub fn parse_import(text: &str) -> (Vec<ImportRow>, Vec<usize>) {
+    let mut rows = vec![];
+    let mut bad_lines = vec![];
+
+    for (index, line) in text.lines().enumerate() {
+        let line = line.trim();
+        // Spreadsheets export their own header row without a #
+        if line.is_empty() || line.starts_with('#') || line.to_lowercase().starts_with("table,") {
+            continue;
+        }
+        match parse_import_row(line) {
+            Some(row) => rows.push(row),
+            None => bad_lines.push(index + 1),
+        }
+    }
+    (rows, bad_lines)
+}
+
+fn parse_import_row(line: &str) -> Option<ImportRow> {
+    let fields = line.split(',').map(|field| field.trim().trim_matches('"'));
+    let [table, address, value] = fields.collect::<Vec<&str>>()[..] else {
+        return None;
+    };
+    let table = SelectedTopTab::from_name(table)?;
+    let address = u16::try_from(address.parse::<u32>().ok()?.checked_sub(1)?).ok()?;
+    let value = parse_u16_value(value)?;
+    match table {
+        SelectedTopTab::Coils if value <= 1 => Some((table, address, value)),
+        SelectedTopTab::HoldingRegisters => Some((table, address, value)),
+        _ => None,
+    }
+}
+
+// A saved capture loaded back, to check the live tables against a known-good baseline
+pub struct Snapshot {
+    pub name: String,
+    values: BTreeMap<(SelectedTopTab, u16), u16>, // Table, Address -> Saved value
+}
+
+impl Snapshot {
+    pub async fn from_file(filename: &str) -> std::io::Result<Self> {
+        Self::parse(filename, &fs::read_to_string(filename).await?)
+    }
+
+    pub fn parse(name: &str, text: &str) -> std::io::Result<Self> {
+        let mut values = BTreeMap::new();
+        for (index, line) in text.lines().enumerate() {
+            let line = line.trim();
+            // Spreadsheets export their own header row without a #
+            if line.is_empty() || line.starts_with('#') || line.to_lowercase().starts_with("table,")
+            {
+                continue;
+            }
+            let mut fields = line.split(',').map(str::trim);
+            let (Some(table), Some(address), Some(value), None) =
+                (fields.next(), fields.next(), fields.next(), fields.next())
+            else {
+                return Err(invalid_line(index, "Expected table,address,value"));
+            };
+            let table = SelectedTopTab::from_name(table)
+                .ok_or_else(|| invalid_line(index, "Unknown table"))?;
+            let address = address
+                .parse::<u32>()
+                .ok()
+                .and_then(|address| u16::try_from(address.checked_sub(1)?).ok())
+                .ok_or_else(|| invalid_line(index, "Invalid address"))?;
+            let value =
+                parse_u16_value(value).ok_or_else(|| invalid_line(index, "Invalid value"))?;
+            values.insert((table, address), value);
+        }
+        Ok(Self {
+            name: name.to_string(),
+            values,
+        })
+    }
+
+    pub fn get(&self, table: SelectedTopTab, address: u16) -> Option<u16> {
+        self.values.get(&(table, address)).copied()
+    }
+
+    // Table by table, in address order
+    pub fn values(&self) -> impl Iterator<Item = (SelectedTopTab, u16, u16)> + '_ {
+        self.values
+            .iter()
+            .map(|((table, address), value)| (*table, *address, *value))
+    }
+
+    // Only saved addresses are compared, anything read since then has no baseline.
+    // Returns the addresses that differ and the ones that haven't been read live
+    pub fn compare(
+        &self,
+        table: SelectedTopTab,
+        live: impl Fn(u16) -> Option<u16>,
+    ) -> (usize, usize) {
+        self.values.range((table, 0)..=(table, u16::MAX)).fold(
+            (0, 0),
+            |(differ, unread), ((_, address), saved)| match live(*address) {
+                Some(value) if value != *saved => (differ + 1, unread),
+                Some(_) => (differ, unread),
+                None => (differ, unread + 1),
+            },
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn import_skips_bad_lines() {
+        let text = "\
+# table,address,value
+table,address,value
+coils,1,1
+holding_registers,40,0x00FF
+4x,65536,65535
+discrete_inputs,1,1
+coils,2,2
+holding_registers,0,1
+holding_registers,1
+pumps,1,1
+";
+        let (rows, bad_lines) = parse_import(text);
+        assert_eq!(
+            rows,
+            vec![
+                (SelectedTopTab::Coils, 0, 1),
+                (SelectedTopTab::HoldingRegisters, 39, 255),
+                (SelectedTopTab::HoldingRegisters, 65535, 65535),
+            ]
+        );
+        assert_eq!(bad_lines, vec![6, 7, 8, 9, 10]);
+    }
+
+    #[test]
+    fn snapshot_counts_differences_per_table() {
+        let snapshot = Snapshot::parse(
+            "baseline.csv",
+            "# Magic Modbus capture\ncoils,1,1\nholding_registers,1,10\nholding_registers,2,20\nholding_registers,3,30\n",
+        )
+        .unwrap();
+        let live = |address| match address {
+            0 => Some(10),
+            1 => Some(21),
+            _ => None,
+        };
+        assert_eq!(
+            snapshot.compare(SelectedTopTab::HoldingRegisters, live),
+            (1, 1)
+        );
+        assert_eq!(
+            snapshot.compare(SelectedTopTab::InputRegisters, live),
+            (0, 0)
+        );
+        assert_eq!(snapshot.get(SelectedTopTab::Coils, 0), Some(1));
+        assert!(Snapshot::parse("bad.csv", "coils,0,1").is_err());
+        assert!(Snapshot::parse("header.csv", "Table,Address,Value\ncoils,1,1").is_ok());
+    }
+}
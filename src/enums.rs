@@ -12,29 +12,52 @@
 //!    See the License for the specific language governing permissions and
 //!    limitations under the License.
 
-use crate::utils::{ModbusReadCommand, ModbusWriteCommand};
+use crate::{
+    app_colors::PALETTES,
+    utils::{
+        ModbusMaskWriteCommand, ModbusReadCommand, ModbusReadWriteCommand, ModbusWriteCommand,
+    },
+};
+use clap::ValueEnum;
 use crossterm::event::Event;
-use ratatui::{style::Style, text::Line};
-use std::net::SocketAddr;
-use strum::{Display, EnumIter, FromRepr};
+use ratatui::{
+    style::{Style, palette::tailwind},
+    text::Line,
+};
+use std::{net::SocketAddr, time::Duration};
+use strum::{Display, EnumIter, FromRepr, IntoEnumIterator};
 
 pub enum Action {
     CEvent(Event),
     Tick,
+    Poll, // Reads whatever the poll schedule says is due
     Render,
     ToModbus(ModbusCommandQueue),   // From App to Modbus
     FromModbus(ModbusCommandQueue), // From Modbus to App
-    SuccessfulWrite,
+    SuccessfulWrite(Vec<ModbusWriteCommand>),
     Connect(SocketAddr),
+    Connected(SocketAddr), // The modbus task reached the device
     ConnectionError(String),
     Disconnect,
+    IdleDisconnect, // The modbus task closed a connection nothing had used for a while
     Error(String),
+    Log(String), // Background failures that shouldn't interrupt with a popup
     PageRefresh,
+    RawResponse(String),       // Reply to a raw PDU, formatted for the popup
+    Latency(Option<Duration>), // None when a request is sent, its round-trip once answered
+    IllegalAddresses(Vec<ModbusReadCommand>), // Rejected by the device, shown as unknown
+    DeviceIdentification(Vec<String>), // One line per object, shown in a popup
 }
 
 pub enum ModbusCommandQueue {
     Read(Vec<ModbusReadCommand>),
+    Prefetch(Vec<ModbusReadCommand>), // Read, put off while anything else is waiting
     Write(Vec<ModbusWriteCommand>),
+    VerifiedWrite(Vec<ModbusWriteCommand>), // Write, then read back and compare
+    MaskWrite(Vec<ModbusMaskWriteCommand>), // Holding registers only
+    ReadWrite(Vec<ModbusReadWriteCommand>), // Holding registers only
+    Raw(u8, Vec<u8>),                       // Function code and data, sent as is
+    DeviceIdentification,                   // Vendor, product and revision (0x2B/0x0E)
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
@@ -61,8 +84,14 @@ impl CellType {
 #[derive(Default, Display)]
 pub enum ConnectionStatus {
     Connected,
+    #[strum(to_string = "Connecting...")]
+    Connecting, // Waiting on the handshake, commands wait in the task's channel until it's done
     #[default]
     NotConnected,
+    #[strum(to_string = "Disconnected Due To Inactivity")]
+    IdleDisconnected, // Reconnect to pick up where it left off
+    #[strum(to_string = "Offline (Macro Authoring)")]
+    Offline, // Changes are queued against a typed target and can only be saved as a macro
 }
 
 #[derive(Clone)]
@@ -76,16 +105,29 @@ pub enum AppMode {
 pub enum PopupType {
     Connection,
     Edit,
+    BitEdit,
+    Ascii,
     Error(String),
+    ConfirmQuit, // Quitting with a non-empty queue
     Goto,
+    GotoPage,
+    ReadRange,
+    Search,
     SaveMacro(SaveMacroMode),
+    SaveCapture,
+    RawPdu, // Expert only, sends a function code the app doesn't otherwise support
+    Fill,
+    ImportCsv,
+    LoadSnapshot,
+    DeviceIdentification(Vec<String>), // One line per object, or why there are none
+    Settings,
 }
 
 #[derive(Clone)]
 pub enum SaveMacroMode {
     Main,
     OverwriteWarning,
-    FileSaved,
+    FileSaved(usize), // Number of unsupported commands left out
 }
 
 #[derive(Default)]
@@ -123,8 +165,38 @@ impl SelectedTopTab {
         Self::from_repr(previous_index).unwrap_or(self)
     }
 
-    pub fn title(self) -> Line<'static> {
-        Line::styled(format!("  {self}  "), Style::default())
+    pub fn title(self, marker: &str) -> Line<'static> {
+        Line::styled(format!("  {self}{marker}  "), Style::default())
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.trim().to_lowercase().replace([' ', '-'], "_").as_str() {
+            "coils" | "coil" | "0x" => Some(SelectedTopTab::Coils),
+            "discrete_inputs" | "discrete_input" | "1x" => Some(SelectedTopTab::DiscreteInputs),
+            "input_registers" | "input_register" | "3x" => Some(SelectedTopTab::InputRegisters),
+            "holding_registers" | "holding_register" | "4x" => {
+                Some(SelectedTopTab::HoldingRegisters)
+            }
+            _ => None,
+        }
+    }
+
+    // Leading digit of the table's Modbus reference numbers (0xxxxx, 1xxxxx, 3xxxxx, 4xxxxx)
+    pub fn reference_digit(self) -> u32 {
+        match self {
+            SelectedTopTab::Coils => 0,
+            SelectedTopTab::DiscreteInputs => 1,
+            SelectedTopTab::InputRegisters => 3,
+            SelectedTopTab::HoldingRegisters => 4,
+        }
+    }
+
+    pub fn from_reference_digit(digit: u32) -> Option<Self> {
+        Self::iter().find(|table| table.reference_digit() == digit)
+    }
+
+    pub fn memory_address(self, address: u16) -> String {
+        format!("0x{}{:04X}", self.reference_digit(), address as u32 + 1)
     }
 }
 
@@ -135,6 +207,12 @@ pub enum SelectedBottomTab {
     Connection,
     #[strum(to_string = "Queue")]
     Queue,
+    #[strum(to_string = "Watch")]
+    Watch,
+    #[strum(to_string = "History")]
+    History,
+    #[strum(to_string = "Alarms")]
+    Alarms,
 }
 
 impl SelectedBottomTab {
@@ -150,9 +228,225 @@ impl SelectedBottomTab {
         Self::from_repr(previous_index).unwrap_or(self)
     }
 
-    pub fn title(self) -> Line<'static> {
-        Line::styled(format!("  {self}  "), Style::default())
+    pub fn title(self, marker: &str) -> Line<'static> {
+        Line::styled(format!("  {self}{marker}  "), Style::default())
+    }
+}
+
+#[derive(Default, Clone, Copy, Display, FromRepr, ValueEnum)]
+pub enum Theme {
+    #[default]
+    #[strum(to_string = "Per Tab")]
+    PerTab, // Follows the selected top tab
+    Rose,
+    Amber,
+    Emerald,
+    Indigo,
+    Sky,
+    Slate,
+}
+
+impl Theme {
+    pub fn next(self) -> Self {
+        Self::from_repr(self as usize + 1).unwrap_or_default()
+    }
+
+    pub fn palette(self, tab: SelectedTopTab) -> &'static tailwind::Palette {
+        match self {
+            Theme::PerTab => &PALETTES[tab as usize],
+            Theme::Rose => &tailwind::ROSE,
+            Theme::Amber => &tailwind::AMBER,
+            Theme::Emerald => &tailwind::EMERALD,
+            Theme::Indigo => &tailwind::INDIGO,
+            Theme::Sky => &tailwind::SKY,
+            Theme::Slate => &tailwind::SLATE,
+        }
+    }
+}
+
+// Order of the four bytes of a 32-bit value (A is the most significant) across two registers
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq, Display, FromRepr, ValueEnum)]
+pub enum WordOrder {
+    #[default]
+    #[strum(to_string = "ABCD")]
+    Abcd, // Big endian
+    #[strum(to_string = "BADC")]
+    Badc, // Big endian, bytes swapped
+    #[strum(to_string = "CDAB")]
+    Cdab, // Little endian, bytes swapped
+    #[strum(to_string = "DCBA")]
+    Dcba, // Little endian
+}
+
+impl WordOrder {
+    pub fn next(self) -> Self {
+        Self::from_repr(self as usize + 1).unwrap_or_default()
+    }
+
+    // Registers in address order
+    pub fn split(self, value: u32) -> [u16; 2] {
+        let (high, low) = ((value >> 16) as u16, value as u16);
+        match self {
+            WordOrder::Abcd => [high, low],
+            WordOrder::Badc => [high.swap_bytes(), low.swap_bytes()],
+            WordOrder::Cdab => [low, high],
+            WordOrder::Dcba => [low.swap_bytes(), high.swap_bytes()],
+        }
+    }
+
+    pub fn join(self, registers: [u16; 2]) -> u32 {
+        let [first, second] = registers;
+        let (high, low) = match self {
+            WordOrder::Abcd => (first, second),
+            WordOrder::Badc => (first.swap_bytes(), second.swap_bytes()),
+            WordOrder::Cdab => (second, first),
+            WordOrder::Dcba => (second.swap_bytes(), first.swap_bytes()),
+        };
+        ((high as u32) << 16) | low as u32
+    }
+}
+
+// How register values are shown in the tables and typed into the Edit popup
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq, Display, FromRepr, ValueEnum)]
+pub enum DisplayMode {
+    #[default]
+    Unsigned,
+    Signed, // Two's complement, -32768 to 32767
+    Binary, // Grouped by nibble, most significant bit first
+}
+
+impl DisplayMode {
+    pub fn next(self) -> Self {
+        Self::from_repr(self as usize + 1).unwrap_or_default()
+    }
+
+    // Fixed width, so a column doesn't shift as values change
+    pub fn format(self, value: u16) -> String {
+        match self {
+            DisplayMode::Unsigned => format!("{:05}", value),
+            DisplayMode::Signed => format!("{:+06}", value as i16),
+            DisplayMode::Binary => nibbles(value, " "),
+        }
+    }
+
+    // Width of a table column holding a formatted register
+    pub fn column_length(self) -> usize {
+        match self {
+            DisplayMode::Unsigned | DisplayMode::Signed => 7,
+            DisplayMode::Binary => 19,
+        }
+    }
+
+    // Binary values are wide, so fewer fit on a row
+    pub fn register_columns(self) -> usize {
+        match self {
+            DisplayMode::Unsigned | DisplayMode::Signed => 8,
+            DisplayMode::Binary => 4,
+        }
+    }
+
+    pub fn text(self, value: u16) -> String {
+        match self {
+            DisplayMode::Unsigned => value.to_string(),
+            DisplayMode::Signed => (value as i16).to_string(),
+            DisplayMode::Binary => format!("0b{}", nibbles(value, "_")),
+        }
+    }
+
+    // The register's bit pattern, so "-40" is 65496 in signed mode.
+    // A 0b prefix is binary in every mode, with '_' allowed between digits.
+    pub fn parse(self, text: &str) -> Option<u16> {
+        if let Some(bits) = text.strip_prefix("0b") {
+            return u16::from_str_radix(&bits.replace('_', ""), 2).ok();
+        }
+        match self {
+            DisplayMode::Unsigned | DisplayMode::Binary => text.parse::<u16>().ok(),
+            DisplayMode::Signed => text.parse::<i16>().ok().map(|value| value as u16),
+        }
+    }
+}
+
+// Base of the digits typed into the Goto and Edit popups, each popup keeps its own
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq, Display, FromRepr)]
+pub enum InputBase {
+    #[default]
+    #[strum(to_string = "DEC")]
+    Decimal,
+    #[strum(to_string = "HEX")]
+    Hexadecimal,
+    #[strum(to_string = "BIN")]
+    Binary,
+}
+
+impl InputBase {
+    pub fn next(self) -> Self {
+        Self::from_repr(self as usize + 1).unwrap_or_default()
+    }
+
+    fn radix(self) -> u32 {
+        match self {
+            InputBase::Decimal => 10,
+            InputBase::Hexadecimal => 16,
+            InputBase::Binary => 2,
+        }
+    }
+
+    pub fn is_digit(self, c: char) -> bool {
+        c.is_digit(self.radix())
     }
+
+    pub fn parse(self, text: &str) -> Option<u32> {
+        u32::from_str_radix(text, self.radix()).ok()
+    }
+
+    pub fn format(self, value: u32) -> String {
+        match self {
+            InputBase::Decimal => value.to_string(),
+            InputBase::Hexadecimal => format!("{value:X}"),
+            InputBase::Binary => format!("{value:b}"),
+        }
+    }
+}
+
+fn nibbles(value: u16, separator: &str) -> String {
+    let bits = format!("{:016b}", value);
+    [&bits[..4], &bits[4..8], &bits[8..12], &bits[12..]].join(separator)
+}
+
+#[derive(Default, Clone, Copy, Display, FromRepr, ValueEnum)]
+pub enum Bell {
+    #[default]
+    Audible, // Terminal bell
+    Visual, // Briefly inverts the footer
+    Off,
+}
+
+impl Bell {
+    pub fn next(self) -> Self {
+        Self::from_repr(self as usize + 1).unwrap_or_default()
+    }
+}
+
+// Rows of the settings popup, in the order they are listed
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq, Display, FromRepr, EnumIter)]
+pub enum Setting {
+    #[default]
+    #[strum(to_string = "Display Mode")]
+    DisplayMode,
+    #[strum(to_string = "Word Order")]
+    WordOrder,
+    Theme,
+    Bell,
+    #[strum(to_string = "Wrap Around")]
+    WrapAround,
+    #[strum(to_string = "Verify Writes")]
+    VerifyWrites,
+    #[strum(to_string = "Confirm Quit")]
+    ConfirmQuit,
+    #[strum(to_string = "Prefetch On Connect")]
+    PrefetchOnConnect,
+    #[strum(to_string = "Read On Tab Switch")]
+    ReadOnTabSwitch,
 }
 
 pub enum SelectedConnectionButton {
@@ -160,13 +454,110 @@ pub enum SelectedConnectionButton {
     Disconnect,
 }
 
+#[derive(Default, Clone, Copy, PartialEq, Eq)]
+pub enum WriteMode {
+    #[default]
+    Single,
+    Masked,    // Mask write (0x16), only the changed bits are touched
+    ReadWrite, // Read/write multiple (0x17), grouped with neighbouring cells
+}
+
+impl WriteMode {
+    pub fn function_code(self) -> &'static str {
+        match self {
+            WriteMode::Single => "",
+            WriteMode::Masked => "0x16",
+            WriteMode::ReadWrite => "0x17",
+        }
+    }
+}
+
 #[derive(Clone)]
 pub enum CellState {
     Normal,
     Queued,
+    Asserted, // Read-only cell pinned as an expected value for macros
 }
 
 pub enum ConnectingField {
     Address,
     Port,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const VALUE: u32 = 0xAABB_CCDD;
+
+    fn assert_order(order: WordOrder, registers: [u16; 2]) {
+        assert_eq!(order.split(VALUE), registers, "{order}");
+        assert_eq!(order.join(registers), VALUE, "{order}");
+    }
+
+    #[test]
+    fn abcd_is_big_endian() {
+        assert_order(WordOrder::Abcd, [0xAABB, 0xCCDD]);
+    }
+
+    #[test]
+    fn badc_swaps_bytes() {
+        assert_order(WordOrder::Badc, [0xBBAA, 0xDDCC]);
+    }
+
+    #[test]
+    fn cdab_swaps_words() {
+        assert_order(WordOrder::Cdab, [0xCCDD, 0xAABB]);
+    }
+
+    #[test]
+    fn dcba_is_little_endian() {
+        assert_order(WordOrder::Dcba, [0xDDCC, 0xBBAA]);
+    }
+
+    #[test]
+    fn signed_mode_uses_twos_complement() {
+        let signed = DisplayMode::Signed;
+        assert_eq!(signed.parse("-40"), Some(65496));
+        assert_eq!(signed.parse("-32768"), Some(0x8000));
+        assert_eq!(signed.parse("32767"), Some(0x7FFF));
+        assert_eq!(signed.parse("-1"), Some(u16::MAX));
+        assert_eq!(signed.parse("32768"), None);
+        assert_eq!(signed.parse("-32769"), None);
+        assert_eq!(DisplayMode::Unsigned.parse("-40"), None);
+
+        assert_eq!(signed.format(0x8000), "-32768");
+        assert_eq!(signed.format(40), "+00040");
+        assert_eq!(signed.text(65496), "-40");
+    }
+
+    #[test]
+    fn binary_mode_groups_nibbles() {
+        let binary = DisplayMode::Binary;
+        assert_eq!(binary.format(0xA5F0), "1010 0101 1111 0000");
+        assert_eq!(binary.text(0x0001), "0b0000_0000_0000_0001");
+        assert_eq!(binary.parse("0b1010_0101_1111_0000"), Some(0xA5F0));
+        assert_eq!(
+            DisplayMode::Signed.parse("0b1111111111111111"),
+            Some(u16::MAX)
+        );
+        assert_eq!(binary.parse("0b1_0000_0000_0000_0000"), None);
+        assert_eq!(binary.parse("0b102"), None);
+    }
+
+    #[test]
+    fn input_base_round_trips() {
+        for base in [
+            InputBase::Decimal,
+            InputBase::Hexadecimal,
+            InputBase::Binary,
+        ] {
+            assert_eq!(base.parse(&base.format(VALUE)), Some(VALUE));
+        }
+        assert_eq!(InputBase::Hexadecimal.format(65496), "FFD8");
+        assert_eq!(InputBase::Binary.parse("101"), Some(5));
+        assert_eq!(InputBase::Binary.parse("102"), None);
+        assert_eq!(InputBase::Hexadecimal.parse(""), None);
+        assert_eq!(InputBase::Binary.next(), InputBase::Decimal);
+    }
+}
@@ -0,0 +1,370 @@
+//!   Copyright 2025 Isaac Schlaegel
+//!
+//!    Licensed under the Apache License, Version 2.0 (the "License");
+//!    you may not use this file except in compliance with the License.
+//!    You may obtain a copy of the License at
+//!
+//!        http://www.apache.org/licenses/LICENSE-2.0
+//!
+//!    Unless required by applicable law or agreed to in writing, software
+//!    distributed under the License is distributed on an "AS IS" BASIS,
+//!    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//!    See the License for the specific language governing permissions and
+//!    limitations under the License.
+
+use crate::enums::SelectedBottomTab;
+use ratatui::{
+    crossterm::event::KeyCode,
+    style::Style,
+    text::{Line, Span},
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum KeyContext {
+    General, // Checked after the focused context
+    Table,
+    Connection,
+    Queue,
+    Watch,
+    History,
+    Alarms,
+}
+
+impl KeyContext {
+    pub fn from_bottom_tab(tab: SelectedBottomTab) -> Self {
+        match tab {
+            SelectedBottomTab::Connection => KeyContext::Connection,
+            SelectedBottomTab::Queue => KeyContext::Queue,
+            SelectedBottomTab::Watch => KeyContext::Watch,
+            SelectedBottomTab::History => KeyContext::History,
+            SelectedBottomTab::Alarms => KeyContext::Alarms,
+        }
+    }
+
+    pub fn title(self) -> &'static str {
+        match self {
+            KeyContext::General => "General Controls",
+            KeyContext::Table => "Table Navigation & Data",
+            KeyContext::Connection => "Connection Controls",
+            KeyContext::Queue => "Queue Controls",
+            KeyContext::Watch => "Watch Controls",
+            KeyContext::History => "History Controls",
+            KeyContext::Alarms => "Alarms Controls",
+        }
+    }
+
+    fn intro(self) -> Option<&'static str> {
+        match self {
+            KeyContext::General => None,
+            KeyContext::Table => Some("When focused on the tables (top panel):"),
+            KeyContext::Connection => Some("When focused on Connection tab (bottom panel):"),
+            KeyContext::Queue => Some("When focused on Queue tab (bottom panel):"),
+            KeyContext::Watch => Some("When focused on Watch tab (bottom panel):"),
+            KeyContext::History => Some("When focused on History tab (bottom panel):"),
+            KeyContext::Alarms => Some("When focused on Alarms tab (bottom panel):"),
+        }
+    }
+
+    // Help text that isn't tied to a single binding
+    fn notes(self) -> &'static [&'static str] {
+        match self {
+            KeyContext::General => &["CTRL+C - Quit, even from a popup"],
+            KeyContext::Connection => &[
+                "",
+                "In Connection Popup:",
+                "• Enter IP address and port",
+                "• Use TAB to switch fields",
+                "• Use UP/DOWN to pick a recent connection",
+            ],
+            _ => &[],
+        }
+    }
+}
+
+// Sections shown on each help menu page
+pub const HELP_PAGES: [&[KeyContext]; 2] = [
+    &[KeyContext::General, KeyContext::Table],
+    &[
+        KeyContext::Connection,
+        KeyContext::Queue,
+        KeyContext::Watch,
+        KeyContext::Alarms,
+    ],
+];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Command {
+    // General
+    Quit,
+    SwitchFocus,
+    ToggleHelp,
+    PreviousTab,
+    NextTab,
+    JumpToTable,
+    OpenSettings,
+
+    // Table
+    PageUp,
+    PageDown,
+    MoveUp,
+    MoveDown,
+    MoveLeft,
+    MoveRight,
+    FirstAddress,
+    LastAddress,
+    GoToAddress,
+    GoToPage,
+    Search,
+    FindNext,
+    NextQueued,
+    PreviousQueued,
+    EditCell,
+    EditBits,
+    EditAscii,
+    ApplyQueue,
+    ApplyQueueKeep,
+    RevertCell,
+    RevertPage,
+    ReadPage,
+    ReadRange,
+    CopyValue,
+    CopyAddress,
+    PinCell,
+    ToggleListView,
+    TogglePageRefresh,
+    ToggleTickRefresh,
+    ToggleVerify,
+    ToggleRecording,
+    CompareSnapshot,
+    CycleTheme,
+    CycleWordOrder,
+    CycleDisplayMode,
+    ToggleWrapAround,
+    SendRawPdu,
+    FillPage,
+    Increment,
+    Decrement,
+    SelectRange,
+
+    // Connection
+    SelectNewConnection,
+    SelectDisconnect,
+    Reconnect,
+    WorkOffline,
+    ReadDeviceIdentification,
+    PressConnectionButton,
+
+    // Queue
+    PreviousQueueItem,
+    NextQueueItem,
+    MarkQueueItem,
+    ToggleReadWrite,
+    ApplyMarked,
+    GoToQueueItem,
+    RevertQueueItem,
+    SaveMacro,
+    ImportCsv,
+
+    // Watch
+    PreviousWatchItem,
+    NextWatchItem,
+    GoToWatchItem,
+    ReadWatchList,
+    Unpin,
+
+    // Alarms
+    ClearAlarmLog,
+}
+
+pub struct Key {
+    code: KeyCode,
+    shift: bool, // Only needed for keys that aren't an upper case character
+}
+
+const fn key(code: KeyCode) -> Key {
+    Key { code, shift: false }
+}
+
+const fn shift(code: KeyCode) -> Key {
+    Key { code, shift: true }
+}
+
+impl Key {
+    fn label(&self) -> String {
+        let label = match self.code {
+            KeyCode::Char(' ') => String::from("SPACE"),
+            KeyCode::Char(c) if c.is_ascii_uppercase() => format!("Shift+{c}"),
+            KeyCode::Char(c) => c.to_ascii_uppercase().to_string(),
+            KeyCode::Up => String::from("↑"),
+            KeyCode::Down => String::from("↓"),
+            KeyCode::Left => String::from("←"),
+            KeyCode::Right => String::from("→"),
+            KeyCode::PageUp => String::from("PGUP"),
+            KeyCode::PageDown => String::from("PGDN"),
+            KeyCode::Enter => String::from("ENTER"),
+            KeyCode::Esc => String::from("ESC"),
+            KeyCode::Tab => String::from("TAB"),
+            code => code.to_string(),
+        };
+        match self.shift {
+            true => format!("Shift+{label}"),
+            false => label,
+        }
+    }
+}
+
+pub struct KeyBinding {
+    pub context: KeyContext,
+    pub keys: &'static [Key],
+    pub command: Command,
+    pub description: &'static str,
+}
+
+const fn bind(
+    context: KeyContext,
+    keys: &'static [Key],
+    command: Command,
+    description: &'static str,
+) -> KeyBinding {
+    KeyBinding {
+        context,
+        keys,
+        command,
+        description,
+    }
+}
+
+// The key handler and the help menu both read from this table, so they can't drift apart.
+// Within a context the first match wins, so shifted bindings come before their plain keys.
+#[rustfmt::skip]
+pub const KEYMAP: &[KeyBinding] = &[
+    // General
+    bind(KeyContext::General, &[key(KeyCode::Esc)], Command::Quit, "Quit Application"),
+    bind(KeyContext::General, &[key(KeyCode::Tab)], Command::SwitchFocus, "Switch Focus (Top/Bottom panels)"),
+    bind(KeyContext::General, &[key(KeyCode::Char('?'))], Command::ToggleHelp, "Toggle Help Menu"),
+    bind(KeyContext::General, &[key(KeyCode::Char('q'))], Command::PreviousTab, "Previous Tab"),
+    bind(KeyContext::General, &[key(KeyCode::Char('e'))], Command::NextTab, "Next Tab"),
+    bind(KeyContext::General, &[key(KeyCode::Char('0')), key(KeyCode::Char('1')), key(KeyCode::Char('3')), key(KeyCode::Char('4'))], Command::JumpToTable, "Jump to Coils (0x), Discrete Inputs (1x), Input Registers (3x) or Holding Registers (4x)"),
+    bind(KeyContext::General, &[key(KeyCode::Char(','))], Command::OpenSettings, "Open the settings popup"),
+    // Table
+    bind(KeyContext::Table, &[shift(KeyCode::Char('w')), shift(KeyCode::Up), key(KeyCode::PageUp)], Command::PageUp, "Page up"),
+    bind(KeyContext::Table, &[shift(KeyCode::Char('s')), shift(KeyCode::Down), key(KeyCode::PageDown)], Command::PageDown, "Page down"),
+    bind(KeyContext::Table, &[key(KeyCode::Char('w')), key(KeyCode::Up)], Command::MoveUp, "Move cursor up"),
+    bind(KeyContext::Table, &[key(KeyCode::Char('s')), key(KeyCode::Down)], Command::MoveDown, "Move cursor down"),
+    bind(KeyContext::Table, &[key(KeyCode::Char('a')), key(KeyCode::Left)], Command::MoveLeft, "Move cursor left"),
+    bind(KeyContext::Table, &[key(KeyCode::Char('d')), key(KeyCode::Right)], Command::MoveRight, "Move cursor right"),
+    bind(KeyContext::Table, &[key(KeyCode::Home)], Command::FirstAddress, "Jump to first address"),
+    bind(KeyContext::Table, &[key(KeyCode::End)], Command::LastAddress, "Jump to last address"),
+    bind(KeyContext::Table, &[key(KeyCode::Char('g'))], Command::GoToAddress, "Go to address (1-65536, or 0/1/3/4xxxxx in another table)"),
+    bind(KeyContext::Table, &[key(KeyCode::Char('p'))], Command::GoToPage, "Jump to page"),
+    bind(KeyContext::Table, &[key(KeyCode::Char('/'))], Command::Search, "Search read values"),
+    bind(KeyContext::Table, &[key(KeyCode::Char('n'))], Command::FindNext, "Find next match"),
+    bind(KeyContext::Table, &[key(KeyCode::Char(']'))], Command::NextQueued, "Go to the next queued cell in this table"),
+    bind(KeyContext::Table, &[key(KeyCode::Char('['))], Command::PreviousQueued, "Go to the previous queued cell in this table"),
+    bind(KeyContext::Table, &[key(KeyCode::Char(' '))], Command::EditCell, "Toggle Coils / Edit Holding Registers / Assert Inputs"),
+    bind(KeyContext::Table, &[key(KeyCode::Char('v'))], Command::SelectRange, "Start/clear a Coil range selection, SPACE toggles every coil in it"),
+    bind(KeyContext::Table, &[key(KeyCode::Char('+'))], Command::Increment, "Add the step (--step, default 1) to a Holding Register"),
+    bind(KeyContext::Table, &[key(KeyCode::Char('-'))], Command::Decrement, "Subtract the step from a Holding Register"),
+    bind(KeyContext::Table, &[key(KeyCode::Char('b'))], Command::EditBits, "Edit Holding Register bits (M toggles mask write)"),
+    bind(KeyContext::Table, &[key(KeyCode::Char('A'))], Command::EditAscii, "View registers as ASCII text (typing writes Holding Registers)"),
+    bind(KeyContext::Table, &[shift(KeyCode::Enter), key(KeyCode::Char('K'))], Command::ApplyQueueKeep, "Apply all queued changes and keep them queued to apply again"),
+    bind(KeyContext::Table, &[key(KeyCode::Enter)], Command::ApplyQueue, "Apply all queued changes"),
+    bind(KeyContext::Table, &[key(KeyCode::Char('u'))], Command::RevertCell, "Revert current cell"),
+    bind(KeyContext::Table, &[key(KeyCode::Char('U'))], Command::RevertPage, "Revert every queued cell on this page"),
+    bind(KeyContext::Table, &[key(KeyCode::Char('I'))], Command::FillPage, "Queue one value on every Coil / Holding Register on this page"),
+    bind(KeyContext::Table, &[key(KeyCode::Char('r'))], Command::ReadPage, "Read current page"),
+    bind(KeyContext::Table, &[key(KeyCode::Char('B'))], Command::ReadRange, "Read any range of addresses into this table"),
+    bind(KeyContext::Table, &[key(KeyCode::Char('c'))], Command::CopyValue, "Copy selected value to clipboard"),
+    bind(KeyContext::Table, &[key(KeyCode::Char('y'))], Command::CopyAddress, "Copy selected memory address (e.g. 0x40001) to clipboard"),
+    bind(KeyContext::Table, &[key(KeyCode::Char('f'))], Command::PinCell, "Pin/unpin selected cell in the Watch tab"),
+    bind(KeyContext::Table, &[key(KeyCode::Char('l'))], Command::ToggleListView, "Toggle list view (one address per row with its label) for this table"),
+    bind(KeyContext::Table, &[key(KeyCode::Char('R'))], Command::TogglePageRefresh, "Toggle auto page refresh for this table [P]"),
+    bind(KeyContext::Table, &[key(KeyCode::Char('T'))], Command::ToggleTickRefresh, "Toggle auto tick refresh for this table [T]"),
+    bind(KeyContext::Table, &[key(KeyCode::Char('V'))], Command::ToggleVerify, "Toggle read-back verification of writes"),
+    bind(KeyContext::Table, &[key(KeyCode::Char('C'))], Command::ToggleRecording, "Start/stop recording reads into a capture file"),
+    bind(KeyContext::Table, &[key(KeyCode::Char('D'))], Command::CompareSnapshot, "Highlight values that differ from a saved capture (again to stop)"),
+    bind(KeyContext::Table, &[key(KeyCode::Char('H'))], Command::CycleTheme, "Cycle color theme"),
+    bind(KeyContext::Table, &[key(KeyCode::Char('O'))], Command::CycleWordOrder, "Cycle 32-bit word order (ABCD/BADC/CDAB/DCBA)"),
+    bind(KeyContext::Table, &[key(KeyCode::Char('x'))], Command::CycleDisplayMode, "Cycle register display mode (unsigned/signed/binary)"),
+    bind(KeyContext::Table, &[key(KeyCode::Char('L'))], Command::ToggleWrapAround, "Toggle wrapping around at the first and last address"),
+    bind(KeyContext::Table, &[key(KeyCode::Char('F'))], Command::SendRawPdu, "Send a raw function code and hex payload (expert)"),
+    // Connection
+    bind(KeyContext::Connection, &[key(KeyCode::Char('a')), key(KeyCode::Left)], Command::SelectNewConnection, "Select New Connection button"),
+    bind(KeyContext::Connection, &[key(KeyCode::Char('d')), key(KeyCode::Right)], Command::SelectDisconnect, "Select Disconnect button"),
+    bind(KeyContext::Connection, &[key(KeyCode::Enter)], Command::PressConnectionButton, "New Connection or Disconnect"),
+    bind(KeyContext::Connection, &[key(KeyCode::Char('r'))], Command::Reconnect, "Reconnect to the last address"),
+    bind(KeyContext::Connection, &[key(KeyCode::Char('o'))], Command::WorkOffline, "Work offline, queueing changes for a macro with a typed target"),
+    bind(KeyContext::Connection, &[key(KeyCode::Char('i'))], Command::ReadDeviceIdentification, "Read the device's vendor, product and revision"),
+    // Queue
+    bind(KeyContext::Queue, &[key(KeyCode::Up)], Command::PreviousQueueItem, "Previous queue item"),
+    bind(KeyContext::Queue, &[key(KeyCode::Down)], Command::NextQueueItem, "Next queue item"),
+    bind(KeyContext::Queue, &[key(KeyCode::Char(' '))], Command::MarkQueueItem, "Mark/unmark selected queue item"),
+    bind(KeyContext::Queue, &[key(KeyCode::Char('a'))], Command::ApplyMarked, "Apply only marked queue items"),
+    bind(KeyContext::Queue, &[key(KeyCode::Char('b'))], Command::ToggleReadWrite, "Send holding register as a read/write multiple (0x17) block"),
+    bind(KeyContext::Queue, &[key(KeyCode::Char('g'))], Command::GoToQueueItem, "Go to selected queue item's address"),
+    bind(KeyContext::Queue, &[key(KeyCode::Char('r'))], Command::RevertQueueItem, "Revert selected queue item"),
+    bind(KeyContext::Queue, &[key(KeyCode::Char('m'))], Command::SaveMacro, "Save queue as macro file"),
+    bind(KeyContext::Queue, &[key(KeyCode::Char('i'))], Command::ImportCsv, "Queue table,address,value rows from a .csv file"),
+    // Watch
+    bind(KeyContext::Watch, &[key(KeyCode::Up)], Command::PreviousWatchItem, "Previous pinned address"),
+    bind(KeyContext::Watch, &[key(KeyCode::Down)], Command::NextWatchItem, "Next pinned address"),
+    bind(KeyContext::Watch, &[key(KeyCode::Char('g'))], Command::GoToWatchItem, "Go to selected pinned address"),
+    bind(KeyContext::Watch, &[key(KeyCode::Char('r'))], Command::ReadWatchList, "Read all pinned addresses now"),
+    bind(KeyContext::Watch, &[key(KeyCode::Char('x'))], Command::Unpin, "Unpin selected address"),
+    // Alarms
+    bind(KeyContext::Alarms, &[key(KeyCode::Char('c'))], Command::ClearAlarmLog, "Clear the alarm log"),
+];
+
+pub fn command(context: KeyContext, code: KeyCode, shift_pressed: bool) -> Option<Command> {
+    let find = |context| {
+        KEYMAP
+            .iter()
+            .filter(|binding| binding.context == context)
+            .find(|binding| {
+                binding
+                    .keys
+                    .iter()
+                    .any(|key| key.code == code && (!key.shift || shift_pressed))
+            })
+            .map(|binding| binding.command)
+    };
+    find(context).or_else(|| find(KeyContext::General))
+}
+
+pub fn help_lines(context: KeyContext) -> Vec<Line<'static>> {
+    let mut lines: Vec<Line> = context.intro().map(Line::from).into_iter().collect();
+    for binding in KEYMAP.iter().filter(|binding| binding.context == context) {
+        let mut spans = vec![];
+        for (index, key) in binding.keys.iter().enumerate() {
+            if index > 0 {
+                spans.push(Span::raw(" or "));
+            }
+            spans.push(Span::styled(key.label(), Style::default().bold()));
+        }
+        spans.push(Span::raw(format!(" - {}", binding.description)));
+        lines.push(Line::from(spans));
+    }
+    lines.extend(context.notes().iter().map(|note| Line::from(*note)));
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A binding shadowed by an earlier one would show up in the help but never run
+    #[test]
+    fn every_binding_reaches_its_command() {
+        for binding in KEYMAP {
+            for key in binding.keys {
+                assert_eq!(
+                    command(binding.context, key.code, key.shift),
+                    Some(binding.command),
+                    "{} in {:?}",
+                    key.label(),
+                    binding.context
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,91 @@
+//!   Copyright 2025 Isaac Schlaegel
+//!
+//!    Licensed under the Apache License, Version 2.0 (the "License");
+//!    you may not use this file except in compliance with the License.
+//!    You may obtain a copy of the License at
+//!
+//!        http://www.apache.org/licenses/LICENSE-2.0
+//!
+//!    Unless required by applicable law or agreed to in writing, software
+//!    distributed under the License is distributed on an "AS IS" BASIS,
+//!    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//!    See the License for the specific language governing permissions and
+//!    limitations under the License.
+
+use crate::{
+    enums::SelectedTopTab,
+    utils::{invalid_line, parse_address_range},
+};
+use std::path::Path;
+use tokio::fs;
+
+struct Label {
+    table: SelectedTopTab,
+    start: u16,
+    end: u16,
+    name: String,
+}
+
+#[derive(Default)]
+pub struct LabelMap {
+    labels: Vec<Label>,
+}
+
+impl LabelMap {
+    // Line format: table,address[-address],name
+    // Addresses are 1-based, matching the rest of the UI
+    pub async fn from_file<P: AsRef<Path>>(filename: P) -> std::io::Result<Self> {
+        let file = fs::read_to_string(filename).await?;
+        let mut labels = vec![];
+
+        for (index, line) in file.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            // Names may contain commas, so only split off the first two fields
+            let fields: Vec<&str> = line.splitn(3, ',').map(str::trim).collect();
+            if fields.len() != 3 || fields[2].is_empty() {
+                return Err(invalid_line(index, "Expected table,address,name"));
+            }
+
+            let table = SelectedTopTab::from_name(fields[0])
+                .ok_or_else(|| invalid_line(index, "Unknown table"))?;
+            let (start, end) = parse_address_range(fields[1])
+                .ok_or_else(|| invalid_line(index, "Invalid address range"))?;
+
+            labels.push(Label {
+                table,
+                start,
+                end,
+                name: fields[2].to_string(),
+            });
+        }
+
+        Ok(Self { labels })
+    }
+
+    pub fn add(&mut self, table: SelectedTopTab, start: u16, end: u16, name: String) {
+        self.labels.push(Label {
+            table,
+            start,
+            end,
+            name,
+        });
+    }
+
+    // The other map's labels take priority over these
+    pub fn extend(&mut self, other: LabelMap) {
+        self.labels.extend(other.labels);
+    }
+
+    pub fn get(&self, table: SelectedTopTab, address: u16) -> Option<&str> {
+        // Later lines take priority so broad ranges can be overridden
+        self.labels
+            .iter()
+            .rev()
+            .find(|label| label.table == table && (label.start..=label.end).contains(&address))
+            .map(|label| label.name.as_str())
+    }
+}
@@ -0,0 +1,142 @@
+//!   Copyright 2025 Isaac Schlaegel
+//!
+//!    Licensed under the Apache License, Version 2.0 (the "License");
+//!    you may not use this file except in compliance with the License.
+//!    You may obtain a copy of the License at
+//!
+//!        http://www.apache.org/licenses/LICENSE-2.0
+//!
+//!    Unless required by applicable law or agreed to in writing, software
+//!    distributed under the License is distributed on an "AS IS" BASIS,
+//!    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//!    See the License for the specific language governing permissions and
+//!    limitations under the License.
+
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
+
+const LATENCY_WINDOW: usize = 20; // Requests kept for the rolling average
+const SPIKE_FACTOR: u32 = 3; // A request this many times slower than average is a spike
+const STALL_AFTER: Duration = Duration::from_secs(1); // Waiting longer than this is shown as a stall
+
+// Round-trip times of the requests the modbus task has sent, for display only
+#[derive(Default)]
+pub struct Latency {
+    samples: VecDeque<Duration>, // Oldest first
+    in_flight: Option<Instant>,  // When the pending request was sent
+}
+
+impl Latency {
+    pub fn start(&mut self, now: Instant) {
+        self.in_flight = Some(now);
+    }
+
+    pub fn finish(&mut self, round_trip: Duration) {
+        self.in_flight = None;
+        if self.samples.len() == LATENCY_WINDOW {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(round_trip);
+    }
+
+    pub fn clear(&mut self) {
+        self.samples.clear();
+        self.in_flight = None;
+    }
+
+    pub fn last(&self) -> Option<Duration> {
+        self.samples.back().copied()
+    }
+
+    pub fn average(&self) -> Option<Duration> {
+        match self.samples.len() {
+            0 => None,
+            len => Some(self.samples.iter().sum::<Duration>() / len as u32),
+        }
+    }
+
+    pub fn max(&self) -> Option<Duration> {
+        self.samples.iter().max().copied()
+    }
+
+    // Time the pending request has been waiting, once it counts as stalled
+    pub fn stalled(&self, now: Instant) -> Option<Duration> {
+        self.in_flight
+            .map(|sent| now.saturating_duration_since(sent))
+            .filter(|waiting| *waiting >= STALL_AFTER)
+    }
+
+    // The last request took much longer than the ones before it
+    pub fn is_spike(&self) -> bool {
+        let (Some(last), earlier) = (self.samples.back(), self.samples.len().saturating_sub(1))
+        else {
+            return false;
+        };
+        match earlier {
+            0 => false,
+            len => {
+                let average = self.samples.iter().take(len).sum::<Duration>() / len as u32;
+                *last > average * SPIKE_FACTOR
+            }
+        }
+    }
+
+    pub fn summary(&self, now: Instant) -> Option<String> {
+        if let Some(waiting) = self.stalled(now) {
+            return Some(format!("waiting {} ms", waiting.as_millis()));
+        }
+        Some(format!(
+            "{} ms (avg {} ms)",
+            self.last()?.as_millis(),
+            self.average()?.as_millis()
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn millis(values: &[u64]) -> Latency {
+        let mut latency = Latency::default();
+        for value in values {
+            latency.finish(Duration::from_millis(*value));
+        }
+        latency
+    }
+
+    #[test]
+    fn average_covers_the_last_window() {
+        let mut latency = millis(&[1000; LATENCY_WINDOW]);
+        for _ in 0..LATENCY_WINDOW {
+            latency.finish(Duration::from_millis(10));
+        }
+        assert_eq!(latency.average(), Some(Duration::from_millis(10)));
+        assert_eq!(latency.max(), Some(Duration::from_millis(10)));
+    }
+
+    #[test]
+    fn slow_request_is_a_spike() {
+        assert!(!millis(&[10, 12, 11, 20]).is_spike());
+        assert!(millis(&[10, 12, 11, 80]).is_spike());
+        assert!(!millis(&[80]).is_spike());
+    }
+
+    #[test]
+    fn pending_request_stalls() {
+        let mut latency = millis(&[10]);
+        let sent = Instant::now();
+        latency.start(sent);
+        assert_eq!(latency.stalled(sent), None);
+        assert_eq!(
+            latency
+                .summary(sent + Duration::from_millis(1500))
+                .as_deref(),
+            Some("waiting 1500 ms")
+        );
+        latency.finish(Duration::from_millis(1600));
+        assert_eq!(latency.stalled(sent + Duration::from_secs(5)), None);
+    }
+}
@@ -14,25 +14,205 @@
 
 use crate::{
     enums::{CellType, SelectedTopTab},
-    utils::{BufReader, ModbusWriteCommand},
+    utils::{
+        BROADCAST_UNIT_ID, BufReader, ModbusWriteCommand, invalid_line, read_back, resolve_host,
+        send_broadcast,
+    },
 };
+use color_eyre::{Report, eyre::eyre};
 use inquire::Text;
 use std::{
+    collections::BTreeMap,
+    fmt,
     net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
     path::Path,
+    time::{Duration, Instant},
 };
 use tokio::{
     fs::{self, File},
     io::AsyncWriteExt,
+    net::TcpStream,
 };
-use tokio_modbus::prelude::*;
+use tokio_modbus::{ExceptionCode, client::Context, prelude::*};
+
+const DEFAULT_UNIT_ID: u8 = 255; // Same as tcp::connect
+const DEFAULT_RETRY_DELAY: Duration = Duration::from_millis(500);
+const FORMAT_VERSION: u8 = 2; // Adds the unit ID, takes the place of the IP protocol byte
+
+// Failures the exit code needs to tell apart from connection and protocol errors
+#[derive(Debug)]
+pub enum MacroFailure {
+    BadFile(std::io::Error),
+    Assertions(usize),   // Inputs that didn't hold the pinned value
+    Verification(usize), // Written values the device read back differently
+}
+
+impl fmt::Display for MacroFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MacroFailure::BadFile(err) => write!(f, "Couldn't load the macro file: {err}"),
+            MacroFailure::Assertions(count) => write!(f, "{count} assertion(s) failed"),
+            MacroFailure::Verification(count) => {
+                write!(f, "Verification failed for {count} value(s)")
+            }
+        }
+    }
+}
+
+impl std::error::Error for MacroFailure {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            MacroFailure::BadFile(err) => Some(err),
+            MacroFailure::Assertions(_) | MacroFailure::Verification(_) => None,
+        }
+    }
+}
+
+// Process exit codes of parse-macro and write, 2 is left to clap's usage errors
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MacroExitCode {
+    Failure = 1,
+    BadFile = 3,
+    ConnectionRefused = 4,
+    Timeout = 5,
+    ModbusException = 6,
+    AssertionFailed = 7, // Also used when --verify reads back a different value
+}
+
+impl MacroExitCode {
+    pub const HELP: &str = "Exit codes:
+  0  Success
+  1  Any other failure
+  3  The macro file couldn't be read or parsed
+  4  The connection was refused
+  5  The connection or a request timed out
+  6  The device answered with a Modbus exception
+  7  An assertion or --verify read-back failed";
+
+    pub fn from_report(report: &Report) -> Self {
+        for cause in report.chain() {
+            if let Some(failure) = cause.downcast_ref::<MacroFailure>() {
+                return match failure {
+                    MacroFailure::BadFile(_) => MacroExitCode::BadFile,
+                    MacroFailure::Assertions(_) | MacroFailure::Verification(_) => {
+                        MacroExitCode::AssertionFailed
+                    }
+                };
+            }
+            if cause.downcast_ref::<ExceptionCode>().is_some() {
+                return MacroExitCode::ModbusException;
+            }
+            // Transport errors don't expose the io::Error as their source
+            let io_error = match cause.downcast_ref::<tokio_modbus::Error>() {
+                Some(tokio_modbus::Error::Transport(err)) => Some(err),
+                _ => cause.downcast_ref::<std::io::Error>(),
+            };
+            match io_error.map(std::io::Error::kind) {
+                Some(std::io::ErrorKind::ConnectionRefused) => {
+                    return MacroExitCode::ConnectionRefused;
+                }
+                Some(std::io::ErrorKind::TimedOut) => return MacroExitCode::Timeout,
+                _ => {}
+            }
+        }
+        MacroExitCode::Failure
+    }
+}
+
+// Prefix for each command line, e.g. "[12/300 4% ETA 1:05]"
+// The ETA needs at least one finished command, and dry runs have none to time.
+// A resumed run only times the commands since the index it started at.
+fn progress_prefix(index: usize, total: usize, started: Option<(Instant, usize)>) -> String {
+    let percent = index * 100 / total.max(1);
+    match started {
+        Some((started, first)) if index > first => {
+            let done = (index - first) as f64;
+            let remaining = started.elapsed().as_secs_f64() / done * (total - index) as f64;
+            let remaining = remaining.round() as u64;
+            format!(
+                "[{}/{total} {percent}% ETA {}:{:02}]",
+                index + 1,
+                remaining / 60,
+                remaining % 60
+            )
+        }
+        _ => format!("[{}/{total} {percent}%]", index + 1),
+    }
+}
+
+// Devices for --targets, one host:port per line with IPv6 literals in brackets
+pub async fn targets_from_file<P: AsRef<Path>>(filename: P) -> std::io::Result<Vec<(String, u16)>> {
+    parse_targets(&fs::read_to_string(filename).await?)
+}
+
+pub fn parse_targets(text: &str) -> std::io::Result<Vec<(String, u16)>> {
+    let mut targets = vec![];
+    for (index, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let (host, port) = line
+            .rsplit_once(':')
+            .filter(|(host, _)| !host.contains(':') || host.ends_with(']'))
+            .ok_or_else(|| invalid_line(index, "Expected host:port"))?;
+        let port = port
+            .trim()
+            .parse::<u16>()
+            .map_err(|_| invalid_line(index, "Invalid port"))?;
+        match host.trim().trim_start_matches('[').trim_end_matches(']') {
+            "" => return Err(invalid_line(index, "Missing host")),
+            host => targets.push((host.to_string(), port)),
+        }
+    }
+
+    match targets.is_empty() {
+        true => Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            "No targets listed",
+        )),
+        false => Ok(targets),
+    }
+}
+
+// Sends one command, returning what was read when an assertion doesn't hold
+async fn send_command(
+    context: &mut Context,
+    command: &ModbusWriteCommand,
+) -> tokio_modbus::Result<Option<String>> {
+    Ok(match *command {
+        (SelectedTopTab::Coils, addr, CellType::Coil(content)) => context
+            .write_single_coil(addr, content)
+            .await?
+            .map(|()| None),
+        (SelectedTopTab::HoldingRegisters, addr, CellType::Word(content)) => context
+            .write_single_register(addr, content)
+            .await?
+            .map(|()| None),
+        (SelectedTopTab::DiscreteInputs, addr, CellType::Coil(content)) => context
+            .read_discrete_inputs(addr, 1)
+            .await?
+            .map(|values| (values[0] != content).then(|| values[0].to_string())),
+        (SelectedTopTab::InputRegisters, addr, CellType::Word(content)) => context
+            .read_input_registers(addr, 1)
+            .await?
+            .map(|values| (values[0] != content).then(|| values[0].to_string())),
+        _ => Ok(None),
+    })
+}
 
 #[derive(Debug, PartialEq)]
 pub struct MagModCommandList {
     ip_addr: IpAddr,
     port: u16,
+    unit_id: u8, // Slave behind the target, 255 when talking to the device directly
     command_count: u32,
     commands: Vec<ModbusWriteCommand>,
+    retries: u32, // Extra attempts for a command that timed out or lost the connection
+    retry_delay: Duration,
+    timeout: Option<Duration>, // Per request, None waits as long as the connection does
+    resume_from: usize,        // Commands an earlier run already sent
 }
 
 impl MagModCommandList {
@@ -40,12 +220,164 @@ impl MagModCommandList {
         Self {
             ip_addr,
             port,
+            unit_id: DEFAULT_UNIT_ID,
             command_count: commands.len() as u32,
             commands,
+            retries: 0,
+            retry_delay: DEFAULT_RETRY_DELAY,
+            timeout: None,
+            resume_from: 0,
+        }
+    }
+
+    pub fn with_unit_id(mut self, unit_id: u8) -> Self {
+        self.unit_id = unit_id;
+        self
+    }
+
+    pub fn with_retries(mut self, retries: u32, retry_delay: Duration) -> Self {
+        self.retries = retries;
+        self.retry_delay = retry_delay;
+        self
+    }
+
+    pub fn with_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    pub fn with_resume_from(mut self, resume_from: usize) -> color_eyre::Result<Self> {
+        if resume_from > self.commands.len() {
+            return Err(eyre!(
+                "Can't resume from command {resume_from}, the macro only has {}",
+                self.commands.len()
+            ));
         }
+        self.resume_from = resume_from;
+        Ok(self)
     }
 
-    pub async fn to_file(&self, mut filename: String, force: bool) -> std::io::Result<()> {
+    // Concatenates in order. A write in a later list replaces the earlier lists' writes to the
+    // same address, while repeated writes inside one list are kept as they are.
+    // Returns a warning for every replaced write that had a different value.
+    pub fn merge(
+        lists: Vec<Self>,
+        target: Option<(IpAddr, u16)>,
+    ) -> color_eyre::Result<(Self, Vec<String>)> {
+        let Some(first) = lists.first() else {
+            return Err(eyre!("Nothing to merge"));
+        };
+        let unit_id = first.unit_id;
+        if lists.iter().any(|list| list.unit_id != unit_id) {
+            return Err(eyre!("Macros target different unit IDs"));
+        }
+        let (ip_addr, port) = match target {
+            Some(target) => target,
+            None => {
+                let target = (first.ip_addr, first.port);
+                if let Some(other) = lists
+                    .iter()
+                    .find(|list| (list.ip_addr, list.port) != target)
+                {
+                    return Err(eyre!(
+                        "Macros target different devices ({} and {}), pass a target to override",
+                        SocketAddr::new(target.0, target.1),
+                        SocketAddr::new(other.ip_addr, other.port)
+                    ));
+                }
+                target
+            }
+        };
+
+        let mut commands: Vec<ModbusWriteCommand> = vec![];
+        let mut warnings = vec![];
+        for list in lists {
+            // Last value this list writes to each address, assertions aren't writes
+            let final_writes: BTreeMap<(SelectedTopTab, u16), CellType> = list
+                .commands
+                .iter()
+                .filter(|(table, _, _)| {
+                    matches!(
+                        table,
+                        SelectedTopTab::Coils | SelectedTopTab::HoldingRegisters
+                    )
+                })
+                .map(|(table, address, content)| ((*table, *address), *content))
+                .collect();
+
+            commands.retain(|(table, address, content)| {
+                match final_writes.get(&(*table, *address)) {
+                    Some(final_content) => {
+                        if final_content != content {
+                            warnings.push(format!(
+                                "{} {} replaced by {}",
+                                table.memory_address(*address),
+                                content.to_u16(),
+                                final_content.to_u16()
+                            ));
+                        }
+                        false
+                    }
+                    None => true,
+                }
+            });
+            commands.extend(list.commands);
+        }
+
+        Ok((
+            Self::new(ip_addr, port, commands).with_unit_id(unit_id),
+            warnings,
+        ))
+    }
+
+    pub fn unsupported_count(&self) -> usize {
+        self.commands
+            .iter()
+            .filter(|command| !Self::is_supported(command))
+            .count()
+    }
+
+    fn is_supported((tab, _, content): &ModbusWriteCommand) -> bool {
+        matches!(
+            (tab, content),
+            (SelectedTopTab::Coils, CellType::Coil(_))
+                | (SelectedTopTab::DiscreteInputs, CellType::Coil(_))
+                | (SelectedTopTab::InputRegisters, CellType::Word(_))
+                | (SelectedTopTab::HoldingRegisters, CellType::Word(_))
+        )
+    }
+
+    // The planned operations for --dry-run-json, addresses are 1-based like the command line
+    pub fn to_json(&self) -> String {
+        let operations: Vec<String> = self
+            .commands
+            .iter()
+            .filter(|command| Self::is_supported(command))
+            .map(|(tab, addr, content)| {
+                let value = match content {
+                    CellType::Coil(content) => content.to_string(),
+                    CellType::Word(content) => content.to_string(),
+                };
+                format!(
+                    "{{\"table\":\"{}\",\"address\":{},\"value\":{value}}}",
+                    tab.to_string().to_lowercase().replace(' ', "_"),
+                    *addr as u32 + 1
+                )
+            })
+            .collect();
+        format!("[{}]", operations.join(","))
+    }
+
+    // Returns the number of unsupported commands that were left out of the file
+    pub async fn to_file(&self, mut filename: String, force: bool) -> std::io::Result<usize> {
+        let skipped_count = self.unsupported_count();
+        if skipped_count == self.commands.len() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "No supported commands to save",
+            ));
+        }
+
         let mut bytes = vec![];
         let mut path_buf = std::env::current_dir()?;
         filename = filename.trim().to_string();
@@ -60,6 +392,11 @@ impl MagModCommandList {
         // File extension
         bytes.extend_from_slice(b"MAGMOD");
 
+        // Files for the default unit keep the original layout, so older versions can read them
+        if self.unit_id != DEFAULT_UNIT_ID {
+            bytes.push(FORMAT_VERSION);
+        }
+
         // IP Address
         bytes.extend_from_slice(&match self.ip_addr {
             IpAddr::V4(addr) => {
@@ -77,8 +414,13 @@ impl MagModCommandList {
         // Port
         bytes.extend(self.port.to_be_bytes());
 
-        // Command count
-        bytes.extend(self.command_count.to_be_bytes());
+        // Unit ID
+        if self.unit_id != DEFAULT_UNIT_ID {
+            bytes.push(self.unit_id);
+        }
+
+        // Command count, excluding anything that can't be encoded
+        bytes.extend((self.command_count - skipped_count as u32).to_be_bytes());
 
         for (tab, address, content) in self.commands.iter() {
             match (tab, content) {
@@ -95,13 +437,26 @@ impl MagModCommandList {
                     bytes.extend_from_slice(&address.to_be_bytes());
                     bytes.extend_from_slice(&content.to_be_bytes());
                 }
+                (SelectedTopTab::DiscreteInputs, CellType::Coil(content)) => {
+                    bytes.extend_from_slice(&[2u8]); // Function code 0x02 - Assert discrete input
+                    bytes.extend_from_slice(&address.to_be_bytes());
+                    match *content {
+                        true => bytes.extend_from_slice(&[0xff, 0x00]),
+                        false => bytes.extend_from_slice(&[0x00, 0x00]),
+                    }
+                }
+                (SelectedTopTab::InputRegisters, CellType::Word(content)) => {
+                    bytes.extend_from_slice(&[4u8]); // Function code 0x04 - Assert input register
+                    bytes.extend_from_slice(&address.to_be_bytes());
+                    bytes.extend_from_slice(&content.to_be_bytes());
+                }
                 _ => {}
             }
         }
 
         file.write_all(&bytes).await?;
 
-        Ok(())
+        Ok(skipped_count)
     }
 
     pub async fn from_file<P: AsRef<Path>>(filename: P) -> std::io::Result<Self> {
@@ -116,7 +471,11 @@ impl MagModCommandList {
             ));
         }
 
-        let ip_protocol = reader.read_u8().await?;
+        let mut ip_protocol = reader.read_u8().await?;
+        let versioned = ip_protocol == FORMAT_VERSION;
+        if versioned {
+            ip_protocol = reader.read_u8().await?;
+        }
         let ip_addr = match ip_protocol {
             4 => IpAddr::V4(Ipv4Addr::from(
                 <[u8; 4]>::try_from(reader.read_exact(4).await?).unwrap(),
@@ -134,11 +493,18 @@ impl MagModCommandList {
 
         let port = reader.read_u16().await?;
 
+        let unit_id = match versioned {
+            true => reader.read_u8().await?,
+            false => DEFAULT_UNIT_ID,
+        };
+
         let command_count = reader.read_u32().await?;
 
         let mut commands = Vec::with_capacity(command_count as usize);
         for _ in 0..command_count {
             let table = match reader.read_u8().await? {
+                2 => SelectedTopTab::DiscreteInputs,
+                4 => SelectedTopTab::InputRegisters,
                 5 => SelectedTopTab::Coils,
                 6 => SelectedTopTab::HoldingRegisters,
                 _ => {
@@ -152,7 +518,7 @@ impl MagModCommandList {
             let output_value = reader.read_u16().await?;
 
             let cell_content = match table {
-                SelectedTopTab::Coils => match output_value {
+                SelectedTopTab::Coils | SelectedTopTab::DiscreteInputs => match output_value {
                     0x0000 => CellType::Coil(false),
                     0xff00 => CellType::Coil(true),
                     _ => {
@@ -162,12 +528,8 @@ impl MagModCommandList {
                         ));
                     }
                 },
-                SelectedTopTab::HoldingRegisters => CellType::Word(output_value),
-                _ => {
-                    return Err(std::io::Error::new(
-                        std::io::ErrorKind::InvalidData,
-                        "Unsupported command.",
-                    ));
+                SelectedTopTab::InputRegisters | SelectedTopTab::HoldingRegisters => {
+                    CellType::Word(output_value)
                 }
             };
             commands.push((table, address, cell_content));
@@ -176,82 +538,396 @@ impl MagModCommandList {
         Ok(Self {
             ip_addr,
             port,
+            unit_id,
             command_count,
             commands,
+            retries: 0,
+            retry_delay: DEFAULT_RETRY_DELAY,
+            timeout: None,
+            resume_from: 0,
         })
     }
 
+    // Runs the commands on each target in turn and prints how every device went.
+    // Stops at the first failed device unless continue_on_error is set; either way the first
+    // failure decides the exit code.
+    pub async fn run_on_targets(
+        &mut self,
+        targets: &[(String, u16)],
+        check_connection: bool,
+        dry_run: bool,
+        verify: bool,
+        continue_on_error: bool,
+    ) -> color_eyre::Result<()> {
+        let mut results: Vec<(String, Option<String>)> = vec![]; // Target, failure
+        let mut first_failure = None;
+
+        for (index, (host, port)) in targets.iter().enumerate() {
+            let target = match host.contains(':') {
+                true => format!("[{host}]:{port}"),
+                false => format!("{host}:{port}"),
+            };
+            println!("Device {} of {}: {target}", index + 1, targets.len());
+
+            let result = match resolve_host(host, *port).await {
+                Ok(socket_addr) => {
+                    self.ip_addr = socket_addr.ip();
+                    self.port = socket_addr.port();
+                    self.run_macro(false, check_connection, dry_run, verify)
+                        .await
+                }
+                Err(err) => Err(Report::new(err).wrap_err(format!("Couldn't resolve {host}"))),
+            };
+
+            match result {
+                Ok(()) => results.push((target, None)),
+                Err(report) => {
+                    println!("    {report:#}");
+                    results.push((target, Some(format!("{report:#}"))));
+                    first_failure.get_or_insert(report);
+                    if !continue_on_error {
+                        break;
+                    }
+                }
+            }
+        }
+
+        println!("Summary:");
+        for (target, failure) in &results {
+            match failure {
+                None => println!("  {target}  OK"),
+                Some(failure) => println!("  {target}  FAILED: {failure}"),
+            }
+        }
+        let skipped = targets.len() - results.len();
+        if skipped > 0 {
+            println!("  {skipped} device(s) skipped, pass --continue-on-error to run them anyway");
+        }
+
+        match first_failure {
+            Some(report) => {
+                let failed = results
+                    .iter()
+                    .filter(|(_, failure)| failure.is_some())
+                    .count();
+                Err(report.wrap_err(format!("{failed} of {} devices failed", targets.len())))
+            }
+            None => Ok(()),
+        }
+    }
+
     // Independent of TUI
+    async fn check_connection(&self, socket_addr: SocketAddr) -> color_eyre::Result<()> {
+        println!("Checking connection to {socket_addr}...");
+        let mut context = tcp::connect(socket_addr).await?;
+        context.set_slave(Slave(self.unit_id));
+        println!("Connection successful.");
+        context.disconnect().await?;
+        Ok(())
+    }
+
+    // Nothing replies to unit 0, so each write is sent without waiting. Input checks and
+    // --verify need a reply and are refused before anything is sent
+    async fn run_broadcast(&self, socket_addr: SocketAddr, verify: bool) -> color_eyre::Result<()> {
+        if verify {
+            return Err(eyre!(
+                "Broadcasts get no reply, so --verify can't read the written values back"
+            ));
+        }
+        if self
+            .commands
+            .iter()
+            .skip(self.resume_from)
+            .any(|(table, _, _)| {
+                matches!(
+                    table,
+                    SelectedTopTab::DiscreteInputs | SelectedTopTab::InputRegisters
+                )
+            })
+        {
+            return Err(eyre!(
+                "Broadcasts get no reply, so the macro's input checks can't be read"
+            ));
+        }
+
+        println!("Connecting to {socket_addr} (unit 0, broadcast)...");
+        let mut stream = TcpStream::connect(socket_addr).await?;
+        println!("Connection established. Beginning command-flow...");
+        if self.resume_from > 0 {
+            println!("Skipping the first {} command(s)", self.resume_from);
+        }
+
+        let mut transaction = 0;
+        let started = Instant::now();
+        for (index, command) in self.commands.iter().enumerate().skip(self.resume_from) {
+            let progress = progress_prefix(
+                index,
+                self.commands.len(),
+                Some((started, self.resume_from)),
+            );
+            let (address_space, addr, content) = command;
+            match (address_space, content) {
+                (SelectedTopTab::Coils, CellType::Coil(content)) => {
+                    println!(
+                        "{} Setting Coil {} to {content}",
+                        progress,
+                        address_space.memory_address(*addr)
+                    );
+                }
+                (SelectedTopTab::HoldingRegisters, CellType::Word(content)) => {
+                    println!(
+                        "{} Setting Register {} to {content}",
+                        progress,
+                        address_space.memory_address(*addr)
+                    );
+                }
+                _ => continue,
+            }
+            if let Err(err) = send_broadcast(&mut stream, &mut transaction, command).await {
+                return Err(Report::new(err).wrap_err(format!(
+                    "Command {} of {} failed, pass --resume-from {index} to continue from it",
+                    index + 1,
+                    self.commands.len()
+                )));
+            }
+            println!("    Sent (broadcast, unverified)");
+        }
+
+        println!("Command-flow completed. Disconnecting from client...");
+        stream.shutdown().await?;
+        Ok(())
+    }
+
     pub async fn run_macro(
         &mut self,
         confirm: bool,
         check_connection: bool,
         dry_run: bool,
+        verify: bool,
     ) -> color_eyre::Result<()> {
+        // Probing right away catches a mistyped target before any writes start
         if confirm {
-            self.ip_addr = Text::new("Confirm Target IP Address")
-                .with_default(&self.ip_addr.to_string())
-                .prompt()?
-                .parse()?;
-            self.port = Text::new("Confirm Target Port (1-65535)")
-                .with_default(&self.port.to_string())
-                .prompt()?
-                .parse()?;
+            loop {
+                let host = Text::new("Confirm Target Address or Hostname")
+                    .with_default(&self.ip_addr.to_string())
+                    .prompt()?;
+                self.port = Text::new("Confirm Target Port (1-65535)")
+                    .with_default(&self.port.to_string())
+                    .prompt()?
+                    .parse()?;
+                match resolve_host(host.trim(), self.port).await {
+                    Ok(socket_addr) => self.ip_addr = socket_addr.ip(),
+                    Err(err) => {
+                        println!("Could not resolve {}: {err}", host.trim());
+                        continue;
+                    }
+                }
+
+                // Dry runs never touch the network, and --check-connection probes below anyway
+                if dry_run || check_connection {
+                    break;
+                }
+                let socket_addr = SocketAddr::new(self.ip_addr, self.port);
+                match self.check_connection(socket_addr).await {
+                    Ok(()) => break,
+                    Err(err) => println!("Could not connect to {socket_addr}: {err}"),
+                }
+            }
         }
 
         let socket_addr = SocketAddr::new(self.ip_addr, self.port);
         match (check_connection, dry_run) {
             (true, false) => {
                 // Check connection only
-                println!("Checking connection to {socket_addr}...");
-                let mut context = tcp::connect(socket_addr).await?;
-                println!("Connection successful.");
-                context.disconnect().await?;
+                self.check_connection(socket_addr).await?;
             }
             (false, true) => {
                 // Dry Run
-                println!("[DRY RUN] Connecting to {socket_addr}...");
+                println!(
+                    "[DRY RUN] Connecting to {socket_addr} (unit {})...",
+                    self.unit_id
+                );
                 println!("[DRY RUN] Connection established. Beginning command-flow...");
+                if self.resume_from > 0 {
+                    println!(
+                        "[DRY RUN] Skipping the first {} command(s)",
+                        self.resume_from
+                    );
+                }
 
-                for command in self.commands.iter() {
+                for (index, command) in self.commands.iter().enumerate().skip(self.resume_from) {
+                    let progress = progress_prefix(index, self.commands.len(), None);
                     let (address_space, addr, content) = command;
                     match (address_space, content) {
                         (SelectedTopTab::Coils, CellType::Coil(content)) => {
-                            println!("[DRY RUN]  Setting Coil 0x0{:04X} to {content}", addr + 1);
+                            println!(
+                                "[DRY RUN] {} Setting Coil {} to {content}",
+                                progress,
+                                address_space.memory_address(*addr)
+                            );
                         }
                         (SelectedTopTab::HoldingRegisters, CellType::Word(content)) => {
                             println!(
-                                "[DRY RUN]  Setting Register 0x4{:04X} to {content}",
-                                addr + 1
+                                "[DRY RUN] {} Setting Register {} to {content}",
+                                progress,
+                                address_space.memory_address(*addr)
+                            );
+                        }
+                        (SelectedTopTab::DiscreteInputs, CellType::Coil(content)) => {
+                            println!(
+                                "[DRY RUN] {} Checking Input {} is {content}",
+                                progress,
+                                address_space.memory_address(*addr)
+                            );
+                        }
+                        (SelectedTopTab::InputRegisters, CellType::Word(content)) => {
+                            println!(
+                                "[DRY RUN] {} Checking Input Register {} is {content}",
+                                progress,
+                                address_space.memory_address(*addr)
                             );
                         }
                         _ => {}
                     }
                 }
 
+                if verify {
+                    println!("[DRY RUN] Reading back written values to verify...");
+                }
+
                 println!("[DRY RUN] Command-flow completed. Disconnecting from client...");
             }
+            (false, false) if self.unit_id == BROADCAST_UNIT_ID => {
+                self.run_broadcast(socket_addr, verify).await?;
+            }
             (false, false) => {
                 // Normal Run
-                println!("Connecting to {socket_addr}...");
+                println!("Connecting to {socket_addr} (unit {})...", self.unit_id);
                 let mut context = tcp::connect(socket_addr).await?;
+                context.set_slave(Slave(self.unit_id));
                 println!("Connection established. Beginning command-flow...");
+                if self.resume_from > 0 {
+                    println!("Skipping the first {} command(s)", self.resume_from);
+                }
 
-                for command in self.commands.iter() {
+                let mut failed_assertions = 0;
+                let started = Instant::now();
+                for (index, command) in self.commands.iter().enumerate().skip(self.resume_from) {
+                    let progress = progress_prefix(
+                        index,
+                        self.commands.len(),
+                        Some((started, self.resume_from)),
+                    );
                     let (address_space, addr, content) = command;
                     match (address_space, content) {
                         (SelectedTopTab::Coils, CellType::Coil(content)) => {
-                            println!("  Setting Coil 0x0{:04X} to {content}", addr + 1);
-                            context.write_single_coil(*addr, *content).await??;
+                            println!(
+                                "{} Setting Coil {} to {content}",
+                                progress,
+                                address_space.memory_address(*addr)
+                            );
                         }
                         (SelectedTopTab::HoldingRegisters, CellType::Word(content)) => {
-                            println!("  Setting Register 0x4{:04X} to {content}", addr + 1);
-                            context.write_single_register(*addr, *content).await??;
+                            println!(
+                                "{} Setting Register {} to {content}",
+                                progress,
+                                address_space.memory_address(*addr)
+                            );
                         }
-                        _ => {}
+                        (SelectedTopTab::DiscreteInputs, CellType::Coil(content)) => {
+                            println!(
+                                "{} Checking Input {} is {content}",
+                                progress,
+                                address_space.memory_address(*addr)
+                            );
+                        }
+                        (SelectedTopTab::InputRegisters, CellType::Word(content)) => {
+                            println!(
+                                "{} Checking Input Register {} is {content}",
+                                progress,
+                                address_space.memory_address(*addr)
+                            );
+                        }
+                        _ => continue,
+                    }
+
+                    // Only transport errors are retried, an exception would come back the same.
+                    // The failed command may not have gone through, so resuming sends it again
+                    let failed_command = || {
+                        format!(
+                            "Command {} of {} failed, pass --resume-from {index} to continue from it",
+                            index + 1,
+                            self.commands.len()
+                        )
+                    };
+                    let mut attempt = 0;
+                    let outcome = loop {
+                        let result = match self.timeout {
+                            Some(timeout) => {
+                                tokio::time::timeout(timeout, send_command(&mut context, command))
+                                    .await
+                                    .unwrap_or_else(|_| {
+                                        Err(tokio_modbus::Error::Transport(
+                                            std::io::ErrorKind::TimedOut.into(),
+                                        ))
+                                    })
+                            }
+                            None => send_command(&mut context, command).await,
+                        };
+                        match result {
+                            Ok(outcome) => break outcome,
+                            Err(err) if attempt < self.retries => {
+                                attempt += 1;
+                                println!("    {err}, retrying ({attempt}/{})...", self.retries);
+                                tokio::time::sleep(self.retry_delay).await;
+                                // The old connection may not survive a timeout or a reset
+                                match tcp::connect(socket_addr).await {
+                                    Ok(new_context) => {
+                                        context = new_context;
+                                        context.set_slave(Slave(self.unit_id));
+                                    }
+                                    Err(err) => println!("    Couldn't reconnect: {err}"),
+                                }
+                            }
+                            Err(err) => return Err(Report::new(err).wrap_err(failed_command())),
+                        }
+                    };
+                    let assertion =
+                        outcome.map_err(|code| Report::new(code).wrap_err(failed_command()))?;
+                    if let Some(actual) = assertion {
+                        println!("    Assertion failed, read {actual}");
+                        failed_assertions += 1;
                     }
                 }
 
+                if verify {
+                    println!("Reading back written values to verify...");
+                    let mut mismatch_count = 0;
+                    for ((address_space, addr, written), read) in
+                        read_back(&mut context, &self.commands).await??
+                    {
+                        if written != read {
+                            mismatch_count += 1;
+                            println!(
+                                "  Mismatch at {}: wrote {}, read {}",
+                                address_space.memory_address(addr),
+                                written.to_u16(),
+                                read.to_u16()
+                            );
+                        }
+                    }
+                    if mismatch_count > 0 {
+                        context.disconnect().await?;
+                        return Err(MacroFailure::Verification(mismatch_count).into());
+                    }
+                    println!("All written values verified.");
+                }
+
+                if failed_assertions > 0 {
+                    context.disconnect().await?;
+                    return Err(MacroFailure::Assertions(failed_assertions).into());
+                }
+
                 println!("Command-flow completed. Disconnecting from client...");
                 context.disconnect().await?;
             }
@@ -260,3 +936,297 @@ impl MagModCommandList {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{io::ErrorKind, path::PathBuf};
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("{name}_{}", std::process::id()))
+    }
+
+    async fn round_trip(command_list: &MagModCommandList, name: &str) -> MagModCommandList {
+        let path = temp_path(name);
+        command_list
+            .to_file(path.to_string_lossy().to_string(), true)
+            .await
+            .unwrap();
+
+        let file_path = path.with_extension("magmod");
+        let result = MagModCommandList::from_file(&file_path).await;
+        let _ = std::fs::remove_file(&file_path);
+        result.unwrap()
+    }
+
+    async fn read_bytes(bytes: &[u8], name: &str) -> std::io::Result<MagModCommandList> {
+        let file_path = temp_path(name).with_extension("magmod");
+        std::fs::write(&file_path, bytes).unwrap();
+        let result = MagModCommandList::from_file(&file_path).await;
+        let _ = std::fs::remove_file(&file_path);
+        result
+    }
+
+    fn mixed_commands() -> Vec<ModbusWriteCommand> {
+        vec![
+            (SelectedTopTab::Coils, 0, CellType::Coil(true)),
+            (SelectedTopTab::HoldingRegisters, 1, CellType::Word(0xBEEF)),
+            (SelectedTopTab::Coils, 65535, CellType::Coil(false)),
+            (SelectedTopTab::HoldingRegisters, 40000, CellType::Word(0)),
+            (SelectedTopTab::DiscreteInputs, 12, CellType::Coil(true)),
+            (SelectedTopTab::InputRegisters, 7, CellType::Word(65535)),
+        ]
+    }
+
+    fn valid_bytes() -> Vec<u8> {
+        let mut bytes = b"MAGMOD".to_vec();
+        bytes.extend_from_slice(&[4, 10, 0, 0, 1]); // 10.0.0.1
+        bytes.extend_from_slice(&502u16.to_be_bytes());
+        bytes.extend_from_slice(&1u32.to_be_bytes());
+        bytes.extend_from_slice(&[6, 0x00, 0x05, 0x12, 0x34]); // Holding register 6 = 0x1234
+        bytes
+    }
+
+    #[tokio::test]
+    async fn round_trip_ipv4() {
+        let command_list = MagModCommandList::new(
+            IpAddr::V4(Ipv4Addr::new(192, 168, 1, 20)),
+            502,
+            mixed_commands(),
+        );
+        assert_eq!(
+            round_trip(&command_list, "round_trip_ipv4").await,
+            command_list
+        );
+    }
+
+    #[tokio::test]
+    async fn round_trip_ipv6() {
+        let command_list = MagModCommandList::new(
+            IpAddr::V6(Ipv6Addr::new(0xfe80, 0, 0, 0, 0x1, 0x2, 0x3, 0x4)),
+            65535,
+            mixed_commands(),
+        );
+        assert_eq!(
+            round_trip(&command_list, "round_trip_ipv6").await,
+            command_list
+        );
+    }
+
+    #[tokio::test]
+    async fn round_trip_unit_id() {
+        let command_list = MagModCommandList::new(
+            IpAddr::V4(Ipv4Addr::new(192, 168, 1, 20)),
+            502,
+            mixed_commands(),
+        )
+        .with_unit_id(17);
+        assert_eq!(
+            round_trip(&command_list, "round_trip_unit_id").await,
+            command_list
+        );
+    }
+
+    #[tokio::test]
+    async fn reads_versioned_file() {
+        let mut bytes = valid_bytes();
+        bytes.insert(6, FORMAT_VERSION);
+        bytes.insert(14, 3);
+        let command_list = read_bytes(&bytes, "reads_versioned_file").await.unwrap();
+        assert_eq!(command_list.unit_id, 3);
+        assert_eq!(command_list.commands.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn reads_handwritten_file() {
+        let command_list = read_bytes(&valid_bytes(), "reads_handwritten_file")
+            .await
+            .unwrap();
+        assert_eq!(
+            command_list,
+            MagModCommandList::new(
+                IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1)),
+                502,
+                vec![(SelectedTopTab::HoldingRegisters, 5, CellType::Word(0x1234))],
+            )
+        );
+        assert_eq!(command_list.unit_id, DEFAULT_UNIT_ID);
+    }
+
+    #[tokio::test]
+    async fn rejects_bad_header() {
+        let mut bytes = valid_bytes();
+        bytes[..6].copy_from_slice(b"MAGMOB");
+        let error = read_bytes(&bytes, "rejects_bad_header").await.unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidData);
+    }
+
+    #[tokio::test]
+    async fn rejects_unknown_protocol() {
+        let mut bytes = valid_bytes();
+        bytes[6] = 5;
+        let error = read_bytes(&bytes, "rejects_unknown_protocol")
+            .await
+            .unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidData);
+    }
+
+    #[tokio::test]
+    async fn rejects_truncated_file() {
+        let bytes = valid_bytes();
+        for len in [3, 9, bytes.len() - 1] {
+            let error = read_bytes(&bytes[..len], "rejects_truncated_file")
+                .await
+                .unwrap_err();
+            assert_eq!(error.kind(), ErrorKind::UnexpectedEof);
+        }
+    }
+
+    fn local_list(port: u16, commands: Vec<ModbusWriteCommand>) -> MagModCommandList {
+        MagModCommandList::new(IpAddr::V4(Ipv4Addr::LOCALHOST), port, commands)
+    }
+
+    #[test]
+    fn resume_stays_within_the_macro() {
+        let commands = vec![
+            (SelectedTopTab::Coils, 0, CellType::Coil(true)),
+            (SelectedTopTab::Coils, 1, CellType::Coil(true)),
+        ];
+        assert!(
+            local_list(502, commands.clone())
+                .with_resume_from(2)
+                .is_ok()
+        );
+        assert!(local_list(502, commands).with_resume_from(3).is_err());
+    }
+
+    #[test]
+    fn merge_keeps_the_last_write() {
+        let first = local_list(
+            502,
+            vec![
+                (SelectedTopTab::HoldingRegisters, 10, CellType::Word(1)),
+                (SelectedTopTab::Coils, 3, CellType::Coil(true)),
+                (SelectedTopTab::HoldingRegisters, 10, CellType::Word(2)),
+                (SelectedTopTab::InputRegisters, 10, CellType::Word(7)),
+            ],
+        );
+        let second = local_list(
+            502,
+            vec![
+                (SelectedTopTab::Coils, 3, CellType::Coil(true)),
+                (SelectedTopTab::HoldingRegisters, 10, CellType::Word(5)),
+                (SelectedTopTab::InputRegisters, 10, CellType::Word(7)),
+            ],
+        );
+
+        let (merged, warnings) = MagModCommandList::merge(vec![first, second], None).unwrap();
+
+        assert_eq!(
+            merged,
+            local_list(
+                502,
+                vec![
+                    (SelectedTopTab::InputRegisters, 10, CellType::Word(7)),
+                    (SelectedTopTab::Coils, 3, CellType::Coil(true)),
+                    (SelectedTopTab::HoldingRegisters, 10, CellType::Word(5)),
+                    (SelectedTopTab::InputRegisters, 10, CellType::Word(7)),
+                ],
+            )
+        );
+        // Both earlier writes to holding register 10 differ, the repeated coil doesn't
+        assert_eq!(warnings.len(), 2);
+    }
+
+    #[test]
+    fn json_lists_supported_commands() {
+        assert_eq!(local_list(502, vec![]).to_json(), "[]");
+        assert_eq!(
+            local_list(
+                502,
+                vec![
+                    (SelectedTopTab::Coils, 0, CellType::Coil(true)),
+                    (SelectedTopTab::InputRegisters, 3, CellType::Coil(true)), // Unsupported
+                    (SelectedTopTab::HoldingRegisters, 65535, CellType::Word(40)),
+                ]
+            )
+            .to_json(),
+            "[{\"table\":\"coils\",\"address\":1,\"value\":true},\
+             {\"table\":\"holding_registers\",\"address\":65536,\"value\":40}]"
+        );
+    }
+
+    #[test]
+    fn targets_need_a_host_and_port() {
+        assert_eq!(
+            parse_targets("# Panels\n10.0.0.1:502\n\nplc-2.lan:1502\n[fe80::1]:502\n").unwrap(),
+            vec![
+                (String::from("10.0.0.1"), 502),
+                (String::from("plc-2.lan"), 1502),
+                (String::from("fe80::1"), 502),
+            ]
+        );
+        assert!(parse_targets("10.0.0.1").is_err());
+        assert!(parse_targets("fe80::1:502").is_err());
+        assert!(parse_targets(":502").is_err());
+        assert!(parse_targets("# Nothing yet\n").is_err());
+    }
+
+    #[test]
+    fn merge_rejects_different_targets() {
+        let lists = || vec![local_list(502, vec![]), local_list(503, vec![])];
+
+        assert!(MagModCommandList::merge(lists(), None).is_err());
+
+        let target = (IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1)), 1502);
+        let (merged, _) = MagModCommandList::merge(lists(), Some(target)).unwrap();
+        assert_eq!(merged, MagModCommandList::new(target.0, target.1, vec![]));
+    }
+
+    #[test]
+    fn exit_codes_follow_the_failure() {
+        let code = |report: Report| MacroExitCode::from_report(&report);
+        let refused = || std::io::Error::from(ErrorKind::ConnectionRefused);
+
+        assert_eq!(
+            code(MacroFailure::BadFile(std::io::Error::from(ErrorKind::InvalidData)).into()),
+            MacroExitCode::BadFile
+        );
+        assert_eq!(code(refused().into()), MacroExitCode::ConnectionRefused);
+        assert_eq!(
+            code(tokio_modbus::Error::Transport(std::io::Error::from(ErrorKind::TimedOut)).into()),
+            MacroExitCode::Timeout
+        );
+        // A command that ran out of retries keeps the cause under the index it failed at
+        assert_eq!(
+            code(
+                Report::new(tokio_modbus::Error::Transport(std::io::Error::from(
+                    ErrorKind::TimedOut
+                )))
+                .wrap_err("Command 3 of 5 failed")
+            ),
+            MacroExitCode::Timeout
+        );
+        assert_eq!(
+            code(ExceptionCode::IllegalDataAddress.into()),
+            MacroExitCode::ModbusException
+        );
+        assert_eq!(
+            code(MacroFailure::Verification(2).into()),
+            MacroExitCode::AssertionFailed
+        );
+        assert_eq!(code(eyre!("Nothing to merge")), MacroExitCode::Failure);
+    }
+
+    #[test]
+    fn progress_counts_from_one() {
+        assert_eq!(progress_prefix(0, 4, None), "[1/4 0%]");
+        assert_eq!(progress_prefix(2, 4, None), "[3/4 50%]");
+        assert_eq!(progress_prefix(0, 0, Some((Instant::now(), 0))), "[1/0 0%]");
+        assert!(progress_prefix(2, 4, Some((Instant::now(), 0))).starts_with("[3/4 50% ETA 0:0"));
+        assert_eq!(
+            progress_prefix(2, 4, Some((Instant::now(), 2))),
+            "[3/4 50%]"
+        );
+    }
+}
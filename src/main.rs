@@ -12,18 +12,55 @@
 //!    See the License for the specific language governing permissions and
 //!    limitations under the License.
 
+mod alarms;
 mod app;
 mod app_colors;
 mod app_table;
+mod capture;
 mod enums;
+mod keymap;
+mod labels;
+mod latency;
 mod macro_parser;
+mod metrics;
+mod mqtt;
+mod one_shot;
+mod poll;
+mod profile;
 mod queue;
+mod recent;
+mod scaling;
+mod server;
+mod settings;
 mod utils;
 
-use crate::{app::App, macro_parser::MagModCommandList};
+use crate::{
+    alarms::AlarmList,
+    app::App,
+    app_colors::ColorConfig,
+    capture::Snapshot,
+    enums::{Bell, CellType, SelectedTopTab, Theme, WordOrder},
+    labels::LabelMap,
+    macro_parser::{MacroExitCode, MacroFailure, MagModCommandList, targets_from_file},
+    metrics::MetricsServer,
+    mqtt::DEFAULT_MQTT_PORT,
+    poll::PollSchedule,
+    profile::DeviceProfile,
+    recent::RecentConnections,
+    scaling::ScalingMap,
+    server::MockServer,
+    settings::Settings,
+    utils::parse_u16_value,
+};
 use clap::{ArgGroup, Parser, Subcommand};
-use color_eyre::Result;
-use std::{net::IpAddr, path::PathBuf};
+use color_eyre::{Result, eyre::eyre};
+use std::{
+    net::{IpAddr, Ipv4Addr, SocketAddr},
+    path::PathBuf,
+    time::Duration,
+};
+
+const DEFAULT_SERVE_PORT: u16 = 5020;
 
 #[derive(Parser)]
 #[command(version, about, author)]
@@ -36,6 +73,70 @@ struct Cli {
     #[arg(short, long, value_parser, requires = "address")]
     /// Target port
     port: Option<u16>,
+    #[arg(long, value_parser)]
+    /// Device profile with names, types, scaling and read-only ranges ([table address] sections)
+    profile: Option<PathBuf>,
+    #[arg(long, value_parser)]
+    /// Engineering-units scaling map (table,address[-address],scale,offset[,unit])
+    scaling: Option<PathBuf>,
+    #[arg(long, value_parser)]
+    /// Register label map (table,address[-address],name)
+    labels: Option<PathBuf>,
+    #[arg(long, value_parser)]
+    /// Threshold alarms (table,address[-address],comparison,threshold)
+    alarms: Option<PathBuf>,
+    #[arg(long, value_parser)]
+    /// Read intervals for tick-refreshed tables and single addresses (table[,address[-address]],ms)
+    poll: Option<PathBuf>,
+    #[arg(long, value_enum)]
+    /// Color theme, per-tab follows the selected table (overrides the saved setting)
+    theme: Option<Theme>,
+    #[arg(long, value_parser)]
+    /// Color overrides applied on top of the theme (field,color)
+    colors: Option<PathBuf>,
+    #[arg(long, value_enum)]
+    /// Byte order of 32-bit values split across two registers, A is the most significant byte
+    /// (overrides the saved setting)
+    word_order: Option<WordOrder>,
+    #[arg(long, value_enum)]
+    /// Feedback for invalid input and alarms (overrides the saved setting)
+    bell: Option<Bell>,
+    #[arg(long)]
+    /// Read the first page of every table as soon as a connection is made
+    prefetch: bool,
+    #[arg(long)]
+    /// Read the current page whenever another table is selected
+    read_on_tab_switch: bool,
+    #[arg(long, value_parser = clap::value_parser!(u64).range(1..))]
+    /// Disconnect after this many seconds without a read or write
+    idle_timeout: Option<u64>,
+    #[arg(long, default_value_t = 255)]
+    /// Unit ID of the slave to target, 0 broadcasts writes to every slave and reads nothing
+    slave: u8,
+    #[arg(long)]
+    /// Quit immediately, even with queued changes that haven't been applied
+    no_confirm_quit: bool,
+    #[arg(long)]
+    /// Stop the cursor at the first and last address instead of wrapping around
+    no_wrap: bool,
+    #[arg(long, value_parser = parse_page_size)]
+    /// Fixed page shape as ROWSxCOLS (e.g. 16x8), instead of fitting the terminal
+    page_size: Option<(usize, usize)>,
+    #[arg(long, default_value_t = 1, value_parser = clap::value_parser!(u16).range(1..))]
+    /// Amount + and - add to or subtract from a holding register
+    step: u16,
+    #[arg(long, value_parser = parse_broker)]
+    /// Publish every value read to this MQTT broker (host[:port], port 1883 by default)
+    mqtt: Option<(String, u16)>,
+    #[arg(long, default_value = "magic_modbus", requires = "mqtt")]
+    /// Topic prefix for --mqtt, values go to <prefix>/<table>/<address>
+    mqtt_prefix: String,
+    #[arg(long, value_parser)]
+    /// Serve the values read as Prometheus gauges on http://<ADDRESS:PORT>/metrics
+    metrics: Option<SocketAddr>,
+    #[arg(long)]
+    /// Start an in-memory mock device (default 127.0.0.1:5020) and connect to it
+    serve: bool,
 }
 
 #[derive(Subcommand)]
@@ -50,8 +151,9 @@ enum Commands {
     ArgGroup::new("run_mode")
     .required(false)
     .multiple(false)
-    .args(["check_connection", "dry_run"])
+    .args(["check_connection", "dry_run", "dry_run_json"])
     ))]
+    #[command(after_help = MacroExitCode::HELP)]
     /// Access the macro parser
     ParseMacro {
         #[arg(short = 'M')]
@@ -66,9 +168,186 @@ enum Commands {
         #[arg(long = "dry-run")]
         /// Simulate a connection without actually doing anything
         dry_run: bool,
+        #[arg(long = "dry-run-json")]
+        /// Print the planned operations as JSON without connecting
+        dry_run_json: bool,
+        #[arg(long = "verify")]
+        /// Read every written value back and fail if the device disagrees
+        verify: bool,
+        #[arg(long)]
+        /// Unit ID of the slave to target, overriding the one saved in the macro
+        slave: Option<u8>,
+        #[arg(long, default_value_t = 0)]
+        /// Times to retry a command that timed out or lost the connection before giving up
+        retries: u32,
+        #[arg(long, default_value_t = 500)]
+        /// Milliseconds to wait before each retry
+        retry_delay: u64,
+        #[arg(long, value_parser = clap::value_parser!(u64).range(1..))]
+        /// Milliseconds to wait for each reply, by default as long as the connection allows
+        timeout: Option<u64>,
+        #[arg(long, default_value_t = 0)]
+        /// Skip this many commands, e.g. the ones a failed run already sent
+        resume_from: usize,
+        #[arg(long, conflicts_with = "macro_file_with_confirm")]
+        /// File of host:port lines, the macro runs on each device in turn instead of its target
+        targets: Option<PathBuf>,
+        #[arg(long, requires = "targets")]
+        /// Keep going to the next device when one fails
+        continue_on_error: bool,
+    },
+    /// Read a range of addresses and print them without starting the TUI
+    Read {
+        #[arg(short, long, value_parser)]
+        /// Target address
+        address: IpAddr,
+        #[arg(short, long, value_parser)]
+        /// Target port
+        port: u16,
+        #[arg(short, long, value_parser = parse_table)]
+        /// Table to read (coils, discrete_inputs, input_registers, holding_registers or 0x/1x/3x/4x)
+        table: SelectedTopTab,
+        #[arg(short, long, value_parser = parse_address)]
+        /// First address to read (1-65536)
+        start: u16,
+        #[arg(short, long, default_value_t = 1)]
+        /// Number of addresses to read
+        count: u16,
+    },
+    #[command(after_help = MacroExitCode::HELP)]
+    /// Read the addresses in a CSV of expected values and report any that differ
+    Verify {
+        #[arg(short, long, value_parser)]
+        /// Target address
+        address: IpAddr,
+        #[arg(short, long, value_parser)]
+        /// Target port
+        port: u16,
+        #[arg(value_parser)]
+        /// CSV of table,address,value lines, like a saved capture snapshot
+        expected: PathBuf,
+    },
+    #[command(group(
+    ArgGroup::new("run_mode")
+    .required(false)
+    .multiple(false)
+    .args(["check_connection", "dry_run", "dry_run_json"])
+    ))]
+    #[command(after_help = MacroExitCode::HELP)]
+    /// Write a single coil or holding register without a macro file
+    Write {
+        #[arg(short, long, value_parser)]
+        /// Target address
+        address: IpAddr,
+        #[arg(short, long, value_parser)]
+        /// Target port
+        port: u16,
+        #[arg(short, long, value_parser = parse_table)]
+        /// Table to write (coils or holding_registers)
+        table: SelectedTopTab,
+        #[arg(short = 'r', long, value_parser = parse_address)]
+        /// Address to write (1-65536)
+        register: u16,
+        #[arg(short, long)]
+        /// Value in decimal or 0x hex, coils also accept on/off and true/false
+        value: String,
+        #[arg(long = "check-connection")]
+        /// Check to see if `magic-modbus` can connect
+        check_connection: bool,
+        #[arg(long = "dry-run")]
+        /// Simulate a connection without actually doing anything
+        dry_run: bool,
+        #[arg(long = "dry-run-json")]
+        /// Print the planned operations as JSON without connecting
+        dry_run_json: bool,
+        #[arg(long = "verify")]
+        /// Read the written value back and fail if the device disagrees
+        verify: bool,
+        #[arg(long, default_value_t = 255)]
+        /// Unit ID of the slave to target
+        slave: u8,
+    },
+    /// Combine macro files into one, later files win when they write the same address
+    MergeMacros {
+        #[arg(required = true, num_args = 2..)]
+        /// Macro files to merge, in order
+        macro_files: Vec<PathBuf>,
+        #[arg(short, long)]
+        /// Name of the merged file, saved as <OUTPUT>.magmod in the current directory
+        output: String,
+        #[arg(short, long, value_parser, requires = "port")]
+        /// Target address for the merged macro, required if the files target different devices
+        address: Option<IpAddr>,
+        #[arg(short, long, value_parser, requires = "address")]
+        /// Target port for the merged macro
+        port: Option<u16>,
+        #[arg(short, long)]
+        /// Overwrite the output file if it exists
+        force: bool,
     },
 }
 
+fn parse_table(name: &str) -> Result<SelectedTopTab, String> {
+    SelectedTopTab::from_name(name).ok_or_else(|| format!("unknown table `{name}`"))
+}
+
+fn parse_write_value(table: SelectedTopTab, text: &str) -> Option<CellType> {
+    match table {
+        SelectedTopTab::Coils => match text.trim().to_lowercase().as_str() {
+            "on" | "true" => Some(CellType::Coil(true)),
+            "off" | "false" => Some(CellType::Coil(false)),
+            _ => match parse_u16_value(text)? {
+                0 => Some(CellType::Coil(false)),
+                1 => Some(CellType::Coil(true)),
+                _ => None,
+            },
+        },
+        SelectedTopTab::HoldingRegisters => parse_u16_value(text).map(CellType::Word),
+        SelectedTopTab::DiscreteInputs | SelectedTopTab::InputRegisters => None,
+    }
+}
+
+// 1-based on the command line, 0-based on the wire
+fn parse_address(text: &str) -> Result<u16, String> {
+    text.parse::<u32>()
+        .ok()
+        .and_then(|address| address.checked_sub(1))
+        .and_then(|address| u16::try_from(address).ok())
+        .ok_or_else(|| String::from("expected an address from 1 to 65536"))
+}
+
+// Scripts tell failures apart by the exit code, see MacroExitCode
+fn exit_on_failure(result: Result<()>) {
+    if let Err(report) = result {
+        eprintln!("Error: {report:?}");
+        std::process::exit(MacroExitCode::from_report(&report) as i32);
+    }
+}
+
+fn parse_page_size(text: &str) -> Result<(usize, usize), String> {
+    text.split_once(['x', 'X'])
+        .and_then(|(rows, cols)| Some((rows.trim().parse().ok()?, cols.trim().parse().ok()?)))
+        .filter(|&(rows, cols): &(usize, usize)| rows > 0 && cols > 0)
+        .ok_or_else(|| String::from("expected ROWSxCOLS, e.g. 16x8"))
+}
+
+// host or host:port, IPv6 literals need brackets when a port is given
+fn parse_broker(text: &str) -> Result<(String, u16), String> {
+    let text = text.trim();
+    let (host, port) = match text.rsplit_once(':') {
+        Some((host, port)) if !host.contains(':') || host.ends_with(']') => (
+            host,
+            port.parse::<u16>()
+                .map_err(|_| String::from("expected a port from 0 to 65535"))?,
+        ),
+        _ => (text, DEFAULT_MQTT_PORT),
+    };
+    match host.trim_start_matches('[').trim_end_matches(']') {
+        "" => Err(String::from("expected host[:port], e.g. broker.lan:1883")),
+        host => Ok((host.to_string(), port)),
+    }
+}
+
 #[tokio::main]
 async fn main() -> Result<()> {
     color_eyre::install()?;
@@ -80,27 +359,266 @@ async fn main() -> Result<()> {
             macro_file_no_confirm,
             check_connection,
             dry_run,
+            dry_run_json,
+            verify,
+            slave,
+            retries,
+            retry_delay,
+            timeout,
+            resume_from,
+            targets,
+            continue_on_error,
         }) => {
-            if let Some(file_path) = macro_file_with_confirm {
-                let mut command_list = MagModCommandList::from_file(file_path).await?;
-                command_list
-                    .run_macro(true, check_connection, dry_run)
-                    .await?;
+            let result: Result<()> = async move {
+                if let Some(file_path) = macro_file_with_confirm {
+                    let mut command_list = MagModCommandList::from_file(file_path)
+                        .await
+                        .map_err(MacroFailure::BadFile)?;
+                    if let Some(unit_id) = slave {
+                        command_list = command_list.with_unit_id(unit_id);
+                    }
+                    command_list = command_list
+                        .with_retries(retries, Duration::from_millis(retry_delay))
+                        .with_timeout(timeout.map(Duration::from_millis))
+                        .with_resume_from(resume_from)?;
+                    match dry_run_json {
+                        true => println!("{}", command_list.to_json()),
+                        false => {
+                            command_list
+                                .run_macro(true, check_connection, dry_run, verify)
+                                .await?
+                        }
+                    }
+                }
+
+                if let Some(file_path) = macro_file_no_confirm {
+                    let mut command_list = MagModCommandList::from_file(file_path)
+                        .await
+                        .map_err(MacroFailure::BadFile)?;
+                    if let Some(unit_id) = slave {
+                        command_list = command_list.with_unit_id(unit_id);
+                    }
+                    command_list = command_list
+                        .with_retries(retries, Duration::from_millis(retry_delay))
+                        .with_timeout(timeout.map(Duration::from_millis))
+                        .with_resume_from(resume_from)?;
+                    let targets = match targets {
+                        Some(file_path) => Some(
+                            targets_from_file(file_path)
+                                .await
+                                .map_err(MacroFailure::BadFile)?,
+                        ),
+                        None => None,
+                    };
+                    match (dry_run_json, targets) {
+                        (true, _) => println!("{}", command_list.to_json()),
+                        (false, Some(targets)) => {
+                            command_list
+                                .run_on_targets(
+                                    &targets,
+                                    check_connection,
+                                    dry_run,
+                                    verify,
+                                    continue_on_error,
+                                )
+                                .await?
+                        }
+                        (false, None) => {
+                            command_list
+                                .run_macro(false, check_connection, dry_run, verify)
+                                .await?
+                        }
+                    }
+                }
+                Ok(())
+            }
+            .await;
+            exit_on_failure(result);
+        }
+        Some(Commands::Read {
+            address,
+            port,
+            table,
+            start,
+            count,
+        }) => {
+            if count == 0 || start as u32 + count as u32 > u16::MAX as u32 + 1 {
+                return Err(eyre!("The range must stay within addresses 1-65536"));
+            }
+            one_shot::read(SocketAddr::new(address, port), table, start, count).await?;
+        }
+        Some(Commands::Verify {
+            address,
+            port,
+            expected,
+        }) => {
+            let result = async {
+                let expected = Snapshot::from_file(&expected.to_string_lossy())
+                    .await
+                    .map_err(MacroFailure::BadFile)?;
+                one_shot::verify(SocketAddr::new(address, port), &expected).await
+            }
+            .await;
+            exit_on_failure(result);
+        }
+        Some(Commands::Write {
+            address,
+            port,
+            table,
+            register,
+            value,
+            check_connection,
+            dry_run,
+            dry_run_json,
+            verify,
+            slave,
+        }) => {
+            let Some(content) = parse_write_value(table, &value) else {
+                return Err(eyre!("`{value}` can't be written to {table}"));
+            };
+            // A one command macro, so it shares the macro runner's connect and write path
+            let mut command_list =
+                MagModCommandList::new(address, port, vec![(table, register, content)])
+                    .with_unit_id(slave);
+            match dry_run_json {
+                true => println!("{}", command_list.to_json()),
+                false => exit_on_failure(
+                    command_list
+                        .run_macro(false, check_connection, dry_run, verify)
+                        .await,
+                ),
+            }
+        }
+        Some(Commands::MergeMacros {
+            macro_files,
+            output,
+            address,
+            port,
+            force,
+        }) => {
+            let mut command_lists = Vec::with_capacity(macro_files.len());
+            for file_path in macro_files {
+                command_lists.push(MagModCommandList::from_file(file_path).await?);
             }
 
-            if let Some(file_path) = macro_file_no_confirm {
-                let mut command_list = MagModCommandList::from_file(file_path).await?;
-                command_list
-                    .run_macro(false, check_connection, dry_run)
-                    .await?;
+            let (merged, warnings) = MagModCommandList::merge(command_lists, address.zip(port))?;
+            for warning in &warnings {
+                println!("Warning: {warning}");
             }
+
+            let output = output.trim_end_matches(".magmod").to_string();
+            merged.to_file(output.clone(), force).await?;
+            println!("Merged macro saved to {output}.magmod");
         }
         None => {
+            let mut profile = match cli.profile {
+                Some(file_path) => DeviceProfile::from_file(file_path).await?,
+                None => DeviceProfile::default(),
+            };
+            // The profile comes first so --scaling and --labels can override it
+            let mut scaling = std::mem::take(&mut profile.scaling);
+            if let Some(file_path) = cli.scaling {
+                scaling.extend(ScalingMap::from_file(file_path).await?);
+            }
+            let mut labels = std::mem::take(&mut profile.labels);
+            if let Some(file_path) = cli.labels {
+                labels.extend(LabelMap::from_file(file_path).await?);
+            }
+            let alarms = match cli.alarms {
+                Some(file_path) => AlarmList::from_file(file_path).await?,
+                None => AlarmList::default(),
+            };
+            let poll_schedule = match cli.poll {
+                Some(file_path) => PollSchedule::from_file(file_path).await?,
+                None => PollSchedule::default(),
+            };
+            let colors = match cli.colors {
+                Some(file_path) => ColorConfig::from_file(file_path).await?,
+                None => ColorConfig::default(),
+            };
+
+            let recent_connections = match RecentConnections::default_path() {
+                Some(file_path) => RecentConnections::from_file(file_path).await?,
+                None => RecentConnections::default(),
+            };
+
+            // Flags only change this run, the file keeps what the settings popup last saved
+            let settings_path = Settings::default_path();
+            let saved_settings = match &settings_path {
+                Some(file_path) => Settings::from_file(file_path).await,
+                None => Settings::default(),
+            };
+            let mut settings = saved_settings;
+            if let Some(theme) = cli.theme {
+                settings.theme = theme;
+            }
+            if let Some(word_order) = cli.word_order {
+                settings.word_order = word_order;
+            }
+            if let Some(bell) = cli.bell {
+                settings.bell = bell;
+            }
+            if cli.prefetch {
+                settings.prefetch_on_connect = true;
+            }
+            if cli.read_on_tab_switch {
+                settings.read_on_tab_switch = true;
+            }
+            if cli.no_confirm_quit {
+                settings.confirm_quit = false;
+            }
+            if cli.no_wrap {
+                settings.wrap_around = false;
+            }
+
+            let (address, port) = match cli.serve {
+                true => {
+                    let server = MockServer::bind(SocketAddr::new(
+                        cli.address.unwrap_or(IpAddr::V4(Ipv4Addr::LOCALHOST)),
+                        cli.port.unwrap_or(DEFAULT_SERVE_PORT),
+                    ))
+                    .await?;
+                    let server_addr = server.local_addr()?;
+                    tokio::spawn(server.run());
+                    (Some(server_addr.ip()), Some(server_addr.port()))
+                }
+                false => (cli.address, cli.port),
+            };
+
+            // Bound before the terminal is taken over, so a port in use is reported plainly
+            let known_values = match cli.metrics {
+                Some(metrics_addr) => {
+                    let server = MetricsServer::bind(metrics_addr).await?;
+                    let known_values = server.values();
+                    tokio::spawn(server.run());
+                    Some(known_values)
+                }
+                None => None,
+            };
+
             let mut terminal = ratatui::init();
 
-            App::new().run(&mut terminal, cli.address, cli.port).await?;
+            // Restored before any error is printed, or the report would land in raw mode
+            let result = App::new()
+                .with_scaling(scaling)
+                .with_labels(labels)
+                .with_profile(profile)
+                .with_alarms(alarms)
+                .with_poll_schedule(poll_schedule)
+                .with_mqtt(cli.mqtt, cli.mqtt_prefix)
+                .with_metrics(known_values)
+                .with_settings(settings, saved_settings, settings_path)
+                .with_idle_timeout(cli.idle_timeout.map(Duration::from_secs))
+                .with_unit_id(cli.slave)
+                .with_page_size(cli.page_size)
+                .with_nudge_step(cli.step)
+                .with_colors(colors)
+                .with_recent_connections(recent_connections)
+                .run(&mut terminal, address, port)
+                .await;
 
             ratatui::restore();
+            result?;
         }
     }
 
@@ -0,0 +1,158 @@
+//!   Copyright 2025 Isaac Schlaegel
+//!
+//!    Licensed under the Apache License, Version 2.0 (the "License");
+//!    you may not use this file except in compliance with the License.
+//!    You may obtain a copy of the License at
+//!
+//!        http://www.apache.org/licenses/LICENSE-2.0
+//!
+//!    Unless required by applicable law or agreed to in writing, software
+//!    distributed under the License is distributed on an "AS IS" BASIS,
+//!    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//!    See the License for the specific language governing permissions and
+//!    limitations under the License.
+
+use crate::enums::SelectedTopTab;
+use std::{
+    collections::BTreeMap,
+    io,
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+};
+
+const MAX_REQUEST_BYTES: usize = 8192; // Anything longer than a plain GET is refused
+
+// Last value read at each address, written by the app and read by the exporter
+pub type KnownValues = Arc<Mutex<BTreeMap<(SelectedTopTab, u16), u16>>>;
+
+// Serves the known values as Prometheus gauges on GET /metrics
+pub struct MetricsServer {
+    listener: TcpListener,
+    values: KnownValues,
+}
+
+impl MetricsServer {
+    pub async fn bind(addr: SocketAddr) -> io::Result<Self> {
+        Ok(Self {
+            listener: TcpListener::bind(addr).await?,
+            values: KnownValues::default(),
+        })
+    }
+
+    #[cfg(test)]
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    pub fn values(&self) -> KnownValues {
+        self.values.clone()
+    }
+
+    pub async fn run(self) -> io::Result<()> {
+        loop {
+            let (stream, _) = self.listener.accept().await?;
+            let values = self.values.clone();
+            // A scraper that hangs up early only ends its own connection
+            tokio::spawn(async move {
+                let _ = respond(stream, values).await;
+            });
+        }
+    }
+}
+
+async fn respond(mut stream: TcpStream, values: KnownValues) -> io::Result<()> {
+    let mut request = vec![];
+    let mut buf = [0; 1024];
+    while !request.windows(4).any(|window| window == b"\r\n\r\n") {
+        let read = stream.read(&mut buf).await?;
+        if read == 0 || request.len() + read > MAX_REQUEST_BYTES {
+            return Ok(());
+        }
+        request.extend_from_slice(&buf[..read]);
+    }
+
+    let request_line = String::from_utf8_lossy(&request);
+    let mut parts = request_line.split_whitespace();
+    let (status, body) = match (parts.next(), parts.next()) {
+        (Some("GET"), Some("/metrics")) => {
+            let values = values
+                .lock()
+                .map_err(|_| io::Error::other("values lock poisoned"))?;
+            ("200 OK", render(&values))
+        }
+        _ => ("404 Not Found", String::from("Try /metrics\n")),
+    };
+
+    let response = format!(
+        "HTTP/1.1 {status}\r\n\
+         Content-Type: text/plain; version=0.0.4\r\n\
+         Content-Length: {}\r\n\
+         Connection: close\r\n\r\n{body}",
+        body.len()
+    );
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown().await
+}
+
+// Prometheus text format, labeled with the table and 1-based address
+pub fn render(values: &BTreeMap<(SelectedTopTab, u16), u16>) -> String {
+    let mut text = String::from(
+        "# HELP magic_modbus_value Last value read from the device\n\
+         # TYPE magic_modbus_value gauge\n",
+    );
+    for ((table, address), value) in values {
+        text.push_str(&format!(
+            "magic_modbus_value{{table=\"{}\",address=\"{}\"}} {value}\n",
+            table.to_string().to_lowercase().replace(' ', "_"),
+            *address as u32 + 1
+        ));
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::{IpAddr, Ipv4Addr};
+
+    async fn get(addr: SocketAddr, path: &str) -> String {
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        stream
+            .write_all(format!("GET {path} HTTP/1.1\r\nHost: test\r\n\r\n").as_bytes())
+            .await
+            .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        response
+    }
+
+    #[tokio::test]
+    async fn known_values_are_served_as_gauges() {
+        let server = MetricsServer::bind(SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 0))
+            .await
+            .unwrap();
+        let addr = server.local_addr().unwrap();
+        let values = server.values();
+        tokio::spawn(server.run());
+
+        values
+            .lock()
+            .unwrap()
+            .insert((SelectedTopTab::HoldingRegisters, 39), 1234);
+        values.lock().unwrap().insert((SelectedTopTab::Coils, 0), 1);
+
+        let response = get(addr, "/metrics").await;
+        assert!(response.starts_with("HTTP/1.1 200 OK"));
+        assert!(response.contains("magic_modbus_value{table=\"coils\",address=\"1\"} 1\n"));
+        assert!(
+            response
+                .contains("magic_modbus_value{table=\"holding_registers\",address=\"40\"} 1234\n")
+        );
+
+        assert!(get(addr, "/").await.starts_with("HTTP/1.1 404"));
+    }
+}
@@ -0,0 +1,231 @@
+//!   Copyright 2025 Isaac Schlaegel
+//!
+//!    Licensed under the Apache License, Version 2.0 (the "License");
+//!    you may not use this file except in compliance with the License.
+//!    You may obtain a copy of the License at
+//!
+//!        http://www.apache.org/licenses/LICENSE-2.0
+//!
+//!    Unless required by applicable law or agreed to in writing, software
+//!    distributed under the License is distributed on an "AS IS" BASIS,
+//!    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//!    See the License for the specific language governing permissions and
+//!    limitations under the License.
+
+use crate::{
+    enums::{Action, SelectedTopTab},
+    utils::resolve_host,
+};
+use std::{
+    io::{Error, ErrorKind},
+    time::Duration,
+};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpStream,
+    sync::mpsc::{self, Sender},
+};
+
+pub const DEFAULT_MQTT_PORT: u16 = 1883;
+const QUEUE_LENGTH: usize = 1000; // Reads waiting to be published, newer ones are dropped past this
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5); // For the dial and the CONNACK alike
+
+// Publishes every value read to {prefix}/{table}/{address} on an MQTT 3.1.1 broker, QoS 0 and
+// retained so a new subscriber sees the last value straight away
+pub struct MqttPublisher {
+    sender: Sender<(SelectedTopTab, u16, u16)>, // Table, Address, Value
+}
+
+impl MqttPublisher {
+    pub fn spawn(broker: String, port: u16, prefix: String, ui_tx: Sender<Action>) -> Self {
+        let (sender, mut receiver) = mpsc::channel::<(SelectedTopTab, u16, u16)>(QUEUE_LENGTH);
+        let client_id = format!("magic_modbus_{}", std::process::id());
+
+        tokio::spawn(async move {
+            let mut reported = false; // Only the first failure in a row is logged
+            loop {
+                let mut stream = match connect(&broker, port, &client_id, CONNECT_TIMEOUT).await {
+                    Ok(stream) => {
+                        reported = false;
+                        let message = format!("MQTT connected to {broker}:{port}");
+                        let _ = ui_tx.send(Action::Log(message)).await;
+                        stream
+                    }
+                    Err(err) => {
+                        if !reported {
+                            reported = true;
+                            let message = format!("MQTT couldn't reach {broker}:{port}: {err}");
+                            let _ = ui_tx.send(Action::Log(message)).await;
+                        }
+                        tokio::time::sleep(RECONNECT_DELAY).await;
+                        continue;
+                    }
+                };
+
+                loop {
+                    let Some((table, address, value)) = receiver.recv().await else {
+                        return;
+                    };
+                    let packet = publish_packet(
+                        &topic(&prefix, table, address),
+                        value.to_string().as_bytes(),
+                    );
+                    if let Err(err) = stream.write_all(&packet).await {
+                        let message = format!("MQTT lost {broker}:{port}: {err}");
+                        let _ = ui_tx.send(Action::Log(message)).await;
+                        break;
+                    }
+                }
+            }
+        });
+
+        Self { sender }
+    }
+
+    // Never waits, values are dropped while the broker is slow or unreachable
+    pub fn publish(&self, table: SelectedTopTab, address: u16, value: u16) {
+        let _ = self.sender.try_send((table, address, value));
+    }
+}
+
+// Addresses are 1-based, like everywhere else a user sees them
+pub fn topic(prefix: &str, table: SelectedTopTab, address: u16) -> String {
+    format!(
+        "{}/{}/{}",
+        prefix.trim_end_matches('/'),
+        table.to_string().to_lowercase().replace(' ', "_"),
+        address as u32 + 1
+    )
+}
+
+// A broker that takes the connection but never answers fails like an unreachable one, so
+// the publisher goes back to retrying instead of waiting on it forever
+async fn connect(
+    broker: &str,
+    port: u16,
+    client_id: &str,
+    timeout: Duration,
+) -> std::io::Result<TcpStream> {
+    let timed_out = |_| Error::new(ErrorKind::TimedOut, "the broker didn't answer in time");
+    let addr = resolve_host(broker, port).await?;
+    let mut stream = tokio::time::timeout(timeout, TcpStream::connect(addr))
+        .await
+        .map_err(timed_out)??;
+    stream.write_all(&connect_packet(client_id)).await?;
+
+    let mut connack = [0; 4];
+    tokio::time::timeout(timeout, stream.read_exact(&mut connack))
+        .await
+        .map_err(timed_out)??;
+    match connack {
+        [0x20, 0x02, _, 0] => Ok(stream),
+        [0x20, 0x02, _, code] => Err(Error::new(
+            ErrorKind::ConnectionRefused,
+            format!("the broker refused the connection (return code {code})"),
+        )),
+        _ => Err(Error::new(
+            ErrorKind::InvalidData,
+            "the reply wasn't an MQTT CONNACK",
+        )),
+    }
+}
+
+// Clean session, no credentials, and a keep alive of 0 so no pings are needed
+fn connect_packet(client_id: &str) -> Vec<u8> {
+    let mut body = vec![];
+    push_string(&mut body, "MQTT");
+    body.extend_from_slice(&[0x04, 0x02, 0x00, 0x00]); // Level 4 (3.1.1), flags, keep alive
+    push_string(&mut body, client_id);
+    packet(0x10, body)
+}
+
+fn publish_packet(topic: &str, payload: &[u8]) -> Vec<u8> {
+    let mut body = vec![];
+    push_string(&mut body, topic);
+    body.extend_from_slice(payload);
+    packet(0x31, body) // PUBLISH, QoS 0, retained
+}
+
+fn packet(header: u8, body: Vec<u8>) -> Vec<u8> {
+    let mut packet = vec![header];
+    // Remaining length, 7 bits per byte with the high bit marking another byte
+    let mut length = body.len();
+    loop {
+        let byte = (length % 128) as u8;
+        length /= 128;
+        match length > 0 {
+            true => packet.push(byte | 0x80),
+            false => {
+                packet.push(byte);
+                break;
+            }
+        }
+    }
+    packet.extend(body);
+    packet
+}
+
+fn push_string(bytes: &mut Vec<u8>, text: &str) {
+    bytes.extend_from_slice(&(text.len() as u16).to_be_bytes());
+    bytes.extend_from_slice(text.as_bytes());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::net::TcpListener;
+
+    #[test]
+    fn packets_follow_mqtt_3_1_1() {
+        assert_eq!(
+            connect_packet("mm"),
+            [
+                0x10, 14, 0, 4, b'M', b'Q', b'T', b'T', 4, 2, 0, 0, 0, 2, b'm', b'm'
+            ]
+        );
+        assert_eq!(
+            publish_packet("a/b", b"42"),
+            [0x31, 7, 0, 3, b'a', b'/', b'b', b'4', b'2']
+        );
+        // Bodies of 128 bytes and up take a second length byte
+        assert_eq!(packet(0x31, vec![0; 200])[..3], [0x31, 0xC8, 0x01]);
+        assert_eq!(
+            topic("plant/", SelectedTopTab::HoldingRegisters, 39),
+            "plant/holding_registers/40"
+        );
+    }
+
+    #[tokio::test]
+    async fn values_are_published_after_connecting() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let (ui_tx, mut ui_rx) = mpsc::channel(10);
+        let publisher =
+            MqttPublisher::spawn(String::from("127.0.0.1"), port, String::from("mm"), ui_tx);
+
+        let (mut broker, _) = listener.accept().await.unwrap();
+        let mut header = [0; 2];
+        broker.read_exact(&mut header).await.unwrap();
+        let mut connect = vec![0; header[1] as usize];
+        broker.read_exact(&mut connect).await.unwrap();
+        broker.write_all(&[0x20, 0x02, 0x00, 0x00]).await.unwrap();
+        assert!(matches!(ui_rx.recv().await, Some(Action::Log(_))));
+
+        publisher.publish(SelectedTopTab::Coils, 0, 1);
+        let expected = publish_packet("mm/coils/1", b"1");
+        let mut published = vec![0; expected.len()];
+        broker.read_exact(&mut published).await.unwrap();
+        assert_eq!(published, expected);
+    }
+
+    #[tokio::test]
+    async fn silent_brokers_time_out() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+
+        // The dial completes in the listener's backlog, but nothing ever answers it
+        let result = connect("127.0.0.1", port, "mm", Duration::from_millis(100)).await;
+        assert_eq!(result.unwrap_err().kind(), ErrorKind::TimedOut);
+    }
+}
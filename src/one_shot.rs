@@ -0,0 +1,157 @@
+//!   Copyright 2025 Isaac Schlaegel
+//!
+//!    Licensed under the Apache License, Version 2.0 (the "License");
+//!    you may not use this file except in compliance with the License.
+//!    You may obtain a copy of the License at
+//!
+//!        http://www.apache.org/licenses/LICENSE-2.0
+//!
+//!    Unless required by applicable law or agreed to in writing, software
+//!    distributed under the License is distributed on an "AS IS" BASIS,
+//!    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//!    See the License for the specific language governing permissions and
+//!    limitations under the License.
+
+use crate::{
+    capture::Snapshot, enums::SelectedTopTab, macro_parser::MacroFailure, poll::coalesce,
+    utils::read_tables,
+};
+use color_eyre::eyre::eyre;
+use std::{collections::BTreeMap, net::SocketAddr};
+use tokio_modbus::client::{Client, tcp};
+
+// Prints one address per line: memory address, decimal, hex
+// Addresses the device rejected are skipped and reported in the error
+pub async fn read(
+    socket_addr: SocketAddr,
+    table: SelectedTopTab,
+    start: u16,
+    count: u16,
+) -> color_eyre::Result<()> {
+    let mut context = tcp::connect(socket_addr).await?;
+    let (values, mut exceptions, rejected) =
+        read_tables(&mut context, vec![(table, start, count)]).await?;
+    context.disconnect().await?;
+
+    for (table, address, content) in values {
+        let value = content.to_u16();
+        println!("{} {value:>5} 0x{value:04X}", table.memory_address(address));
+    }
+
+    for (table, start, count) in rejected {
+        exceptions.push(format!(
+            "{table}: Illegal data address {}-{}",
+            table.memory_address(start),
+            table.memory_address(start + (count - 1))
+        ));
+    }
+
+    match exceptions.is_empty() {
+        true => Ok(()),
+        false => Err(eyre!("Modbus Error: {}", exceptions.join(" | "))),
+    }
+}
+
+// Reads every expected address and prints a PASS or FAIL row for each. Any mismatch or
+// address the device rejected fails like a macro assertion, so scripts get the same exit code
+pub async fn verify(socket_addr: SocketAddr, expected: &Snapshot) -> color_eyre::Result<()> {
+    let commands = coalesce(
+        expected
+            .values()
+            .map(|(table, address, _)| (table, address, 1))
+            .collect(),
+    );
+    let mut context = tcp::connect(socket_addr).await?;
+    let (values, _, _) = read_tables(&mut context, commands).await?;
+    context.disconnect().await?;
+    let live: BTreeMap<(SelectedTopTab, u16), u16> = values
+        .into_iter()
+        .map(|(table, address, content)| ((table, address), content.to_u16()))
+        .collect();
+
+    let mut failed = 0;
+    println!("Result  Address  Expected    Actual");
+    for (table, address, expected) in expected.values() {
+        let (result, actual) = match live.get(&(table, address)) {
+            Some(&actual) if actual == expected => ("PASS", actual.to_string()),
+            Some(&actual) => ("FAIL", actual.to_string()),
+            None => ("FAIL", String::from("rejected")),
+        };
+        if result == "FAIL" {
+            failed += 1;
+        }
+        println!(
+            "{result:<7} {} {expected:>9} {actual:>9}",
+            table.memory_address(address)
+        );
+    }
+
+    let total = expected.values().count();
+    println!("{} passed, {failed} failed", total - failed);
+    match failed {
+        0 => Ok(()),
+        failed => Err(MacroFailure::Assertions(failed).into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::server::MockServer;
+    use std::net::{IpAddr, Ipv4Addr};
+
+    #[tokio::test]
+    async fn read_fails_past_the_device_end() {
+        let server = MockServer::bind(SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 0))
+            .await
+            .unwrap();
+        let addr = server.local_addr().unwrap();
+        server
+            .registers()
+            .lock()
+            .unwrap()
+            .input_registers
+            .truncate(10);
+        tokio::spawn(server.run());
+
+        assert!(
+            read(addr, SelectedTopTab::InputRegisters, 0, 10)
+                .await
+                .is_ok()
+        );
+        assert!(
+            read(addr, SelectedTopTab::InputRegisters, 5, 10)
+                .await
+                .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn verify_fails_on_any_mismatch() {
+        let server = MockServer::bind(SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 0))
+            .await
+            .unwrap();
+        let addr = server.local_addr().unwrap();
+        {
+            let registers = server.registers();
+            let mut registers = registers.lock().unwrap();
+            registers.coils[0] = true;
+            registers.holding_registers[39] = 1234;
+        }
+        tokio::spawn(server.run());
+
+        let expected = |text| Snapshot::parse("expected.csv", text).unwrap();
+        assert!(
+            verify(addr, &expected("coils,1,1\nholding_registers,40,1234\n"))
+                .await
+                .is_ok()
+        );
+        let report = verify(addr, &expected("coils,1,0\nholding_registers,40,0x04D2\n"))
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            report.downcast_ref::<MacroFailure>(),
+            Some(MacroFailure::Assertions(1))
+        ));
+    }
+}
@@ -0,0 +1,224 @@
+//!   Copyright 2025 Isaac Schlaegel
+//!
+//!    Licensed under the Apache License, Version 2.0 (the "License");
+//!    you may not use this file except in compliance with the License.
+//!    You may obtain a copy of the License at
+//!
+//!        http://www.apache.org/licenses/LICENSE-2.0
+//!
+//!    Unless required by applicable law or agreed to in writing, software
+//!    distributed under the License is distributed on an "AS IS" BASIS,
+//!    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//!    See the License for the specific language governing permissions and
+//!    limitations under the License.
+
+use crate::{
+    enums::SelectedTopTab,
+    utils::{ModbusReadCommand, invalid_line, parse_address_range},
+};
+use std::{
+    path::Path,
+    time::{Duration, Instant},
+};
+use tokio::fs;
+
+pub const POLL_RESOLUTION: Duration = Duration::from_millis(100); // How often the schedule is checked
+const DEFAULT_TABLE_INTERVAL: Duration = Duration::from_secs(1); // Tick refresh without a rate
+
+struct PollRange {
+    table: SelectedTopTab,
+    start: u16,
+    end: u16,
+    interval: Duration,
+    next_due: Option<Instant>, // None until first read
+}
+
+// When tick-refreshed tables and individual addresses are next read
+#[derive(Default)]
+pub struct PollSchedule {
+    ranges: Vec<PollRange>,
+    table_intervals: [Option<Duration>; 4], // Indexed by table
+    table_due: [Option<Instant>; 4],
+}
+
+impl PollSchedule {
+    pub async fn from_file<P: AsRef<Path>>(filename: P) -> std::io::Result<Self> {
+        Self::parse(&fs::read_to_string(filename).await?)
+    }
+
+    // Line format: table,milliseconds or table,address[-address],milliseconds
+    // The first sets how often a table with tick refresh on is read, the second reads the
+    // addresses at that rate whenever connected. Addresses are 1-based.
+    pub fn parse(text: &str) -> std::io::Result<Self> {
+        let mut schedule = Self::default();
+
+        for (index, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+            if !(2..=3).contains(&fields.len()) {
+                return Err(invalid_line(
+                    index,
+                    "Expected table,milliseconds or table,address,milliseconds",
+                ));
+            }
+
+            let table = SelectedTopTab::from_name(fields[0])
+                .ok_or_else(|| invalid_line(index, "Unknown table"))?;
+            let interval = fields[fields.len() - 1]
+                .parse::<u64>()
+                .ok()
+                .map(Duration::from_millis)
+                .filter(|interval| *interval >= POLL_RESOLUTION)
+                .ok_or_else(|| invalid_line(index, "Interval must be at least 100 ms"))?;
+
+            match fields.len() {
+                2 => schedule.table_intervals[table as usize] = Some(interval),
+                _ => {
+                    let (start, end) = parse_address_range(fields[1])
+                        .ok_or_else(|| invalid_line(index, "Invalid address range"))?;
+                    schedule.ranges.push(PollRange {
+                        table,
+                        start,
+                        end,
+                        interval,
+                        next_due: None,
+                    });
+                }
+            }
+        }
+
+        Ok(schedule)
+    }
+
+    pub fn table_interval(&self, table: SelectedTopTab) -> Duration {
+        self.table_intervals[table as usize].unwrap_or(DEFAULT_TABLE_INTERVAL)
+    }
+
+    // True once per interval, the caller reads the table's page when it is
+    pub fn table_due(&mut self, table: SelectedTopTab, now: Instant) -> bool {
+        let interval = self.table_interval(table);
+        let next_due = &mut self.table_due[table as usize];
+        match next_due.is_none_or(|due| due <= now) {
+            true => {
+                *next_due = Some(now + interval);
+                true
+            }
+            false => false,
+        }
+    }
+
+    // Every scheduled range whose interval has passed, rescheduled from now
+    pub fn due_ranges(&mut self, now: Instant) -> Vec<ModbusReadCommand> {
+        let mut commands = vec![];
+        for range in &mut self.ranges {
+            if range.next_due.is_none_or(|due| due <= now) {
+                range.next_due = Some(now + range.interval);
+                commands.push((range.table, range.start, range.end - range.start + 1));
+            }
+        }
+        commands
+    }
+}
+
+// Overlapping and neighbouring ranges of a table become one read, split later by read_chunks
+pub fn coalesce(mut commands: Vec<ModbusReadCommand>) -> Vec<ModbusReadCommand> {
+    commands.sort();
+    let mut merged: Vec<(SelectedTopTab, u16, u32)> = vec![]; // Exclusive end, so 65536 fits
+    for (table, start, count) in commands {
+        let end = start as u32 + count as u32;
+        match merged.last_mut() {
+            Some((last_table, _, last_end))
+                if *last_table == table && start as u32 <= *last_end =>
+            {
+                *last_end = (*last_end).max(end);
+            }
+            _ => merged.push((table, start, end)),
+        }
+    }
+    merged
+        .into_iter()
+        .map(|(table, start, end)| {
+            (
+                table,
+                start,
+                (end - start as u32).min(u16::MAX as u32) as u16,
+            )
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ranges_are_due_at_their_own_rate() {
+        let mut schedule = PollSchedule::parse(
+            "holding_registers,250\n\
+             input_registers,1-4,200\n\
+             input_registers,100,1000\n",
+        )
+        .unwrap();
+        let start = Instant::now();
+
+        assert_eq!(
+            schedule.due_ranges(start),
+            vec![
+                (SelectedTopTab::InputRegisters, 0, 4),
+                (SelectedTopTab::InputRegisters, 99, 1)
+            ]
+        );
+        assert!(
+            schedule
+                .due_ranges(start + Duration::from_millis(100))
+                .is_empty()
+        );
+        assert_eq!(
+            schedule.due_ranges(start + Duration::from_millis(200)),
+            vec![(SelectedTopTab::InputRegisters, 0, 4)]
+        );
+
+        assert!(schedule.table_due(SelectedTopTab::HoldingRegisters, start));
+        assert!(!schedule.table_due(SelectedTopTab::HoldingRegisters, start));
+        assert!(schedule.table_due(
+            SelectedTopTab::HoldingRegisters,
+            start + Duration::from_millis(250)
+        ));
+        assert_eq!(
+            schedule.table_interval(SelectedTopTab::Coils),
+            DEFAULT_TABLE_INTERVAL
+        );
+    }
+
+    #[test]
+    fn intervals_below_the_resolution_are_rejected() {
+        assert!(PollSchedule::parse("coils,50").is_err());
+        assert!(PollSchedule::parse("coils,1-2,abc").is_err());
+    }
+
+    #[test]
+    fn neighbouring_reads_are_merged() {
+        let commands = coalesce(vec![
+            (SelectedTopTab::HoldingRegisters, 10, 5),
+            (SelectedTopTab::Coils, 0, 8),
+            (SelectedTopTab::HoldingRegisters, 0, 10),
+            (SelectedTopTab::HoldingRegisters, 12, 10),
+            (SelectedTopTab::HoldingRegisters, 30, 1),
+            (SelectedTopTab::Coils, 65535, 1),
+        ]);
+
+        assert_eq!(
+            commands,
+            vec![
+                (SelectedTopTab::Coils, 0, 8),
+                (SelectedTopTab::Coils, 65535, 1),
+                (SelectedTopTab::HoldingRegisters, 0, 22),
+                (SelectedTopTab::HoldingRegisters, 30, 1),
+            ]
+        );
+    }
+}
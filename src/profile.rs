@@ -0,0 +1,469 @@
+//!   Copyright 2025 Isaac Schlaegel
+//!
+//!    Licensed under the Apache License, Version 2.0 (the "License");
+//!    you may not use this file except in compliance with the License.
+//!    You may obtain a copy of the License at
+//!
+//!        http://www.apache.org/licenses/LICENSE-2.0
+//!
+//!    Unless required by applicable law or agreed to in writing, software
+//!    distributed under the License is distributed on an "AS IS" BASIS,
+//!    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//!    See the License for the specific language governing permissions and
+//!    limitations under the License.
+
+use crate::{
+    enums::{DisplayMode, SelectedTopTab},
+    labels::LabelMap,
+    scaling::{ScalingMap, ScalingRule},
+    utils::{invalid_line, parse_address_range, registers_to_ascii},
+};
+use std::path::Path;
+use tokio::fs;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DataType {
+    U16,
+    I16,
+    U32,
+    I32,
+    F32,
+    Bitfield,
+    String, // Two ASCII characters per register, high byte first
+}
+
+impl DataType {
+    fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "u16" => Some(DataType::U16),
+            "i16" => Some(DataType::I16),
+            "u32" => Some(DataType::U32),
+            "i32" => Some(DataType::I32),
+            "f32" => Some(DataType::F32),
+            "bitfield" => Some(DataType::Bitfield),
+            "string" => Some(DataType::String),
+            _ => None,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            DataType::U16 => "u16",
+            DataType::I16 => "i16",
+            DataType::U32 => "u32",
+            DataType::I32 => "i32",
+            DataType::F32 => "f32",
+            DataType::Bitfield => "bitfield",
+            DataType::String => "string",
+        }
+    }
+
+    // Typed in decimal with their own range, the others are edited as a raw register
+    pub fn is_numeric(self) -> bool {
+        matches!(
+            self,
+            DataType::I16 | DataType::U32 | DataType::I32 | DataType::F32
+        )
+    }
+
+    // Longest value the Edit popup takes, floats are cut off well past f32 precision
+    pub fn input_len(self) -> usize {
+        match self {
+            DataType::I16 => 6,
+            DataType::U32 => 10,
+            DataType::I32 => 11,
+            DataType::F32 => 16,
+            DataType::U16 | DataType::Bitfield | DataType::String => 5,
+        }
+    }
+
+    // Shown in the Edit popup before anything is typed
+    pub fn range(self) -> String {
+        let range = match self {
+            DataType::I16 => "-32768 to 32767",
+            DataType::U32 => "0 to 4294967295",
+            DataType::I32 => "-2147483648 to 2147483647",
+            DataType::F32 => "finite 32-bit float",
+            DataType::U16 | DataType::Bitfield | DataType::String => "0 to 65535",
+        };
+        format!("{}: {range}", self.name())
+    }
+
+    // The register bits of a typed value, None when it doesn't fit the type
+    pub fn parse_value(self, text: &str) -> Option<u32> {
+        match self {
+            DataType::I16 => text.parse::<i16>().ok().map(|value| value as u16 as u32),
+            DataType::U32 => text.parse::<u32>().ok(),
+            DataType::I32 => text.parse::<i32>().ok().map(|value| value as u32),
+            DataType::F32 => text
+                .parse::<f32>()
+                .ok()
+                .filter(|value| value.is_finite())
+                .map(f32::to_bits),
+            DataType::U16 | DataType::Bitfield | DataType::String => {
+                text.parse::<u16>().ok().map(u32::from)
+            }
+        }
+    }
+
+    // Registers taken up by one value
+    pub fn width(self) -> u16 {
+        match self {
+            DataType::U32 | DataType::I32 | DataType::F32 => 2,
+            _ => 1,
+        }
+    }
+
+    // A single register in a table cell, None when the display mode's format fits better
+    pub fn format_register(self, value: u16, display_mode: DisplayMode) -> Option<String> {
+        match (self, display_mode) {
+            (DataType::I16, _) => Some(DisplayMode::Signed.format(value)),
+            (DataType::Bitfield, DisplayMode::Binary) => None,
+            (DataType::Bitfield, _) => Some(format!("0x{value:04X}")),
+            (DataType::String, _) => Some(registers_to_ascii(&[value])),
+            _ => None,
+        }
+    }
+
+    // A register pair joined in the configured word order
+    pub fn format_wide(self, value: u32) -> String {
+        match self {
+            DataType::I32 => (value as i32).to_string(),
+            DataType::F32 => f32::from_bits(value).to_string(),
+            _ => value.to_string(),
+        }
+    }
+}
+
+struct TypeRule {
+    table: SelectedTopTab,
+    start: u16,
+    end: u16,
+    data_type: DataType,
+    read_only: bool,
+}
+
+// One [table address] section while it is being read
+struct Section {
+    line: usize,
+    table: SelectedTopTab,
+    start: u16,
+    end: u16,
+    name: Option<String>,
+    data_type: Option<DataType>,
+    scale: Option<f64>,
+    offset: Option<f64>,
+    unit: Option<String>,
+    read_only: bool,
+}
+
+// A vendor register map that sets up labels, scaling, data types and read-only
+// addresses in one file
+#[derive(Default)]
+pub struct DeviceProfile {
+    pub labels: LabelMap,
+    pub scaling: ScalingMap,
+    types: Vec<TypeRule>,
+    address_spaces: Vec<(SelectedTopTab, usize)>, // Table, Number of addresses
+}
+
+impl DeviceProfile {
+    pub async fn from_file<P: AsRef<Path>>(filename: P) -> std::io::Result<Self> {
+        Self::parse(&fs::read_to_string(filename).await?)
+    }
+
+    // Each address range starts a section, followed by key = value lines:
+    //   [holding_registers 40-41]
+    //   name = Setpoint
+    //   type = f32            (u16, i16, u32, i32, f32, bitfield or string)
+    //   scale = 0.1           (scale, offset and unit are for u16 registers)
+    //   offset = 0
+    //   unit = °C
+    //   access = read-only    (or read-write)
+    // Addresses are 1-based, matching the rest of the UI. An [address_space] section
+    // instead lists how many addresses each table has, e.g. holding_registers = 200
+    pub fn parse(text: &str) -> std::io::Result<Self> {
+        let mut profile = Self::default();
+        let mut section: Option<Section> = None;
+        let mut in_address_space = false;
+
+        for (index, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            if let Some(header) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+                if let Some(section) = section.take() {
+                    profile.add(section)?;
+                }
+                in_address_space = header.trim() == "address_space";
+                if in_address_space {
+                    continue;
+                }
+                let (table, range) = header
+                    .trim()
+                    .split_once(char::is_whitespace)
+                    .ok_or_else(|| invalid_line(index, "Expected [table address]"))?;
+                let table = SelectedTopTab::from_name(table)
+                    .ok_or_else(|| invalid_line(index, "Unknown table"))?;
+                let (start, end) = parse_address_range(range)
+                    .ok_or_else(|| invalid_line(index, "Invalid address range"))?;
+                section = Some(Section {
+                    line: index,
+                    table,
+                    start,
+                    end,
+                    name: None,
+                    data_type: None,
+                    scale: None,
+                    offset: None,
+                    unit: None,
+                    read_only: false,
+                });
+                continue;
+            }
+
+            if in_address_space {
+                let (table, size) = line
+                    .split_once('=')
+                    .ok_or_else(|| invalid_line(index, "Expected table = size"))?;
+                let table = SelectedTopTab::from_name(table)
+                    .ok_or_else(|| invalid_line(index, "Unknown table"))?;
+                let size = size
+                    .trim()
+                    .parse::<usize>()
+                    .ok()
+                    .filter(|size| (1..=u16::MAX as usize + 1).contains(size))
+                    .ok_or_else(|| invalid_line(index, "Size must be 1-65536"))?;
+                profile.address_spaces.push((table, size));
+                continue;
+            }
+
+            let current = section
+                .as_mut()
+                .ok_or_else(|| invalid_line(index, "Expected a [table address] section"))?;
+            let (key, value) = line
+                .split_once('=')
+                .ok_or_else(|| invalid_line(index, "Expected key = value"))?;
+            let value = value.trim().trim_matches('"');
+            match key.trim() {
+                "name" => current.name = Some(value.to_string()),
+                "type" => {
+                    current.data_type = Some(
+                        DataType::from_name(value)
+                            .ok_or_else(|| invalid_line(index, "Unknown type"))?,
+                    )
+                }
+                "scale" => {
+                    current.scale = Some(
+                        value
+                            .parse::<f64>()
+                            .ok()
+                            .filter(|scale| scale.is_finite() && *scale != 0.0)
+                            .ok_or_else(|| invalid_line(index, "Invalid scale"))?,
+                    )
+                }
+                "offset" => {
+                    current.offset = Some(
+                        value
+                            .parse::<f64>()
+                            .ok()
+                            .filter(|offset| offset.is_finite())
+                            .ok_or_else(|| invalid_line(index, "Invalid offset"))?,
+                    )
+                }
+                "unit" => current.unit = Some(value.to_string()),
+                "access" => {
+                    current.read_only = match value {
+                        "read-only" | "ro" => true,
+                        "read-write" | "rw" => false,
+                        _ => return Err(invalid_line(index, "Expected read-only or read-write")),
+                    }
+                }
+                _ => return Err(invalid_line(index, "Unknown key")),
+            }
+        }
+
+        if let Some(section) = section.take() {
+            profile.add(section)?;
+        }
+        Ok(profile)
+    }
+
+    fn add(&mut self, section: Section) -> std::io::Result<()> {
+        let is_register = matches!(
+            section.table,
+            SelectedTopTab::InputRegisters | SelectedTopTab::HoldingRegisters
+        );
+        let data_type = match (section.data_type, is_register) {
+            (Some(_), false) => {
+                return Err(invalid_line(section.line, "Only registers have a type"));
+            }
+            (data_type, _) => data_type.unwrap_or(DataType::U16),
+        };
+        let is_scaled =
+            section.scale.is_some() || section.offset.is_some() || section.unit.is_some();
+        if is_scaled && data_type != DataType::U16 {
+            return Err(invalid_line(
+                section.line,
+                "Only u16 registers can be scaled",
+            ));
+        }
+        if (section.end as u32 - section.start as u32 + 1) % data_type.width() as u32 != 0 {
+            return Err(invalid_line(
+                section.line,
+                "Range doesn't fit a whole number of values",
+            ));
+        }
+
+        if let Some(name) = section.name {
+            self.labels
+                .add(section.table, section.start, section.end, name);
+        }
+        if is_scaled {
+            self.scaling.add(ScalingRule {
+                table: section.table,
+                start: section.start,
+                end: section.end,
+                scale: section.scale.unwrap_or(1.0),
+                offset: section.offset.unwrap_or(0.0),
+                unit: section.unit.unwrap_or_default(),
+            });
+        }
+        self.types.push(TypeRule {
+            table: section.table,
+            start: section.start,
+            end: section.end,
+            data_type,
+            read_only: section.read_only,
+        });
+        Ok(())
+    }
+
+    fn get(&self, table: SelectedTopTab, address: u16) -> Option<&TypeRule> {
+        // Later sections take priority so broad ranges can be overridden
+        self.types
+            .iter()
+            .rev()
+            .find(|rule| rule.table == table && (rule.start..=rule.end).contains(&address))
+    }
+
+    // The type at an address and the first register of the value it is part of
+    pub fn data_type(&self, table: SelectedTopTab, address: u16) -> Option<(DataType, u16)> {
+        self.get(table, address).map(|rule| {
+            let width = rule.data_type.width();
+            let value_start = rule.start + (address - rule.start) / width * width;
+            (rule.data_type, value_start)
+        })
+    }
+
+    pub fn is_read_only(&self, table: SelectedTopTab, address: u16) -> bool {
+        self.get(table, address).is_some_and(|rule| rule.read_only)
+    }
+
+    // None keeps the full 65536 addresses
+    pub fn address_space(&self, table: SelectedTopTab) -> Option<usize> {
+        self.address_spaces
+            .iter()
+            .rev()
+            .find(|(space_table, _)| *space_table == table)
+            .map(|(_, size)| *size)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PROFILE: &str = "
+        # Example drive
+        [address_space]
+        holding_registers = 200
+        coils = 16
+
+        [holding_registers 1-10]
+        access = read-only
+
+        [holding_registers 3-4]
+        name = \"Setpoint\"
+        type = f32
+        access = read-write
+
+        [input_registers 1]
+        name = Temperature
+        scale = 0.1
+        unit = °C
+
+        [coils 1-8]
+        name = Outputs
+        access = ro
+    ";
+
+    #[test]
+    fn sections_set_up_every_map() {
+        let profile = DeviceProfile::parse(PROFILE).unwrap();
+        let holding = SelectedTopTab::HoldingRegisters;
+
+        assert!(profile.is_read_only(holding, 0));
+        assert!(!profile.is_read_only(holding, 2));
+        assert!(profile.is_read_only(SelectedTopTab::Coils, 7));
+        assert!(!profile.is_read_only(SelectedTopTab::Coils, 8));
+
+        assert_eq!(profile.data_type(holding, 3), Some((DataType::F32, 2)));
+        assert_eq!(profile.data_type(holding, 5), Some((DataType::U16, 5)));
+        assert_eq!(profile.data_type(holding, 10), None);
+
+        assert_eq!(profile.labels.get(holding, 2), Some("Setpoint"));
+        let rule = profile
+            .scaling
+            .get(SelectedTopTab::InputRegisters, 0)
+            .unwrap();
+        assert_eq!(rule.format(215), "21.5");
+        assert_eq!(rule.unit, "°C");
+
+        assert_eq!(profile.address_space(holding), Some(200));
+        assert_eq!(profile.address_space(SelectedTopTab::Coils), Some(16));
+        assert_eq!(profile.address_space(SelectedTopTab::InputRegisters), None);
+    }
+
+    #[test]
+    fn rejects_invalid_sections() {
+        let invalid = [
+            "name = No section",
+            "[holding_registers]",
+            "[holding_registers 1]\ntype = f64",
+            "[holding_registers 1]\ntype = f32",
+            "[holding_registers 1-2]\ntype = i16\nscale = 2",
+            "[coils 1]\ntype = u16",
+            "[coils 1]\naccess = sometimes",
+            "[coils 1]\ncolor = red",
+            "[address_space]\ncoils = 0",
+            "[address_space]\ncoils = 65537",
+            "[address_space]\npumps = 10",
+        ];
+        for text in invalid {
+            assert!(DeviceProfile::parse(text).is_err(), "{text}");
+        }
+    }
+
+    #[test]
+    fn typed_values_must_fit_their_type() {
+        assert_eq!(DataType::I16.parse_value("-1"), Some(0xFFFF));
+        assert_eq!(DataType::I16.parse_value("40000"), None);
+        assert_eq!(DataType::U16.parse_value("-1"), None);
+        assert_eq!(DataType::U32.parse_value("70000"), Some(70000));
+        assert_eq!(DataType::U32.parse_value("-1"), None);
+        assert_eq!(DataType::I32.parse_value("-2"), Some(0xFFFF_FFFE));
+        assert_eq!(DataType::F32.parse_value("1.5"), Some(1.5f32.to_bits()));
+        assert_eq!(DataType::F32.parse_value("1e39"), None);
+        assert_eq!(DataType::I16.range(), "i16: -32768 to 32767");
+    }
+
+    #[test]
+    fn wide_types_format_the_joined_pair() {
+        assert_eq!(DataType::F32.format_wide(1.5f32.to_bits()), "1.5");
+        assert_eq!(DataType::I32.format_wide(u32::MAX), "-1");
+        assert_eq!(DataType::U32.format_wide(u32::MAX), "4294967295");
+    }
+}
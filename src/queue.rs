@@ -21,6 +21,10 @@ pub struct QueueItem {
 }
 
 impl QueueItem {
+    pub fn memory_address(&self) -> String {
+        self.cell.table_type.memory_address(self.address)
+    }
+
     pub fn original_content(&self) -> String {
         format!(
             "{:05}",
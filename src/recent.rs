@@ -0,0 +1,141 @@
+//!   Copyright 2025 Isaac Schlaegel
+//!
+//!    Licensed under the Apache License, Version 2.0 (the "License");
+//!    you may not use this file except in compliance with the License.
+//!    You may obtain a copy of the License at
+//!
+//!        http://www.apache.org/licenses/LICENSE-2.0
+//!
+//!    Unless required by applicable law or agreed to in writing, software
+//!    distributed under the License is distributed on an "AS IS" BASIS,
+//!    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//!    See the License for the specific language governing permissions and
+//!    limitations under the License.
+
+use crate::utils::config_dir;
+use std::{
+    io::ErrorKind,
+    net::{IpAddr, SocketAddr},
+    path::PathBuf,
+};
+use tokio::fs;
+
+const MAX_RECENT: usize = 8;
+
+// Addresses the TUI has successfully connected to, offered in the connection popup
+#[derive(Default)]
+pub struct RecentConnections {
+    entries: Vec<SocketAddr>, // Most recent first
+    path: Option<PathBuf>,    // Where the list is saved, None keeps it in memory only
+}
+
+impl RecentConnections {
+    // $XDG_CONFIG_HOME/magic_modbus/recent
+    pub fn default_path() -> Option<PathBuf> {
+        Some(config_dir()?.join("recent"))
+    }
+
+    // Line format: address,port
+    // The file is rewritten on every connection, so lines that don't parse are dropped
+    pub async fn from_file(path: PathBuf) -> std::io::Result<Self> {
+        let file = match fs::read_to_string(&path).await {
+            Ok(file) => file,
+            Err(err) if err.kind() == ErrorKind::NotFound => String::new(),
+            Err(err) => return Err(err),
+        };
+
+        let mut recent = Self {
+            entries: vec![],
+            path: Some(path),
+        };
+        for line in file.lines().rev() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let Some((address, port)) = line.split_once(',') else {
+                continue;
+            };
+            if let (Ok(address), Ok(port)) =
+                (address.trim().parse::<IpAddr>(), port.trim().parse::<u16>())
+            {
+                recent.push(SocketAddr::new(address, port));
+            }
+        }
+
+        Ok(recent)
+    }
+
+    pub fn entries(&self) -> &[SocketAddr] {
+        &self.entries
+    }
+
+    pub fn get(&self, index: usize) -> Option<SocketAddr> {
+        self.entries.get(index).copied()
+    }
+
+    pub fn push(&mut self, addr: SocketAddr) {
+        self.entries.retain(|entry| *entry != addr);
+        self.entries.insert(0, addr);
+        self.entries.truncate(MAX_RECENT);
+    }
+
+    pub async fn save(&self) -> std::io::Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).await?;
+        }
+
+        let mut contents = String::from("# Magic Modbus recent connections, most recent first\n");
+        for entry in &self.entries {
+            contents.push_str(&format!("{},{}\n", entry.ip(), entry.port()));
+        }
+        fs::write(path, contents).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::Ipv4Addr;
+
+    fn local(port: u16) -> SocketAddr {
+        SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), port)
+    }
+
+    #[test]
+    fn push_dedupes_and_caps() {
+        let mut recent = RecentConnections::default();
+        for port in 0..MAX_RECENT as u16 + 2 {
+            recent.push(local(port));
+        }
+        recent.push(local(5));
+
+        assert_eq!(recent.entries().len(), MAX_RECENT);
+        assert_eq!(recent.get(0), Some(local(5)));
+        assert_eq!(
+            recent.entries().iter().filter(|e| **e == local(5)).count(),
+            1
+        );
+        assert!(!recent.entries().contains(&local(0)));
+    }
+
+    #[tokio::test]
+    async fn save_and_reload_keeps_order() {
+        let path = std::env::temp_dir()
+            .join(format!("recent_{}", std::process::id()))
+            .join("recent");
+
+        let mut recent = RecentConnections::from_file(path.clone()).await.unwrap();
+        assert!(recent.entries().is_empty());
+        recent.push(local(502));
+        recent.push("[::1]:5020".parse().unwrap());
+        recent.save().await.unwrap();
+
+        let reloaded = RecentConnections::from_file(path.clone()).await;
+        let _ = std::fs::remove_dir_all(path.parent().unwrap());
+        assert_eq!(reloaded.unwrap().entries(), recent.entries());
+    }
+}
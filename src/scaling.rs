@@ -0,0 +1,183 @@
+//!   Copyright 2025 Isaac Schlaegel
+//!
+//!    Licensed under the Apache License, Version 2.0 (the "License");
+//!    you may not use this file except in compliance with the License.
+//!    You may obtain a copy of the License at
+//!
+//!        http://www.apache.org/licenses/LICENSE-2.0
+//!
+//!    Unless required by applicable law or agreed to in writing, software
+//!    distributed under the License is distributed on an "AS IS" BASIS,
+//!    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//!    See the License for the specific language governing permissions and
+//!    limitations under the License.
+
+use crate::{
+    enums::SelectedTopTab,
+    utils::{invalid_line, parse_address_range},
+};
+use std::path::Path;
+use tokio::fs;
+
+pub struct ScalingRule {
+    pub table: SelectedTopTab,
+    pub start: u16,
+    pub end: u16,
+    pub scale: f64,
+    pub offset: f64,
+    pub unit: String,
+}
+
+impl ScalingRule {
+    pub fn to_engineering(&self, raw: u16) -> f64 {
+        raw as f64 * self.scale + self.offset
+    }
+
+    pub fn to_raw(&self, value: f64) -> Option<u16> {
+        let raw = ((value - self.offset) / self.scale).round();
+        if raw.is_finite() && (0.0..=u16::MAX as f64).contains(&raw) {
+            Some(raw as u16)
+        } else {
+            None
+        }
+    }
+
+    pub fn format(&self, raw: u16) -> String {
+        // Enough decimals to show a single step of the scale (0.1 -> 1, 0.01 -> 2)
+        let precision = (-self.scale.abs().log10()).ceil().clamp(0.0, 4.0) as usize;
+        format!("{:.precision$}", self.to_engineering(raw))
+    }
+
+    // Lowest to highest engineering value, a negative scale flips the raw range
+    pub fn range(&self) -> String {
+        let (low, high) = match self.scale < 0.0 {
+            true => (u16::MAX, 0),
+            false => (0, u16::MAX),
+        };
+        format!(
+            "{} to {} {}",
+            self.format(low),
+            self.format(high),
+            self.unit
+        )
+        .trim_end()
+        .to_string()
+    }
+
+    // Characters in the longest value this rule can show, at either end of the raw range
+    pub fn width(&self) -> usize {
+        usize::max(self.format(0).len(), self.format(u16::MAX).len())
+    }
+
+    fn contains(&self, table: SelectedTopTab, address: u16) -> bool {
+        self.table == table && (self.start..=self.end).contains(&address)
+    }
+}
+
+#[derive(Default)]
+pub struct ScalingMap {
+    rules: Vec<ScalingRule>,
+}
+
+impl ScalingMap {
+    // Line format: table,address[-address],scale,offset[,unit]
+    // Addresses are 1-based, matching the rest of the UI
+    pub async fn from_file<P: AsRef<Path>>(filename: P) -> std::io::Result<Self> {
+        let file = fs::read_to_string(filename).await?;
+        let mut rules = vec![];
+
+        for (index, line) in file.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+            if !(4..=5).contains(&fields.len()) {
+                return Err(invalid_line(index, "Expected 4 or 5 fields"));
+            }
+
+            let table = SelectedTopTab::from_name(fields[0])
+                .ok_or_else(|| invalid_line(index, "Unknown table"))?;
+            let (start, end) = parse_address_range(fields[1])
+                .ok_or_else(|| invalid_line(index, "Invalid address range"))?;
+            let scale = fields[2]
+                .parse::<f64>()
+                .ok()
+                .filter(|scale| scale.is_finite() && *scale != 0.0)
+                .ok_or_else(|| invalid_line(index, "Invalid scale"))?;
+            let offset = fields[3]
+                .parse::<f64>()
+                .ok()
+                .filter(|offset| offset.is_finite())
+                .ok_or_else(|| invalid_line(index, "Invalid offset"))?;
+            let unit = fields.get(4).unwrap_or(&"").to_string();
+
+            rules.push(ScalingRule {
+                table,
+                start,
+                end,
+                scale,
+                offset,
+                unit,
+            });
+        }
+
+        Ok(Self { rules })
+    }
+
+    pub fn add(&mut self, rule: ScalingRule) {
+        self.rules.push(rule);
+    }
+
+    // The other map's rules take priority over these
+    pub fn extend(&mut self, other: ScalingMap) {
+        self.rules.extend(other.rules);
+    }
+
+    pub fn get(&self, table: SelectedTopTab, address: u16) -> Option<&ScalingRule> {
+        // Later lines take priority so broad ranges can be overridden
+        self.rules
+            .iter()
+            .rev()
+            .find(|rule| rule.contains(table, address))
+    }
+
+    // Widest scaled value in the table, None when nothing in it is scaled
+    pub fn max_width(&self, table: SelectedTopTab) -> Option<usize> {
+        self.rules
+            .iter()
+            .filter(|rule| rule.table == table)
+            .map(ScalingRule::width)
+            .max()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(table: SelectedTopTab, scale: f64, offset: f64) -> ScalingRule {
+        ScalingRule {
+            table,
+            start: 0,
+            end: 0,
+            scale,
+            offset,
+            unit: String::new(),
+        }
+    }
+
+    #[test]
+    fn width_fits_both_ends_of_the_range() {
+        let mut scaling = ScalingMap::default();
+        scaling.add(rule(SelectedTopTab::HoldingRegisters, 0.1, 0.0));
+        scaling.add(rule(SelectedTopTab::HoldingRegisters, 0.1, -1000.0));
+        scaling.add(rule(SelectedTopTab::InputRegisters, 10.0, 0.0));
+
+        // -1000.0 at raw 0 is wider than 6553.5 or 5553.5 at the top
+        assert_eq!(scaling.max_width(SelectedTopTab::HoldingRegisters), Some(7));
+        assert_eq!(scaling.max_width(SelectedTopTab::InputRegisters), Some(6));
+        assert_eq!(scaling.max_width(SelectedTopTab::Coils), None);
+    }
+}
@@ -0,0 +1,383 @@
+//!   Copyright 2025 Isaac Schlaegel
+//!
+//!    Licensed under the Apache License, Version 2.0 (the "License");
+//!    you may not use this file except in compliance with the License.
+//!    You may obtain a copy of the License at
+//!
+//!        http://www.apache.org/licenses/LICENSE-2.0
+//!
+//!    Unless required by applicable law or agreed to in writing, software
+//!    distributed under the License is distributed on an "AS IS" BASIS,
+//!    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//!    See the License for the specific language governing permissions and
+//!    limitations under the License.
+
+use crate::utils::{MAX_READ_BITS, MAX_READ_WORDS};
+use std::{
+    future, io,
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+};
+use tokio::net::TcpListener;
+use tokio_modbus::{
+    ExceptionCode, Request, Response,
+    server::{
+        Service,
+        tcp::{Server, accept_tcp_connection},
+    },
+};
+
+const ADDRESS_SPACE: usize = u16::MAX as usize + 1;
+
+// In-memory device backing the mock server, one entry per address
+pub struct Registers {
+    pub coils: Vec<bool>,
+    pub discrete_inputs: Vec<bool>,
+    pub input_registers: Vec<u16>,
+    pub holding_registers: Vec<u16>,
+    pub reads: usize, // Read requests served, so tests can count what went over the wire
+    pub faulted: bool, // Fails every read with a device failure, like a device in a fault state
+}
+
+impl Default for Registers {
+    fn default() -> Self {
+        Self {
+            coils: vec![false; ADDRESS_SPACE],
+            discrete_inputs: vec![false; ADDRESS_SPACE],
+            input_registers: vec![0; ADDRESS_SPACE],
+            holding_registers: vec![0; ADDRESS_SPACE],
+            reads: 0,
+            faulted: false,
+        }
+    }
+}
+
+impl Registers {
+    fn handle(&mut self, request: Request<'static>) -> Result<Response, ExceptionCode> {
+        if let Request::ReadCoils(..)
+        | Request::ReadDiscreteInputs(..)
+        | Request::ReadInputRegisters(..)
+        | Request::ReadHoldingRegisters(..) = request
+        {
+            self.reads += 1;
+            if self.faulted {
+                return Err(ExceptionCode::ServerDeviceFailure);
+            }
+        }
+        match request {
+            Request::ReadCoils(addr, count) => Ok(Response::ReadCoils(read(
+                &self.coils,
+                addr,
+                count,
+                MAX_READ_BITS,
+            )?)),
+            Request::ReadDiscreteInputs(addr, count) => Ok(Response::ReadDiscreteInputs(read(
+                &self.discrete_inputs,
+                addr,
+                count,
+                MAX_READ_BITS,
+            )?)),
+            Request::ReadInputRegisters(addr, count) => Ok(Response::ReadInputRegisters(read(
+                &self.input_registers,
+                addr,
+                count,
+                MAX_READ_WORDS,
+            )?)),
+            Request::ReadHoldingRegisters(addr, count) => Ok(Response::ReadHoldingRegisters(read(
+                &self.holding_registers,
+                addr,
+                count,
+                MAX_READ_WORDS,
+            )?)),
+            Request::WriteSingleCoil(addr, coil) => {
+                write(&mut self.coils, addr, &[coil])?;
+                Ok(Response::WriteSingleCoil(addr, coil))
+            }
+            Request::WriteMultipleCoils(addr, coils) => {
+                write(&mut self.coils, addr, &coils)?;
+                Ok(Response::WriteMultipleCoils(addr, coils.len() as u16))
+            }
+            Request::WriteSingleRegister(addr, word) => {
+                write(&mut self.holding_registers, addr, &[word])?;
+                Ok(Response::WriteSingleRegister(addr, word))
+            }
+            Request::WriteMultipleRegisters(addr, words) => {
+                write(&mut self.holding_registers, addr, &words)?;
+                Ok(Response::WriteMultipleRegisters(addr, words.len() as u16))
+            }
+            Request::MaskWriteRegister(addr, and_mask, or_mask) => {
+                let register = &mut self.holding_registers[addr as usize];
+                *register = (*register & and_mask) | (or_mask & !and_mask);
+                Ok(Response::MaskWriteRegister(addr, and_mask, or_mask))
+            }
+            Request::ReadWriteMultipleRegisters(read_addr, count, write_addr, words) => {
+                // The write is performed before the read
+                write(&mut self.holding_registers, write_addr, &words)?;
+                Ok(Response::ReadWriteMultipleRegisters(read(
+                    &self.holding_registers,
+                    read_addr,
+                    count,
+                    MAX_READ_WORDS,
+                )?))
+            }
+            _ => Err(ExceptionCode::IllegalFunction),
+        }
+    }
+}
+
+// Like a real device, a request for more than the protocol allows is rejected outright
+fn read<T: Copy>(
+    table: &[T],
+    addr: u16,
+    count: u16,
+    max_count: u16,
+) -> Result<Vec<T>, ExceptionCode> {
+    if count == 0 || count > max_count {
+        return Err(ExceptionCode::IllegalDataValue);
+    }
+    table
+        .get(addr as usize..addr as usize + count as usize)
+        .map(<[T]>::to_vec)
+        .ok_or(ExceptionCode::IllegalDataAddress)
+}
+
+fn write<T: Copy>(table: &mut [T], addr: u16, values: &[T]) -> Result<(), ExceptionCode> {
+    table
+        .get_mut(addr as usize..addr as usize + values.len())
+        .ok_or(ExceptionCode::IllegalDataAddress)?
+        .copy_from_slice(values);
+    Ok(())
+}
+
+struct MockService {
+    registers: Arc<Mutex<Registers>>,
+}
+
+impl Service for MockService {
+    type Request = Request<'static>;
+    type Response = Response;
+    type Exception = ExceptionCode;
+    type Future = future::Ready<Result<Self::Response, Self::Exception>>;
+
+    fn call(&self, request: Self::Request) -> Self::Future {
+        let result = match self.registers.lock() {
+            Ok(mut registers) => registers.handle(request),
+            Err(_) => Err(ExceptionCode::ServerDeviceFailure),
+        };
+        future::ready(result)
+    }
+}
+
+pub struct MockServer {
+    listener: TcpListener,
+    registers: Arc<Mutex<Registers>>,
+}
+
+impl MockServer {
+    pub async fn bind(addr: SocketAddr) -> io::Result<Self> {
+        Ok(Self {
+            listener: TcpListener::bind(addr).await?,
+            registers: Arc::new(Mutex::new(Registers::default())),
+        })
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    // Shared with the server, so tests can seed and inspect the device state
+    #[cfg(test)]
+    pub fn registers(&self) -> Arc<Mutex<Registers>> {
+        self.registers.clone()
+    }
+
+    pub async fn run(self) -> io::Result<()> {
+        let registers = self.registers;
+        let on_connected = |stream, socket_addr| {
+            let registers = registers.clone();
+            async move {
+                accept_tcp_connection(stream, socket_addr, |_| {
+                    Ok(Some(MockService {
+                        registers: registers.clone(),
+                    }))
+                })
+            }
+        };
+        Server::new(self.listener)
+            .serve(&on_connected, |_| {})
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        enums::{CellType, SelectedTopTab},
+        macro_parser::MagModCommandList,
+        utils::BROADCAST_UNIT_ID,
+    };
+    use std::{
+        net::{IpAddr, Ipv4Addr},
+        time::Duration,
+    };
+
+    async fn start_server() -> (SocketAddr, Arc<Mutex<Registers>>) {
+        let server = MockServer::bind(SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 0))
+            .await
+            .unwrap();
+        let addr = server.local_addr().unwrap();
+        let registers = server.registers();
+        tokio::spawn(server.run());
+        (addr, registers)
+    }
+
+    async fn replay(
+        addr: SocketAddr,
+        name: &str,
+        commands: Vec<(SelectedTopTab, u16, CellType)>,
+    ) -> color_eyre::Result<()> {
+        let path = std::env::temp_dir().join(format!("{name}_{}", std::process::id()));
+        let command_list = MagModCommandList::new(addr.ip(), addr.port(), commands);
+        command_list
+            .to_file(path.to_string_lossy().to_string(), true)
+            .await?;
+
+        let file_path = path.with_extension("magmod");
+        let mut saved_list = MagModCommandList::from_file(&file_path).await?;
+        let _ = std::fs::remove_file(&file_path);
+        saved_list.run_macro(false, false, false, true).await
+    }
+
+    #[tokio::test]
+    async fn replayed_macro_sets_server_state() {
+        let (addr, registers) = start_server().await;
+        registers.lock().unwrap().input_registers[7] = 1234;
+
+        replay(
+            addr,
+            "replayed_macro_sets_server_state",
+            vec![
+                (SelectedTopTab::Coils, 0, CellType::Coil(true)),
+                (SelectedTopTab::Coils, 65535, CellType::Coil(true)),
+                (SelectedTopTab::HoldingRegisters, 10, CellType::Word(42)),
+                (SelectedTopTab::HoldingRegisters, 10, CellType::Word(43)),
+                (SelectedTopTab::InputRegisters, 7, CellType::Word(1234)),
+            ],
+        )
+        .await
+        .unwrap();
+
+        let registers = registers.lock().unwrap();
+        assert!(registers.coils[0]);
+        assert!(registers.coils[65535]);
+        assert!(!registers.coils[1]);
+        assert_eq!(registers.holding_registers[10], 43);
+    }
+
+    #[tokio::test]
+    async fn replayed_macro_writes_last_address() {
+        let (addr, registers) = start_server().await;
+
+        replay(
+            addr,
+            "replayed_macro_writes_last_address",
+            vec![(
+                SelectedTopTab::HoldingRegisters,
+                65535,
+                CellType::Word(0xBEEF),
+            )],
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(registers.lock().unwrap().holding_registers[65535], 0xBEEF);
+    }
+
+    #[tokio::test]
+    async fn replayed_macro_fails_on_assertion() {
+        let (addr, registers) = start_server().await;
+
+        let result = replay(
+            addr,
+            "replayed_macro_fails_on_assertion",
+            vec![
+                (SelectedTopTab::HoldingRegisters, 0, CellType::Word(5)),
+                (SelectedTopTab::DiscreteInputs, 3, CellType::Coil(true)),
+            ],
+        )
+        .await;
+
+        assert!(result.is_err());
+        // Writes before the failed assertion still reach the device
+        assert_eq!(registers.lock().unwrap().holding_registers[0], 5);
+    }
+
+    #[tokio::test]
+    async fn macro_runs_on_every_target() {
+        let (first, first_registers) = start_server().await;
+        let (last, last_registers) = start_server().await;
+        // Nothing listens here once the listener is dropped
+        let refused = TcpListener::bind("127.0.0.1:0")
+            .await
+            .unwrap()
+            .local_addr()
+            .unwrap();
+        let targets: Vec<(String, u16)> = [first, refused, last]
+            .iter()
+            .map(|addr| (addr.ip().to_string(), addr.port()))
+            .collect();
+        let mut command_list = MagModCommandList::new(
+            IpAddr::V4(Ipv4Addr::LOCALHOST),
+            502,
+            vec![(SelectedTopTab::HoldingRegisters, 4, CellType::Word(7))],
+        );
+
+        let result = command_list
+            .run_on_targets(&targets, false, false, false, false)
+            .await;
+        assert!(result.is_err());
+        assert_eq!(first_registers.lock().unwrap().holding_registers[4], 7);
+        assert_eq!(last_registers.lock().unwrap().holding_registers[4], 0);
+
+        let report = command_list
+            .run_on_targets(&targets, false, false, false, true)
+            .await
+            .unwrap_err();
+        assert_eq!(report.to_string(), "1 of 3 devices failed");
+        assert_eq!(last_registers.lock().unwrap().holding_registers[4], 7);
+    }
+
+    #[tokio::test]
+    async fn broadcast_macro_sends_without_waiting() {
+        let (addr, registers) = start_server().await;
+        let mut command_list = MagModCommandList::new(
+            addr.ip(),
+            addr.port(),
+            vec![(SelectedTopTab::HoldingRegisters, 4, CellType::Word(7))],
+        )
+        .with_unit_id(BROADCAST_UNIT_ID);
+
+        // There is nothing to read back from a broadcast
+        assert!(
+            command_list
+                .run_macro(false, false, false, true)
+                .await
+                .is_err()
+        );
+        assert_eq!(registers.lock().unwrap().holding_registers[4], 0);
+
+        command_list
+            .run_macro(false, false, false, false)
+            .await
+            .unwrap();
+        // Nothing confirms the write, so give the server a moment to get to it
+        for _ in 0..50 {
+            if registers.lock().unwrap().holding_registers[4] == 7 {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert_eq!(registers.lock().unwrap().holding_registers[4], 7);
+    }
+}
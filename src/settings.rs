@@ -0,0 +1,215 @@
+//!   Copyright 2025 Isaac Schlaegel
+//!
+//!    Licensed under the Apache License, Version 2.0 (the "License");
+//!    you may not use this file except in compliance with the License.
+//!    You may obtain a copy of the License at
+//!
+//!        http://www.apache.org/licenses/LICENSE-2.0
+//!
+//!    Unless required by applicable law or agreed to in writing, software
+//!    distributed under the License is distributed on an "AS IS" BASIS,
+//!    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//!    See the License for the specific language governing permissions and
+//!    limitations under the License.
+
+use crate::{
+    enums::{Bell, DisplayMode, Setting, Theme, WordOrder},
+    utils::config_dir,
+};
+use clap::ValueEnum;
+use std::path::{Path, PathBuf};
+use tokio::fs;
+
+// Options from the settings popup, kept between runs
+#[derive(Clone, Copy)]
+pub struct Settings {
+    pub display_mode: DisplayMode,
+    pub word_order: WordOrder,
+    pub theme: Theme,
+    pub bell: Bell,
+    pub wrap_around: bool,
+    pub verify_writes: bool,
+    pub confirm_quit: bool,
+    pub prefetch_on_connect: bool,
+    pub read_on_tab_switch: bool,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            display_mode: DisplayMode::default(),
+            word_order: WordOrder::default(),
+            theme: Theme::default(),
+            bell: Bell::default(),
+            wrap_around: true,
+            verify_writes: false,
+            confirm_quit: true,
+            prefetch_on_connect: false,
+            read_on_tab_switch: false,
+        }
+    }
+}
+
+impl Settings {
+    // $XDG_CONFIG_HOME/magic_modbus/settings, next to the recent connections
+    pub fn default_path() -> Option<PathBuf> {
+        Some(config_dir()?.join("settings"))
+    }
+
+    // A missing or unreadable file gives the defaults, so a bad config never stops the app
+    pub async fn from_file(path: &Path) -> Self {
+        match fs::read_to_string(path).await {
+            Ok(file) => Self::parse(&file),
+            Err(_) => Self::default(),
+        }
+    }
+
+    // Line format: key = value
+    // Unknown keys and values that don't parse keep their defaults
+    pub fn parse(text: &str) -> Self {
+        let mut settings = Self::default();
+        for line in text.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let Some((key, value)) = line.split_once('=') else {
+                continue;
+            };
+            let value = value.trim();
+            match key.trim() {
+                "display_mode" => parse_into(&mut settings.display_mode, value),
+                "word_order" => parse_into(&mut settings.word_order, value),
+                "theme" => parse_into(&mut settings.theme, value),
+                "bell" => parse_into(&mut settings.bell, value),
+                "wrap_around" => parse_bool_into(&mut settings.wrap_around, value),
+                "verify_writes" => parse_bool_into(&mut settings.verify_writes, value),
+                "confirm_quit" => parse_bool_into(&mut settings.confirm_quit, value),
+                "prefetch_on_connect" => parse_bool_into(&mut settings.prefetch_on_connect, value),
+                "read_on_tab_switch" => parse_bool_into(&mut settings.read_on_tab_switch, value),
+                _ => {}
+            }
+        }
+        settings
+    }
+
+    // Takes one setting from `from`, leaving the rest as they were
+    pub fn copy_setting(&mut self, setting: Setting, from: &Settings) {
+        match setting {
+            Setting::DisplayMode => self.display_mode = from.display_mode,
+            Setting::WordOrder => self.word_order = from.word_order,
+            Setting::Theme => self.theme = from.theme,
+            Setting::Bell => self.bell = from.bell,
+            Setting::WrapAround => self.wrap_around = from.wrap_around,
+            Setting::VerifyWrites => self.verify_writes = from.verify_writes,
+            Setting::ConfirmQuit => self.confirm_quit = from.confirm_quit,
+            Setting::PrefetchOnConnect => self.prefetch_on_connect = from.prefetch_on_connect,
+            Setting::ReadOnTabSwitch => self.read_on_tab_switch = from.read_on_tab_switch,
+        }
+    }
+
+    pub async fn save(&self, path: &Path) -> std::io::Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).await?;
+        }
+
+        let contents = format!(
+            "# Magic Modbus settings, rewritten whenever one changes in the settings popup\n\
+             display_mode = {}\n\
+             word_order = {}\n\
+             theme = {}\n\
+             bell = {}\n\
+             wrap_around = {}\n\
+             verify_writes = {}\n\
+             confirm_quit = {}\n\
+             prefetch_on_connect = {}\n\
+             read_on_tab_switch = {}\n",
+            value_name(self.display_mode),
+            value_name(self.word_order),
+            value_name(self.theme),
+            value_name(self.bell),
+            self.wrap_around,
+            self.verify_writes,
+            self.confirm_quit,
+            self.prefetch_on_connect,
+            self.read_on_tab_switch,
+        );
+        fs::write(path, contents).await
+    }
+}
+
+// Same names as the command line flags take
+fn value_name<T: ValueEnum>(value: T) -> String {
+    value
+        .to_possible_value()
+        .map(|value| value.get_name().to_owned())
+        .unwrap_or_default()
+}
+
+fn parse_into<T: ValueEnum>(setting: &mut T, value: &str) {
+    if let Ok(parsed) = T::from_str(value, true) {
+        *setting = parsed;
+    }
+}
+
+fn parse_bool_into(setting: &mut bool, value: &str) {
+    if let Ok(parsed) = value.parse::<bool>() {
+        *setting = parsed;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn malformed_lines_keep_the_defaults() {
+        let settings = Settings::parse(
+            "theme = sky\n\
+             bell = loud\n\
+             wrap_around = maybe\n\
+             confirm_quit = false\n\
+             no equals sign\n\
+             unknown = 1\n",
+        );
+
+        assert!(matches!(settings.theme, Theme::Sky));
+        assert!(matches!(settings.bell, Bell::Audible));
+        assert!(settings.wrap_around);
+        assert!(!settings.confirm_quit);
+        assert_eq!(settings.display_mode, DisplayMode::Unsigned);
+    }
+
+    #[tokio::test]
+    async fn save_and_reload_keeps_every_setting() {
+        let path = std::env::temp_dir()
+            .join(format!("settings_{}", std::process::id()))
+            .join("settings");
+
+        assert!(Settings::from_file(&path).await.wrap_around);
+        let settings = Settings {
+            display_mode: DisplayMode::Binary,
+            word_order: WordOrder::Cdab,
+            theme: Theme::Amber,
+            bell: Bell::Visual,
+            wrap_around: false,
+            verify_writes: true,
+            confirm_quit: false,
+            prefetch_on_connect: true,
+            read_on_tab_switch: true,
+        };
+        settings.save(&path).await.unwrap();
+
+        let reloaded = Settings::from_file(&path).await;
+        let _ = std::fs::remove_dir_all(path.parent().unwrap());
+        assert_eq!(reloaded.display_mode, DisplayMode::Binary);
+        assert_eq!(reloaded.word_order, WordOrder::Cdab);
+        assert!(matches!(reloaded.theme, Theme::Amber));
+        assert!(matches!(reloaded.bell, Bell::Visual));
+        assert!(!reloaded.wrap_around);
+        assert!(reloaded.verify_writes);
+        assert!(!reloaded.confirm_quit);
+        assert!(reloaded.prefetch_on_connect);
+        assert!(reloaded.read_on_tab_switch);
+    }
+}
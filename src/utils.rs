@@ -13,12 +13,53 @@
 //!    limitations under the License.
 
 use crate::enums::{CellType, SelectedTopTab};
-use ratatui::layout::{Constraint, Layout, Rect};
-use std::io::Cursor;
-use tokio::io::AsyncReadExt;
+use ratatui::{
+    layout::{Constraint, Layout, Rect},
+    style::{Modifier, Style},
+    text::{Line, Span},
+};
+use std::{
+    collections::BTreeMap,
+    io::Cursor,
+    net::SocketAddr,
+    path::{Path, PathBuf},
+};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpStream, lookup_host},
+};
+use tokio_modbus::{
+    ExceptionCode,
+    client::{Context, Reader},
+};
 
 pub type ModbusReadCommand = (SelectedTopTab, u16, u16); // Table, Starting Address, Address Count
 pub type ModbusWriteCommand = (SelectedTopTab, u16, CellType); // Table, Table Address, Content
+pub type ModbusMaskWriteCommand = (u16, u16, u16, u16); // Address, AND Mask, OR Mask, Full Value
+pub type ModbusReadWriteCommand = (u16, Vec<u16>); // Starting Address, Values
+pub type ModbusReadResult = (
+    Vec<ModbusWriteCommand>, // Values read
+    Vec<String>,             // Exception messages
+    Vec<ModbusReadCommand>,  // Ranges rejected as illegal data addresses
+);
+
+// Per-request quantity limits from the Modbus spec
+pub const MAX_READ_BITS: u16 = 2000;
+pub const MAX_READ_WORDS: u16 = 125;
+pub const MAX_READ_WRITE_WORDS: u16 = 121; // Write side limit of 0x17
+pub const MAX_PDU_BYTES: usize = 253; // Function code and up to 252 bytes of data
+pub const DEVICE_ID_FUNCTION: u8 = 0x2B; // Encapsulated interface transport
+pub const DEVICE_ID_MEI_TYPE: u8 = 0x0E; // Read Device Identification
+pub const BROADCAST_UNIT_ID: u8 = 0; // Every slave acts on it and none of them replies
+
+// $XDG_CONFIG_HOME/magic_modbus, falling back to ~/.config or %APPDATA%
+pub fn config_dir() -> Option<PathBuf> {
+    let config_dir = std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".config")))
+        .or_else(|| std::env::var_os("APPDATA").map(PathBuf::from))?;
+    Some(config_dir.join("magic_modbus"))
+}
 
 pub fn centered_rect(length_x: u16, length_y: u16, rect: Rect) -> Rect {
     let vertical = Layout::vertical([
@@ -52,6 +93,394 @@ pub fn trim_borders(rect: Rect) -> Rect {
     .split(vertical)[1]
 }
 
+// Case-insensitive, returns None when the filter isn't found anywhere in the line
+pub fn highlight_matches(line: &Line, filter: &str, highlight: Style) -> Option<Line<'static>> {
+    let filter = filter.to_ascii_lowercase();
+    let mut found = false;
+    let mut spans = vec![];
+
+    for span in &line.spans {
+        let content = span.content.as_ref();
+        let lowered = content.to_ascii_lowercase();
+        let mut last = 0;
+        for (start, _) in lowered.match_indices(&filter) {
+            if start < last {
+                continue;
+            }
+            found = true;
+            let end = start + filter.len();
+            spans.push(Span::styled(content[last..start].to_string(), span.style));
+            spans.push(Span::styled(
+                content[start..end].to_string(),
+                span.style.patch(highlight),
+            ));
+            last = end;
+        }
+        spans.push(Span::styled(content[last..].to_string(), span.style));
+    }
+
+    found.then(|| Line::from(spans).style(line.style))
+}
+
+// Text before, under and after a cursor, with a blank cell under it when it is past the end
+pub fn cursor_spans(
+    input: &str,
+    cursor: usize,
+    style: Style,
+    cursor_style: Style,
+) -> Vec<Span<'_>> {
+    let split = input
+        .char_indices()
+        .nth(cursor)
+        .map_or(input.len(), |(index, _)| index);
+    let (before, rest) = input.split_at(split);
+    let mut after = rest.chars();
+    let under = after.next().map_or(String::from(" "), String::from);
+    vec![
+        Span::styled(before, style),
+        Span::styled(under, cursor_style),
+        Span::styled(after.as_str(), style),
+    ]
+}
+
+// An input box of fixed width with the cursor reversed, one cell wider than max_len so the
+// cursor still fits after the last character
+pub fn input_line(input: &str, cursor: usize, max_len: usize, style: Style) -> Line<'_> {
+    let mut spans = cursor_spans(input, cursor, style, style.add_modifier(Modifier::REVERSED));
+    let used = input.chars().count().max(cursor + 1);
+    spans.push(Span::styled(
+        " ".repeat((max_len + 1).saturating_sub(used)),
+        style,
+    ));
+    Line::from(spans)
+}
+
+// Splits a read into (start, count) requests that respect the spec's quantity limits
+pub fn read_chunks(table: SelectedTopTab, start: u16, count: u16) -> Vec<(u16, u16)> {
+    let max_count = match table {
+        SelectedTopTab::Coils | SelectedTopTab::DiscreteInputs => MAX_READ_BITS,
+        SelectedTopTab::InputRegisters | SelectedTopTab::HoldingRegisters => MAX_READ_WORDS,
+    } as u32;
+    let end = u32::min(start as u32 + count as u32, u16::MAX as u32 + 1);
+
+    (start as u32..end)
+        .step_by(max_count as usize)
+        .map(|chunk_start| {
+            let chunk_count = u32::min(max_count, end - chunk_start);
+            (chunk_start as u16, chunk_count as u16)
+        })
+        .collect()
+}
+
+// Reads every range in spec-sized chunks, returning the values read, one message per
+// Modbus exception and the ranges the device rejected as illegal data addresses. A
+// rejected chunk is split in half until only the rejected addresses are left, so sparse
+// maps still fill in around the gaps. Only transport failures abort the whole read
+pub async fn read_tables(
+    ctx: &mut Context,
+    commands: Vec<ModbusReadCommand>,
+) -> Result<ModbusReadResult, tokio_modbus::Error> {
+    let mut values = vec![];
+    let mut exceptions = vec![];
+    let mut rejected: Vec<ModbusReadCommand> = vec![];
+    for (table, start, count) in commands {
+        // Popped from the back, so the chunks are read in address order
+        let mut pending = read_chunks(table, start, count);
+        pending.reverse();
+        while let Some((start, count)) = pending.pop() {
+            let result = match table {
+                SelectedTopTab::Coils => ctx
+                    .read_coils(start, count)
+                    .await?
+                    .map(|coils| coils.into_iter().map(CellType::Coil).collect::<Vec<_>>()),
+                SelectedTopTab::DiscreteInputs => ctx
+                    .read_discrete_inputs(start, count)
+                    .await?
+                    .map(|coils| coils.into_iter().map(CellType::Coil).collect()),
+                SelectedTopTab::InputRegisters => ctx
+                    .read_input_registers(start, count)
+                    .await?
+                    .map(|words| words.into_iter().map(CellType::Word).collect()),
+                SelectedTopTab::HoldingRegisters => ctx
+                    .read_holding_registers(start, count)
+                    .await?
+                    .map(|words| words.into_iter().map(CellType::Word).collect()),
+            };
+            match result {
+                Ok(contents) => values.extend(
+                    contents
+                        .into_iter()
+                        .enumerate()
+                        .map(|(i, content)| (table, start + i as u16, content)),
+                ),
+                Err(ExceptionCode::IllegalDataAddress) if count > 1 => {
+                    let half = count / 2;
+                    pending.push((start + half, count - half));
+                    pending.push((start, half));
+                }
+                Err(ExceptionCode::IllegalDataAddress) => match rejected.last_mut() {
+                    Some((last_table, last_start, last_count))
+                        if *last_table == table
+                            && *last_start as u32 + *last_count as u32 == start as u32 =>
+                    {
+                        *last_count += 1
+                    }
+                    _ => rejected.push((table, start, 1)),
+                },
+                Err(exception) => exceptions.push(format!("{table}: {exception}")),
+            }
+        }
+    }
+    Ok((values, exceptions, rejected))
+}
+
+// Reads back the final value written to every address in `commands`
+// Returns (written command, value the device reported) pairs
+pub async fn read_back(
+    ctx: &mut Context,
+    commands: &[ModbusWriteCommand],
+) -> tokio_modbus::Result<Vec<(ModbusWriteCommand, CellType)>> {
+    let mut final_values = BTreeMap::new();
+    for &(table, address, content) in commands {
+        final_values.insert((table, address), content);
+    }
+
+    let mut results = Vec::with_capacity(final_values.len());
+    for ((table, address), written) in final_values {
+        let read = match table {
+            SelectedTopTab::Coils => match ctx.read_coils(address, 1).await? {
+                Ok(coils) => CellType::Coil(coils[0]),
+                Err(exception) => return Ok(Err(exception)),
+            },
+            SelectedTopTab::HoldingRegisters => {
+                match ctx.read_holding_registers(address, 1).await? {
+                    Ok(words) => CellType::Word(words[0]),
+                    Err(exception) => return Ok(Err(exception)),
+                }
+            }
+            _ => continue,
+        };
+        results.push(((table, address, written), read));
+    }
+
+    Ok(Ok(results))
+}
+
+// Modbus TCP frame writing one coil or holding register to every slave, None for input tables
+pub fn broadcast_frame(transaction: u16, command: &ModbusWriteCommand) -> Option<Vec<u8>> {
+    let (function, address, value) = match *command {
+        (SelectedTopTab::Coils, address, CellType::Coil(on)) => {
+            let value = match on {
+                true => 0xFF00,
+                false => 0x0000,
+            };
+            (0x05, address, value)
+        }
+        (SelectedTopTab::HoldingRegisters, address, CellType::Word(word)) => (0x06, address, word),
+        _ => return None,
+    };
+    let mut frame = transaction.to_be_bytes().to_vec();
+    // Protocol 0, then the 6 bytes that follow: unit, function, address and value
+    frame.extend_from_slice(&[0x00, 0x00, 0x00, 0x06, BROADCAST_UNIT_ID, function]);
+    frame.extend_from_slice(&address.to_be_bytes());
+    frame.extend_from_slice(&value.to_be_bytes());
+    Some(frame)
+}
+
+// tokio-modbus always waits for a reply, which never comes for unit 0, so broadcasts go out
+// on a plain socket. Returns false for commands that aren't writes
+pub async fn send_broadcast(
+    stream: &mut TcpStream,
+    transaction: &mut u16,
+    command: &ModbusWriteCommand,
+) -> std::io::Result<bool> {
+    let Some(frame) = broadcast_frame(*transaction, command) else {
+        return Ok(false);
+    };
+    *transaction = transaction.wrapping_add(1);
+    stream.write_all(&frame).await?;
+    Ok(true)
+}
+
+// Parses a 1-based "address" or "start-end" field into a 0-based inclusive range
+pub fn parse_address_range(field: &str) -> Option<(u16, u16)> {
+    let (start, end) = match field.split_once('-') {
+        Some((start, end)) => (start.trim(), end.trim()),
+        None => (field, field),
+    };
+    // 1-based, so the last address is 65536
+    let start = u16::try_from(start.parse::<u32>().ok()?.checked_sub(1)?).ok()?;
+    let end = u16::try_from(end.parse::<u32>().ok()?.checked_sub(1)?).ok()?;
+    (start <= end).then_some((start, end))
+}
+
+// Parses a 1-based "start,count" into a read that stays within the table
+pub fn parse_read_range(text: &str) -> Option<(u16, u16)> {
+    let (start, count) = text.split_once(',')?;
+    let start = u16::try_from(start.trim().parse::<u32>().ok()?.checked_sub(1)?).ok()?;
+    let count = count.trim().parse::<u16>().ok()?;
+    (count > 0 && start as u32 + count as u32 <= u16::MAX as u32 + 1).then_some((start, count))
+}
+
+// Accepts decimal ("1234") or hex ("0x04D2") input
+pub fn parse_u16_value(text: &str) -> Option<u16> {
+    let text = text.trim();
+    match text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+        Some(hex) => u16::from_str_radix(hex, 16).ok(),
+        None => text.parse::<u16>().ok(),
+    }
+}
+
+// A 32-bit integer for a register pair, negative values are stored as two's complement
+pub fn parse_wide_value(text: &str) -> Option<u32> {
+    match text.starts_with('-') {
+        true => text.parse::<i32>().ok().map(|value| value as u32),
+        false => text.parse::<u32>().ok(),
+    }
+}
+
+// A 1-based address, optionally preceded by a table's reference digit as a sixth digit,
+// so "400040" is holding register 40 and "40" stays in the current table
+pub fn parse_goto_address(text: &str) -> Option<(Option<SelectedTopTab>, u16)> {
+    if text.is_empty() || !text.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    let (table, address) = match text.len() {
+        6 => (
+            Some(SelectedTopTab::from_reference_digit(
+                text[..1].parse().ok()?,
+            )?),
+            &text[1..],
+        ),
+        len if len < 6 => (None, text),
+        _ => return None,
+    };
+    let address = u16::try_from(address.parse::<u32>().ok()?.checked_sub(1)?).ok()?;
+    Some((table, address))
+}
+
+// Hex bytes, optionally space separated, with the function code first. Codes from 0x80
+// up are exception responses, so they can't be sent
+pub fn parse_raw_pdu(text: &str) -> Option<(u8, Vec<u8>)> {
+    let digits: String = text.split_whitespace().collect();
+    if digits.is_empty()
+        || digits.len() % 2 != 0
+        || digits.len() > MAX_PDU_BYTES * 2
+        || !digits.chars().all(|c| c.is_ascii_hexdigit())
+    {
+        return None;
+    }
+    let bytes: Vec<u8> = (0..digits.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&digits[i..i + 2], 16))
+        .collect::<Result<_, _>>()
+        .ok()?;
+    match bytes.split_first() {
+        Some((&function, data)) if (0x01..0x80).contains(&function) => {
+            Some((function, data.to_vec()))
+        }
+        _ => None,
+    }
+}
+
+pub type DeviceObject = (u8, String); // Object ID, Value
+
+// A Read Device Identification reply after the function code, returning its objects and
+// the object to ask for next when more follow than fit in one response
+pub fn parse_device_identification(data: &[u8]) -> Option<(Vec<DeviceObject>, Option<u8>)> {
+    // MEI type, read code, conformity level, more follows, next object ID, object count
+    let (header, mut objects_data) = data.split_first_chunk::<6>()?;
+    let [mei_type, _, _, more_follows, next_object, count] = *header;
+    if mei_type != DEVICE_ID_MEI_TYPE {
+        return None;
+    }
+
+    let mut objects = vec![];
+    for _ in 0..count {
+        let (&[id, length], rest) = objects_data.split_first_chunk::<2>()?;
+        let (value, rest) = rest.split_at_checked(length as usize)?;
+        objects.push((id, String::from_utf8_lossy(value).into_owned()));
+        objects_data = rest;
+    }
+    let next_object = (more_follows == 0xFF).then_some(next_object);
+    Some((objects, next_object))
+}
+
+pub fn device_object_name(id: u8) -> String {
+    match id {
+        0x00 => String::from("Vendor Name"),
+        0x01 => String::from("Product Code"),
+        0x02 => String::from("Revision"),
+        0x03 => String::from("Vendor URL"),
+        0x04 => String::from("Product Name"),
+        0x05 => String::from("Model Name"),
+        0x06 => String::from("User Application Name"),
+        _ => format!("Object 0x{id:02X}"),
+    }
+}
+
+pub fn bytes_to_hex(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .map(|byte| format!("{byte:02X}"))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+// Two characters per register, high byte first; bytes that aren't printable ASCII show as '.'
+pub fn registers_to_ascii(words: &[u16]) -> String {
+    words
+        .iter()
+        .flat_map(|word| word.to_be_bytes())
+        .map(|byte| match byte {
+            0x20..=0x7E => byte as char,
+            _ => '.',
+        })
+        .collect()
+}
+
+// Packs text into `count` registers, high byte first, padding the rest with NUL bytes.
+// An odd length leaves a NUL in the low byte of the last character's register.
+pub fn ascii_to_registers(text: &str, count: usize) -> Option<Vec<u16>> {
+    if !text.is_ascii() || text.len() > count * 2 {
+        return None;
+    }
+    let mut bytes = text.as_bytes().to_vec();
+    bytes.resize(count * 2, 0);
+    Some(
+        bytes
+            .chunks(2)
+            .map(|pair| u16::from_be_bytes([pair[0], pair[1]]))
+            .collect(),
+    )
+}
+
+// Literal addresses skip the lookup. Hostnames prefer IPv4 since most devices and gateways,
+// and `--serve` by default, only listen on it, while `localhost` often lists ::1 first
+pub async fn resolve_host(host: &str, port: u16) -> std::io::Result<SocketAddr> {
+    let addrs = lookup_host((host, port))
+        .await?
+        .collect::<Vec<SocketAddr>>();
+    addrs
+        .iter()
+        .find(|addr| addr.is_ipv4())
+        .or(addrs.first())
+        .copied()
+        .ok_or_else(|| {
+            std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                format!("no addresses found for {host}"),
+            )
+        })
+}
+
+pub fn invalid_line(index: usize, message: &str) -> std::io::Error {
+    std::io::Error::new(
+        std::io::ErrorKind::InvalidData,
+        format!("Line {}: {message}", index + 1),
+    )
+}
+
 pub struct BufReader<'a> {
     cursor: Cursor<&'a [u8]>,
 }
@@ -81,3 +510,267 @@ impl<'a> BufReader<'a> {
         Ok(buf)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::server::MockServer;
+    use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+    use tokio_modbus::client::tcp;
+
+    async fn connect(holding_register_count: usize) -> Context {
+        let server = MockServer::bind(SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 0))
+            .await
+            .unwrap();
+        let addr = server.local_addr().unwrap();
+        server
+            .registers()
+            .lock()
+            .unwrap()
+            .holding_registers
+            .truncate(holding_register_count);
+        tokio::spawn(server.run());
+        tcp::connect(addr).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn holding_register_exception_keeps_connection() {
+        let server = MockServer::bind(SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 0))
+            .await
+            .unwrap();
+        let addr = server.local_addr().unwrap();
+        let registers = server.registers();
+        registers.lock().unwrap().faulted = true;
+        tokio::spawn(server.run());
+        let mut ctx = tcp::connect(addr).await.unwrap();
+
+        let (values, exceptions, rejected) =
+            read_tables(&mut ctx, vec![(SelectedTopTab::HoldingRegisters, 0, 2)])
+                .await
+                .unwrap();
+        assert!(values.is_empty());
+        assert_eq!(exceptions.len(), 1);
+        assert!(exceptions[0].starts_with("Holding Registers"));
+        assert!(rejected.is_empty());
+
+        registers.lock().unwrap().faulted = false;
+        let (values, exceptions, _) =
+            read_tables(&mut ctx, vec![(SelectedTopTab::HoldingRegisters, 0, 3)])
+                .await
+                .unwrap();
+        assert_eq!(values.len(), 3);
+        assert!(exceptions.is_empty());
+    }
+
+    #[tokio::test]
+    async fn illegal_data_address_is_rejected_not_reported() {
+        let mut ctx = connect(100).await;
+
+        // Reading past the last register the device has is an illegal data address
+        let (values, exceptions, rejected) =
+            read_tables(&mut ctx, vec![(SelectedTopTab::HoldingRegisters, 100, 2)])
+                .await
+                .unwrap();
+        assert!(values.is_empty());
+        assert!(exceptions.is_empty());
+        assert_eq!(rejected, vec![(SelectedTopTab::HoldingRegisters, 100, 2)]);
+
+        let (values, exceptions, rejected) =
+            read_tables(&mut ctx, vec![(SelectedTopTab::HoldingRegisters, 0, 3)])
+                .await
+                .unwrap();
+        assert_eq!(values.len(), 3);
+        assert!(exceptions.is_empty());
+        assert!(rejected.is_empty());
+    }
+
+    #[tokio::test]
+    async fn rejected_range_is_split_around_valid_addresses() {
+        let mut ctx = connect(100).await;
+
+        let (values, exceptions, rejected) =
+            read_tables(&mut ctx, vec![(SelectedTopTab::HoldingRegisters, 93, 10)])
+                .await
+                .unwrap();
+
+        assert!(exceptions.is_empty());
+        assert_eq!(
+            values
+                .iter()
+                .map(|(_, address, _)| *address)
+                .collect::<Vec<_>>(),
+            (93..100).collect::<Vec<_>>()
+        );
+        assert_eq!(rejected, vec![(SelectedTopTab::HoldingRegisters, 100, 3)]);
+    }
+
+    #[tokio::test]
+    async fn oversized_coil_read_is_split() {
+        let server = MockServer::bind(SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 0))
+            .await
+            .unwrap();
+        let addr = server.local_addr().unwrap();
+        let registers = server.registers();
+        registers.lock().unwrap().coils[2999] = true;
+        tokio::spawn(server.run());
+        let mut ctx = tcp::connect(addr).await.unwrap();
+
+        let (values, exceptions, _) = read_tables(&mut ctx, vec![(SelectedTopTab::Coils, 0, 3000)])
+            .await
+            .unwrap();
+
+        assert!(exceptions.is_empty());
+        assert_eq!(values.len(), 3000);
+        assert_eq!(
+            values[2999],
+            (SelectedTopTab::Coils, 2999, CellType::Coil(true))
+        );
+        assert_eq!(registers.lock().unwrap().reads, 2);
+    }
+
+    #[test]
+    fn broadcast_frames_address_unit_zero() {
+        assert_eq!(
+            broadcast_frame(7, &(SelectedTopTab::Coils, 1, CellType::Coil(true))),
+            Some(vec![0, 7, 0, 0, 0, 6, 0, 0x05, 0, 1, 0xFF, 0x00])
+        );
+        assert_eq!(
+            broadcast_frame(
+                0x0102,
+                &(SelectedTopTab::HoldingRegisters, 39, CellType::Word(0xBEEF))
+            ),
+            Some(vec![1, 2, 0, 0, 0, 6, 0, 0x06, 0, 39, 0xBE, 0xEF])
+        );
+        assert_eq!(
+            broadcast_frame(0, &(SelectedTopTab::InputRegisters, 0, CellType::Word(1))),
+            None
+        );
+    }
+
+    #[test]
+    fn goto_address_takes_a_table_prefix() {
+        assert_eq!(parse_goto_address("40"), Some((None, 39)));
+        assert_eq!(parse_goto_address("65536"), Some((None, 65535)));
+        assert_eq!(
+            parse_goto_address("400040"),
+            Some((Some(SelectedTopTab::HoldingRegisters), 39))
+        );
+        assert_eq!(
+            parse_goto_address("000001"),
+            Some((Some(SelectedTopTab::Coils), 0))
+        );
+        assert_eq!(parse_goto_address("200001"), None);
+        assert_eq!(parse_goto_address("365537"), None);
+        assert_eq!(parse_goto_address("0"), None);
+        assert_eq!(parse_goto_address(""), None);
+    }
+
+    #[test]
+    fn read_range_stays_within_the_table() {
+        assert_eq!(parse_read_range("100, 50"), Some((99, 50)));
+        assert_eq!(parse_read_range("65536,1"), Some((65535, 1)));
+        assert_eq!(parse_read_range("65536,2"), None);
+        assert_eq!(parse_read_range("0,10"), None);
+        assert_eq!(parse_read_range("1,0"), None);
+        assert_eq!(parse_read_range("100"), None);
+    }
+
+    #[test]
+    fn wide_value_takes_either_sign() {
+        assert_eq!(parse_wide_value("4294967295"), Some(u32::MAX));
+        assert_eq!(parse_wide_value("-1"), Some(u32::MAX));
+        assert_eq!(parse_wide_value("-2147483648"), Some(0x8000_0000));
+        assert_eq!(parse_wide_value("70000"), Some(70000));
+        assert_eq!(parse_wide_value("4294967296"), None);
+        assert_eq!(parse_wide_value("-2147483649"), None);
+        assert_eq!(parse_wide_value(""), None);
+    }
+
+    #[test]
+    fn raw_pdu_is_validated() {
+        assert_eq!(parse_raw_pdu("41 01 0a"), Some((0x41, vec![0x01, 0x0A])));
+        assert_eq!(parse_raw_pdu("4101FF"), Some((0x41, vec![0x01, 0xFF])));
+        assert_eq!(parse_raw_pdu("64"), Some((0x64, vec![])));
+        assert_eq!(parse_raw_pdu(""), None);
+        assert_eq!(parse_raw_pdu("41 1"), None);
+        assert_eq!(parse_raw_pdu("41 0G"), None);
+        assert_eq!(parse_raw_pdu("00 01"), None);
+        assert_eq!(parse_raw_pdu("83 02"), None);
+        assert_eq!(parse_raw_pdu("41 é"), None);
+        assert_eq!(parse_raw_pdu(&"41".repeat(MAX_PDU_BYTES + 1)), None);
+
+        assert_eq!(bytes_to_hex(&[0x41, 0x01, 0x0A]), "41 01 0A");
+    }
+
+    #[test]
+    fn device_identification_objects_are_read() {
+        let reply = [
+            0x0E, 0x01, 0x01, 0xFF, 0x02, 0x02, // Two objects, more from object 2
+            0x00, 0x04, b'A', b'c', b'm', b'e', // Vendor Name
+            0x01, 0x02, b'P', b'1', // Product Code
+        ];
+        let (objects, next_object) = parse_device_identification(&reply).unwrap();
+        assert_eq!(
+            objects,
+            vec![(0x00, String::from("Acme")), (0x01, String::from("P1"))]
+        );
+        assert_eq!(next_object, Some(0x02));
+
+        // Last response, so the next object ID is ignored
+        let reply = [0x0E, 0x01, 0x01, 0x00, 0x00, 0x01, 0x02, 0x01, b'1'];
+        assert_eq!(
+            parse_device_identification(&reply),
+            Some((vec![(0x02, String::from("1"))], None))
+        );
+
+        // An object running past the end of the reply
+        assert_eq!(
+            parse_device_identification(&[0x0E, 0x01, 0x01, 0x00, 0x00, 0x01, 0x00, 0x05, b'A']),
+            None
+        );
+        assert_eq!(parse_device_identification(&[0x0D, 0x01]), None);
+        assert_eq!(device_object_name(0x80), "Object 0x80");
+    }
+
+    #[test]
+    fn ascii_packs_high_byte_first() {
+        let words = ascii_to_registers("ABC", 3).unwrap();
+        assert_eq!(words, vec![0x4142, 0x4300, 0x0000]);
+        assert_eq!(registers_to_ascii(&words), "ABC...");
+        assert_eq!(registers_to_ascii(&[0x7F41, 0x0D0A]), ".A..");
+
+        assert_eq!(ascii_to_registers("ABCDE", 2), None);
+        assert_eq!(ascii_to_registers("°C", 4), None);
+    }
+
+    #[test]
+    fn highlight_matches_is_case_insensitive() {
+        let line = Line::from(vec![
+            Span::styled("G", Style::new().bold()),
+            Span::raw(" - Go to address"),
+        ]);
+
+        let highlighted = highlight_matches(&line, "go", Style::new().reversed()).unwrap();
+        let contents: Vec<&str> = highlighted
+            .spans
+            .iter()
+            .map(|span| span.content.as_ref())
+            .collect();
+
+        assert_eq!(contents, ["G", " - ", "Go", " to address"]);
+        assert_eq!(highlighted.spans[2].style, Style::new().reversed());
+        assert!(highlight_matches(&line, "coil", Style::new().reversed()).is_none());
+    }
+
+    #[tokio::test]
+    async fn literal_addresses_resolve_to_themselves() {
+        assert_eq!(
+            resolve_host("10.0.0.1", 502).await.unwrap(),
+            SocketAddr::new(IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1)), 502)
+        );
+        assert_eq!(
+            resolve_host("::1", 1502).await.unwrap(),
+            SocketAddr::new(IpAddr::V6(Ipv6Addr::LOCALHOST), 1502)
+        );
+    }
+}